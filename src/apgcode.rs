@@ -0,0 +1,219 @@
+use bevy::prelude::Entity;
+use bevy::utils::HashSet;
+use crate::components::TileMap;
+use crate::grid::GridType;
+use crate::rules::Rule;
+use crate::systems;
+use crate::uVec3::uVec3;
+use crate::settings::update_count_limit;
+
+// apgcodes de Catagolue : xs4_33 (bloc), xp2_7 (clignotant), xq4_153 (planeur).
+// Préfixe : xs + population pour un objet stable, xp + période pour un oscillateur, xq + période pour un vaisseau.
+// Corps en Wechsler étendu : bandes de 5 lignes séparées par z, une colonne par caractère (0-9, a-v :
+// bit 0 = ligne du haut), suites de colonnes vides abrégées (w = 2, x = 3, y0 .. yz = 4 à 39).
+// Le code canonique est le plus court (puis le premier dans l'ordre ASCII) parmi les 8 symétries du carré
+// et, pour les oscillateurs et vaisseaux, toutes les phases.
+
+pub const MAX_PERIOD: u32 = 1024;
+pub const MAX_OBJECT_SIZE: usize = 10000;
+
+const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+// corps du code, pour une orientation déjà fixée ; les lignes vont vers le bas
+fn wechsler(cells: &[(i32, i32)]) -> String{
+    let width = cells.iter().map(|(x, _)| x + 1).max().unwrap_or(0) as usize;
+    let height = cells.iter().map(|(_, y)| y + 1).max().unwrap_or(0) as usize;
+    let strips = (height + 4) / 5;
+    let mut columns: Vec<Vec<u8>> = vec![vec![0; width]; strips];
+    for (x, y) in cells{
+        columns[*y as usize / 5][*x as usize] |= 1 << (*y % 5);
+    }
+    let mut code = String::new();
+    for (i, strip) in columns.iter().enumerate(){
+        if i > 0 {
+            code.push('z');
+        }
+        let length = strip.iter().rposition(|c| *c != 0).map_or(0, |p| p + 1);
+        let mut zeros = 0;
+        for value in strip[..length].iter(){
+            if *value == 0 {
+                zeros += 1;
+                continue;
+            }
+            push_zeros(&mut code, zeros);
+            zeros = 0;
+            code.push(DIGITS[*value as usize] as char);
+        }
+    }
+    code
+}
+
+fn push_zeros(code: &mut String, mut zeros: usize){
+    while zeros > 0 {
+        match zeros {
+            1 => code.push('0'),
+            2 => code.push('w'),
+            3 => code.push('x'),
+            _ => {
+                let run = zeros.min(39);
+                code.push('y');
+                code.push(DIGITS[run - 4] as char);
+                zeros -= run;
+                continue;
+            }
+        }
+        return;
+    }
+}
+
+// les 8 symétries, chacune ramenée dans le quart de plan positif (y vers le bas)
+fn orientations(cells: &[uVec3]) -> Vec<Vec<(i32, i32)>>{
+    let mut result = Vec::new();
+    for transform in 0..8 {
+        let points: Vec<(i32, i32)> = cells.iter().map(|p| {
+            let (x, y) = (p.x, -p.y);
+            let (x, y) = if transform & 1 == 1 { (y, x) } else { (x, y) };
+            let x = if transform & 2 == 2 { -x } else { x };
+            let y = if transform & 4 == 4 { -y } else { y };
+            (x, y)
+        }).collect();
+        let minX = points.iter().map(|(x, _)| *x).min().unwrap_or(0);
+        let minY = points.iter().map(|(_, y)| *y).min().unwrap_or(0);
+        result.push(points.iter().map(|(x, y)| (x - minX, y - minY)).collect());
+    }
+    result
+}
+
+fn shortest(a: String, b: String) -> String{
+    if (b.len(), &b) < (a.len(), &a) { b } else { a }
+}
+
+// corps canonique d'un ensemble de cellules, sans préfixe
+pub fn encode(cells: &[uVec3]) -> String{
+    orientations(cells).into_iter().map(|points| wechsler(&points)).reduce(shortest).unwrap_or_default()
+}
+
+// xs, xp ou xq suivi d'un nombre et d'un corps, sans espace
+pub fn isApgcode(text: &str) -> bool{
+    let Some((prefix, body)) = text.split_once('_') else {
+        return false;
+    };
+    ["xs", "xp", "xq"].iter().any(|p| prefix.strip_prefix(p).map_or(false, |n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit())))
+        && !body.is_empty() && body.chars().all(|c| c.is_ascii_digit() || c.is_ascii_lowercase())
+}
+
+// cellules d'un apgcode (préfixe facultatif), le coin haut gauche en (0, 0), y vers le haut
+pub fn decode(code: &str) -> Result<Vec<uVec3>, String>{
+    let body = match code.split_once('_') {
+        Some((prefix, body)) if prefix.starts_with("xs") || prefix.starts_with("xp") || prefix.starts_with("xq") => body,
+        Some((prefix, _)) => return Err(format!("unsupported apgcode prefix '{}'", prefix)),
+        None => code,
+    };
+    let mut cells: Vec<uVec3> = Vec::new();
+    let (mut x, mut strip) = (0, 0);
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            'w' => x += 2,
+            'x' => x += 3,
+            'y' => {
+                let run = chars.next().and_then(|d| DIGITS.iter().position(|v| *v as char == d))
+                    .ok_or(format!("invalid apgcode '{}': y must be followed by 0-9 or a-z", code))?;
+                x += 4 + run as i32;
+            }
+            'z' => {
+                x = 0;
+                strip += 1;
+            }
+            _ => {
+                let value = DIGITS[..32].iter().position(|v| *v as char == c)
+                    .ok_or(format!("invalid apgcode '{}': unexpected '{}'", code, c))?;
+                for bit in 0..5 {
+                    if value >> bit & 1 == 1 {
+                        cells.push(uVec3::new(x, -(strip * 5 + bit), 0));
+                    }
+                }
+                x += 1;
+            }
+        }
+    }
+    Ok(cells)
+}
+
+// forme de l'objet (translatée en (0, 0)) et coin min
+fn normalize(cells: &[uVec3]) -> (Vec<(i32, i32)>, (i32, i32)){
+    let minX = cells.iter().map(|p| p.x).min().unwrap_or(0);
+    let minY = cells.iter().map(|p| p.y).min().unwrap_or(0);
+    let mut shape: Vec<(i32, i32)> = cells.iter().map(|p| (p.x - minX, p.y - minY)).collect();
+    shape.sort();
+    (shape, (minX, minY))
+}
+
+// apgcode complet d'un objet isolé : la règle est simulée jusqu'à ce que l'objet revienne à sa forme de départ
+pub fn identify(cells: &[uVec3], rule: &Rule) -> Result<String, String>{
+    if rule.states != 2 || rule.grid != GridType::Square || rule.turmite.is_some() || rule.wolfram.is_some() || rule.lenia.is_some() {
+        return Err(String::from("apgcodes need a two-state rule on the square grid"));
+    }
+    let unique: HashSet<uVec3> = cells.iter().map(|p| uVec3::new(p.x, p.y, 0)).collect();
+    let cells: Vec<uVec3> = unique.into_iter().collect();
+    if cells.is_empty() {
+        return Err(String::from("no cell to identify"));
+    }
+    if cells.len() > MAX_OBJECT_SIZE {
+        return Err(format!("object too large ({} cells, at most {})", cells.len(), MAX_OBJECT_SIZE));
+    }
+
+    let mut tileMap = TileMap::new(rule.clone());
+    for pos in cells.iter(){
+        tileMap.current_state.insert(*pos, (None, update_count_limit(), 1));
+    }
+    let (shape, corner) = normalize(&cells);
+    let mut phases: Vec<Vec<uVec3>> = vec![cells.clone()];
+    let mut inTileMapRemove: Vec<Entity> = Vec::new();
+    for period in 1..MAX_PERIOD + 1 {
+        systems::step_generation(&mut tileMap, &mut inTileMapRemove);
+        let current: Vec<uVec3> = systems::living_cells(&tileMap).into_iter().map(|(p, _)| p).collect();
+        if current.is_empty() {
+            return Err(format!("the object dies after {} generations", period));
+        }
+        if current.len() > MAX_OBJECT_SIZE {
+            return Err(String::from("the object keeps growing"));
+        }
+        let (currentShape, currentCorner) = normalize(&current);
+        if currentShape == shape {
+            let body = phases.iter().map(|phase| encode(phase)).reduce(shortest).unwrap_or_default();
+            return Ok(if period == 1 && currentCorner == corner {
+                format!("xs{}_{}", cells.len(), body)
+            } else if currentCorner == corner {
+                format!("xp{}_{}", period, body)
+            } else {
+                format!("xq{}_{}", period, body)
+            });
+        }
+        phases.push(current);
+    }
+    Err(format!("not periodic within {} generations", MAX_PERIOD))
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn encode_decode_round_trip(){
+        for code in ["xs4_33", "xp2_7", "xq4_153", "xs6_696", "xq4_6frc", "xs8_3pe0ep3", "xp3_co9nas0san9oczgoldlo0oldlogz1047210127401", "xp30_w33z8kqrqk8zzzx33", "xp15_4r4z4r4"]{
+            let cells = decode(code).unwrap();
+            let body = code.split_once('_').unwrap().1;
+            assert_eq!(encode(&cells), body, "{}", code);
+            assert_eq!(decode(body).unwrap().len(), cells.len());
+        }
+        // deux blocs éloignés : suite de colonnes vides en y
+        let blocks: Vec<uVec3> = [(0, 0), (1, 0), (0, -1), (1, -1), (12, 0), (13, 0), (12, -1), (13, -1)].into_iter().map(|(x, y)| uVec3::new(x, y, 0)).collect();
+        let body = encode(&blocks);
+        assert!(body.contains('y'));
+        assert_eq!(encode(&decode(&body).unwrap()), body);
+        assert!(isApgcode("xq4_153"));
+        assert!(!isApgcode("x = 3, y = 3"));
+        assert!(decode("xs4_3!").is_err());
+    }
+}
@@ -0,0 +1,140 @@
+use bevy::prelude::Resource;
+use crate::import::ImportSettings;
+use crate::rle;
+use crate::rules::Rule;
+use crate::topology::Topology;
+use crate::uVec3::uVec3;
+
+// arguments de la fenêtre principale ; "record" a ses propres options (record::USAGE)
+
+pub const USAGE: &str = "usage: GameOfLife2D [options]
+       GameOfLife2D record <pattern> <output.gif|output.png> [options]   (record without opening a window)
+
+options:
+  --pattern FILE            open a pattern (.rle, .cells, .mc or .png), centred on the origin
+  --load FILE               restore a universe saved with Ctrl+S or Ctrl+M, at its saved position
+  --rule RULE               rule to use instead of the file's (B36/S23, B2/S34H, R2,C0,M1,S2..3,B3..3,NM ...)
+  --topology TOPOLOGY       plane (default), bounded:WIDTHxHEIGHT or torus:WIDTHxHEIGHT, centred on the origin
+  --gens-per-second N       generations per second instead of one per frame
+  --paused                  do not start the simulation when a pattern is given
+  --window WIDTHxHEIGHT     window size, e.g. 1280x720
+  --vsync                   enable vertical sync
+  --set NAME=VALUE          override a setting of settings.ron
+  --remote PORT             accept JSON-lines commands on 127.0.0.1:PORT (load_pattern, set_cell, step, run ...)
+  -h, --help                print this help";
+
+#[derive(Resource)]
+pub struct Options{
+    pattern: Option<(String, bool)>,    // (fichier, à centrer), lu par load_pattern une fois settings.ron chargé
+    ruleString: Option<String>,
+    pub cells: Vec<(uVec3, u8)>,
+    pub rule: Option<Rule>,
+    pub topology: Topology,
+    pub generationsPerSecond: Option<f32>,
+    pub running: bool,
+    pub overrides: Vec<String>,     // réglages de settings.ron remplacés (--window, --vsync, --set)
+    pub remotePort: Option<u16>,
+}
+
+fn size(value: &str) -> Option<(u32, u32)>{
+    let (width, height) = value.split_once('x')?;
+    Some((width.trim().parse().ok()?, height.trim().parse().ok()?))
+}
+
+// None si l'aide a été demandée
+pub fn parse(args: &[String]) -> Result<Option<Options>, String>{
+    let mut pattern: Option<(String, bool)> = None;
+    let mut ruleString: Option<String> = None;
+    let mut topology = Topology::Plane;
+    let mut generationsPerSecond: Option<f32> = None;
+    let mut paused = false;
+    let mut overrides: Vec<String> = Vec::new();
+    let mut remotePort: Option<u16> = None;
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i].as_str();
+        match arg {
+            "-h" | "--help" => return Ok(None),
+            "--paused" => {
+                paused = true;
+                i += 1;
+                continue;
+            }
+            "--vsync" => {
+                overrides.push(String::from("vsync=true"));
+                i += 1;
+                continue;
+            }
+            "--pattern" | "--load" | "--rule" | "--topology" | "--gens-per-second" | "--window" | "--set" | "--remote" => {}
+            _ => return Err(format!("unknown option {}", arg)),
+        }
+        let value = args.get(i + 1).ok_or(format!("missing value for {}", arg))?;
+        match arg {
+            "--pattern" | "--load" => {
+                if pattern.is_some() {
+                    return Err(String::from("only one of --pattern and --load can be given"));
+                }
+                pattern = Some((value.clone(), arg == "--pattern"));
+            }
+            "--rule" => ruleString = Some(value.clone()),
+            "--topology" => topology = Topology::parse(value)?,
+            "--gens-per-second" => {
+                let rate = value.parse::<f32>().ok().filter(|r| *r > 0. && *r <= 10000.)
+                    .ok_or(format!("invalid value '{}' for {}: expected a number between 0 and 10000", value, arg))?;
+                generationsPerSecond = Some(rate);
+            }
+            "--window" => {
+                let (width, height) = size(value).ok_or(format!("invalid window size '{}': expected WIDTHxHEIGHT", value))?;
+                overrides.push(format!("windowWidth={}", width));
+                overrides.push(format!("windowHeight={}", height));
+            }
+            "--remote" => {
+                let port = value.parse::<u16>().ok().filter(|p| *p != 0).ok_or(format!("invalid port '{}' for {}", value, arg))?;
+                remotePort = Some(port);
+            }
+            _ => overrides.push(value.clone()),
+        }
+        i += 2;
+    }
+
+    Ok(Some(Options{
+        running: pattern.is_some() && !paused,
+        pattern,
+        ruleString,
+        cells: Vec::new(),
+        rule: None,
+        topology,
+        generationsPerSecond,
+        overrides,
+        remotePort,
+    }))
+}
+
+impl Options{
+    // lit le motif (image comprise, d'où les réglages d'import) et la règle
+    pub fn load_pattern(&mut self, image: &ImportSettings) -> Result<(), String>{
+        let pattern = match &self.pattern {
+            Some((path, centred)) => Some((rle::read_file(path, image)?, *centred)),
+            None => None,
+        };
+        // la règle de la ligne de commande l'emporte sur celle du fichier
+        self.rule = match self.ruleString.as_ref().or(pattern.as_ref().and_then(|(p, _)| p.rule.as_ref())) {
+            Some(rulestring) => Some(Rule::parse(rulestring)?),
+            None => None,
+        };
+        let states = self.rule.as_ref().map_or(2, |r| r.states);
+        if let Some((pattern, centred)) = &pattern {
+            let mut cells: Vec<(uVec3, u8)> = pattern.cells.iter().filter(|(_, s)| *s != 0 && *s < states).copied().collect();
+            if *centred && !cells.is_empty() {
+                let (minX, maxX) = (cells.iter().map(|(p, _)| p.x).min().unwrap(), cells.iter().map(|(p, _)| p.x).max().unwrap());
+                let (minY, maxY) = (cells.iter().map(|(p, _)| p.y).min().unwrap(), cells.iter().map(|(p, _)| p.y).max().unwrap());
+                let (dx, dy) = ((minX + maxX) / 2, (minY + maxY) / 2);
+                for (pos, _) in cells.iter_mut(){
+                    *pos = uVec3::new(pos.x - dx, pos.y - dy, pos.z);
+                }
+            }
+            self.cells = cells;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+use std::io::{Read, Write};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+use crossbeam_channel::Receiver;
+
+// presse-papiers du système sans dépendance : les outils habituels de chaque plateforme sont essayés dans l'ordre
+// (wl-copy / wl-paste sous Wayland, xclip ou xsel sous X11, pbcopy / pbpaste sous macOS, clip / PowerShell sous Windows).
+// Les outils tournent dans un thread, le résultat arrive par un canal lu à chaque frame ; un outil bloqué est tué après TIMEOUT.
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+fn copy_commands() -> Vec<(&'static str, Vec<&'static str>)>{
+    if cfg!(target_os = "macos") {
+        vec![("pbcopy", vec![])]
+    } else if cfg!(target_os = "windows") {
+        vec![("clip", vec![])]
+    } else {
+        vec![("wl-copy", vec![]), ("xclip", vec!["-selection", "clipboard"]), ("xsel", vec!["--clipboard", "--input"])]
+    }
+}
+
+fn paste_commands() -> Vec<(&'static str, Vec<&'static str>)>{
+    if cfg!(target_os = "macos") {
+        vec![("pbpaste", vec![])]
+    } else if cfg!(target_os = "windows") {
+        vec![("powershell", vec!["-NoProfile", "-Command", "Get-Clipboard"])]
+    } else {
+        vec![("wl-paste", vec!["--no-newline"]), ("xclip", vec!["-selection", "clipboard", "-o"]), ("xsel", vec!["--clipboard", "--output"])]
+    }
+}
+
+// fin de l'outil, ou false s'il a échoué ou dépassé TIMEOUT (il est alors tué)
+fn wait(child: &mut Child) -> bool{
+    let start = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return status.success(),
+            Ok(None) if start.elapsed() < TIMEOUT => std::thread::sleep(Duration::from_millis(10)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return false;
+            }
+        }
+    }
+}
+
+fn copy_now(text: &str) -> Result<(), String>{
+    for (program, args) in copy_commands(){
+        // xclip et xsel restent en arrière-plan pour garder la sélection : ne pas attendre leur sortie standard
+        let Ok(mut child) = Command::new(program).args(&args).stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::null()).spawn() else {
+            continue;
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            if stdin.write_all(text.as_bytes()).is_err() {
+                let _ = child.kill();
+                let _ = child.wait();
+                continue;
+            }
+        }
+        if wait(&mut child) {
+            return Ok(());
+        }
+    }
+    Err(String::from("no clipboard tool found (install wl-clipboard, xclip or xsel)"))
+}
+
+fn paste_now() -> Result<String, String>{
+    for (program, args) in paste_commands(){
+        let Ok(mut child) = Command::new(program).args(&args).stdout(Stdio::piped()).stderr(Stdio::null()).spawn() else {
+            continue;
+        };
+        // la sortie est lue à part pour qu'un texte plus grand que le tampon du tube ne bloque pas l'outil
+        let Some(mut stdout) = child.stdout.take() else {
+            continue;
+        };
+        let reader = std::thread::spawn(move || {
+            let mut bytes = Vec::new();
+            stdout.read_to_end(&mut bytes).map(|_| bytes)
+        });
+        // un outil tué peut laisser un processus fils qui garde le tube ouvert : le thread de lecture est alors abandonné
+        if !wait(&mut child) {
+            continue;
+        }
+        if let Ok(Ok(bytes)) = reader.join() {
+            return String::from_utf8(bytes).map_err(|_| String::from("the clipboard does not contain text"));
+        }
+    }
+    Err(String::from("no clipboard tool found (install wl-clipboard, xclip or xsel)"))
+}
+
+pub fn copy(text: String) -> Receiver<Result<(), String>>{
+    let (sender, reply) = crossbeam_channel::bounded(1);
+    std::thread::spawn(move || sender.send(copy_now(&text)));
+    reply
+}
+
+pub fn paste() -> Receiver<Result<String, String>>{
+    let (sender, reply) = crossbeam_channel::bounded(1);
+    std::thread::spawn(move || sender.send(paste_now()));
+    reply
+}
@@ -1,15 +1,140 @@
-use bevy::prelude::{Component, Entity};
-use bevy::utils::{HashMap};
+use bevy::asset::Handle;
+use bevy::math::{Vec2, Vec3};
+use bevy::prelude::{ColorMaterial, Component, Entity, Image, Mesh, StandardMaterial};
+use std::sync::Arc;
+use std::time::SystemTime;
+use bevy::utils::{HashMap, HashSet};
+use bevy::sprite::Mesh2dHandle;
+use crossbeam_channel::Receiver;
+use crate::grid;
+use crate::grid::GridType;
+use crate::keybindings::{Action, Binding};
+use crate::lenia::LeniaWorld;
+use crate::macrocell;
+use crate::objects::Object;
+use crate::palette;
+use crate::rules::Rule;
+use crate::topology::Topology;
 use crate::uVec3::uVec3;
 
 #[derive(Component)]
 pub struct MainCamera;
 
+// état de la caméra 2D : marque-pages (Ctrl+1..9 / Alt+1..9), objet suivi (T) et glisser au bouton du milieu
 #[derive(Component)]
+pub struct CameraControl{
+    pub bookmarks: [Option<(Vec3, f32)>; 9],    // (position, échelle de la projection)
+    pub follow: Option<(Vec2, f32)>,            // (centre de l'objet suivi, rayon de recherche)
+    pub lastCursor: Option<Vec2>,               // position du curseur pendant un glisser
+}
+
+#[derive(Component, Clone)]
 pub struct TileMap{
     pub running: bool,
-    pub current_state: HashMap<uVec3, (Option<Entity>, i32)>,   // map qui est mise à jour toute les frames
-    pub stable_current_state: HashMap<uVec3, (Option<Entity>, i32)>,  // map qui contiens les pixels n'ayant pas été mis à jour depuis plus de X itérations. ces pixels sont "réveillé" (transféré vers l'autre hashmap) quand il y a une mise à jour d'un pixel à proximité.
+    pub rule: Rule,
+    pub layer: i32,     // couche z éditée et affichée en 2D, toujours 0 hors Life 3D
+    pub brush: u8,      // état posé au clic gauche avec une table de règles ou une turmite (touches 1 à 9)
+    pub ants: Vec<Ant>, // turmites posées avec Shift + clic gauche
+    pub topology: Topology,     // plan infini, rectangle borné ou tore (--topology)
+    pub macrocell: Option<Arc<macrocell::Loaded>>,     // univers complet quand seule une fenêtre d'un fichier .mc est chargée
+    pub row1D: Option<i32>,     // règles 1D : ligne courante du diagramme espace-temps (oned.rs)
+    // (entité affichée, compteur de stabilité, état de la cellule : 1 = vivante, 2.. = mourante)
+    pub current_state: HashMap<uVec3, (Option<Entity>, i32, u8)>,   // map qui est mise à jour toute les frames
+    pub stable_current_state: HashMap<uVec3, (Option<Entity>, i32, u8)>,  // map qui contiens les pixels n'ayant pas été mis à jour depuis plus de X itérations. ces pixels sont "réveillé" (transféré vers l'autre hashmap) quand il y a une mise à jour d'un pixel à proximité.
+}
+
+impl TileMap{
+    pub fn new(rule: Rule) -> TileMap{
+        TileMap{
+            running: false,
+            rule,
+            layer: 0,
+            brush: 1,
+            ants: Vec::new(),
+            topology: Topology::Plane,
+            macrocell: None,
+            row1D: None,
+            current_state: HashMap::new(),
+            stable_current_state: HashMap::new(),
+        }
+    }
+}
+
+// turmite : case occupée, direction (0 haut, 1 droite, 2 bas, 3 gauche) et état interne
+#[derive(Clone, Copy, PartialEq)]
+pub struct Ant{
+    pub pos: uVec3,
+    pub direction: u8,
+    pub state: usize,
+}
+
+// flèche affichée sur une turmite
+#[derive(Component)]
+pub struct AntMarker;
+
+#[derive(Component)]
+pub struct AntAssets{
+    pub mesh: Mesh2dHandle,
+    pub material: Handle<ColorMaterial>,
+}
+
+// automate continu : grille bornée affichée dans une image, visible seulement avec une règle Lenia
+#[derive(Component)]
+pub struct Lenia{
+    pub world: LeniaWorld,
+    pub image: Handle<Image>,
+    pub seed: u32,
+    pub dirty: bool,    // l'image doit être recopiée
+}
+
+// vue de loin : une image où chaque pixel est un bloc de cellules, plus opaque quand il est plus peuplé
+#[derive(Component)]
+pub struct DensityView{
+    pub image: Handle<Image>,
+    pub lod: bool,      // vrai quand les tuiles sont remplacées par l'image
+    pub lastUpdate: f32,
+}
+
+// index dans theme::THEMES
+#[derive(Component)]
+pub struct CurrentTheme{
+    pub index: usize,
+}
+
+// lignes de la grille et axes (touche L), un seul mesh reconstruit quand la vue change
+#[derive(Component)]
+pub struct GridOverlay{
+    pub mesh: Handle<Mesh>,
+    pub enabled: bool,
+    pub lastView: Option<(Vec2, f32, Vec2, usize)>,     // (centre, échelle, taille de la fenêtre, thème)
+}
+
+// minimap en haut à droite : zone du monde [min, min + span] dessinée dans image
+#[derive(Component)]
+pub struct Minimap{
+    pub image: Handle<Image>,
+    pub min: Vec2,
+    pub span: f32,
+    pub lastUpdate: f32,
+}
+
+// caméra perspective de la vue 3D, qui tourne autour de target
+#[derive(Component)]
+pub struct View3D{
+    pub enabled: bool,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub distance: f32,
+    pub target: Vec3,
+}
+
+#[derive(Component)]
+pub struct Cube;
+
+#[derive(Component)]
+pub struct CubeAssets{
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<StandardMaterial>,
 }
 
 #[derive(Component)]
@@ -24,13 +149,130 @@ pub struct Stable;
 #[derive(Component)]
 pub struct ReferenceTile;
 
+// un material par état de cellule, index 0 inutilisé (mort)
+#[derive(Component)]
+pub struct StatePalette{
+    pub materials: Vec<Handle<ColorMaterial>>,
+    pub rule: String,   // règle pour laquelle les materials ont été construits
+}
+
+// un mesh par forme de cellule, selon le réseau de la règle
+#[derive(Component)]
+pub struct TileMeshes{
+    pub square: Mesh2dHandle,
+    pub hexagon: Mesh2dHandle,
+    pub triangleUp: Mesh2dHandle,
+    pub triangleDown: Mesh2dHandle,
+}
+
+impl TileMeshes{
+    pub fn get(&self, grid: GridType, pos: &uVec3) -> &Mesh2dHandle{
+        match grid {
+            GridType::Square | GridType::Cubic => &self.square,
+            GridType::Hexagonal => &self.hexagon,
+            GridType::Triangular => if grid::isUpTriangle(pos) { &self.triangleUp } else { &self.triangleDown },
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct TilesCache{
     pub entities: Vec<Entity>
 }
 
+// vitesse de la simulation (--gens-per-second), None : une génération par frame
+#[derive(Component)]
+pub struct SimulationSpeed{
+    pub generationsPerSecond: Option<f32>,
+    pub pending: f32,       // fraction de génération accumulée entre deux frames
+    pub generations: u32,   // générations de cette frame, calculées par run_simulation (ou run_lenia pour Lenia)
+}
+
 #[derive(Component)]
 pub struct RefreshTimer{
     pub lastRefresh: u128,
     pub timeBetweenRefresh: u128,
-}
\ No newline at end of file
+}
+
+// enregistrement GIF / PNG : zone choisie avec Alt + glisser (centres des cellules des coins, None = tout le motif)
+// et calcul en cours dans un thread, pour ne pas bloquer l'affichage
+#[derive(Component)]
+pub struct Recorder{
+    pub region: Option<(Vec2, Vec2)>,
+    pub dragStart: Option<Vec2>,
+    pub job: Option<std::thread::JoinHandle<Result<String, String>>>,
+}
+
+// objets séparés (touche O), entourés d'une couleur chacun, avec une info-bulle sur l'objet survolé
+#[derive(Component)]
+pub struct ObjectLabels{
+    pub enabled: bool,
+    pub distance: i32,                  // distance de connexion entre deux cellules d'un objet (Shift+O)
+    pub objects: Vec<Object>,
+    pub lastUpdate: f32,
+    pub classifications: HashMap<Vec<(i32, i32)>, String>,     // par forme (objects::shape), pour la règle `rule`
+    pub rule: String,
+    pub job: Option<(Vec<(i32, i32)>, std::thread::JoinHandle<String>)>,   // classification en cours dans un thread
+}
+
+// motif collé (Ctrl+V) qui suit le curseur jusqu'au clic, coin haut gauche en (0, 0)
+#[derive(Component)]
+pub struct Stamp{
+    pub pending: Option<Vec<(uVec3, u8)>>,
+    pub consumed: bool,     // le clic qui a posé ou annulé le motif ne dessine pas, jusqu'au relâchement
+    pub clipboard: Option<ClipboardJob>,
+}
+
+// copie ou collage en cours (clipboard.rs), le résultat arrive dans une frame suivante
+pub enum ClipboardJob{
+    Copy{ reply: Receiver<Result<(), String>>, cells: usize, cut: Vec<uVec3> },   // cut : cellules à effacer une fois copiées
+    Paste(Receiver<Result<String, String>>),
+}
+
+// raccourcis du clavier et de la manette (keybindings.ron) et actions enfoncées à cette frame
+#[derive(Component)]
+pub struct Controls{
+    pub bindings: HashMap<Action, Vec<Binding>>,
+    pub pressed: HashSet<Action>,
+    pub justPressed: HashSet<Action>,
+    pub lastModified: Option<SystemTime>,   // date du fichier chargé, pour le recharger quand il change
+    pub lastCheck: f32,
+    pub triggered: Vec<Action>,     // actions lancées sans touche (palette de commandes), actives à la frame suivante
+    pub captured: bool,             // le clavier est pris par la palette de commandes
+}
+
+impl Controls{
+    pub fn pressed(&self, action: Action) -> bool{
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool{
+        self.justPressed.contains(&action)
+    }
+
+    pub fn rebind(&mut self, action: Action, bindings: Vec<Binding>){
+        self.bindings.insert(action, bindings);
+    }
+
+    pub fn trigger(&mut self, action: Action){
+        self.triggered.push(action);
+    }
+}
+
+// aide (F1) : toutes les actions avec leurs raccourcis actuels, sur deux colonnes
+#[derive(Component)]
+pub struct HelpOverlay{
+    pub visible: bool,
+}
+
+#[derive(Component)]
+pub struct HelpColumn(pub usize);
+
+// palette de commandes (Ctrl+P) : recherche approximative parmi les actions et les motifs du dossier patterns/
+#[derive(Component)]
+pub struct CommandPalette{
+    pub open: bool,
+    pub query: String,
+    pub selected: usize,
+    pub entries: Vec<palette::Entry>,
+}
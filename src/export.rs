@@ -0,0 +1,60 @@
+use std::fs::File;
+use std::io::BufWriter;
+use bevy::math::Vec2;
+use crate::grid::GridType;
+use crate::uVec3::uVec3;
+
+// image calculée à partir des cellules (pas de capture du rendu GPU) :
+// chaque cellule est un carré de cellSize pixels placé à la position de son centre dans le monde.
+// La ligne 0 de l'image est en haut, le y du monde va vers le haut.
+
+pub const MAX_EXPORT_SIZE: u32 = 16384;
+
+pub struct Raster{
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,    // RGBA
+}
+
+// centres extrêmes des cellules
+pub fn bounding_box(cells: &[(uVec3, u8)], grid: GridType) -> Option<(Vec2, Vec2)>{
+    let mut points = cells.iter().map(|(pos, _)| grid.to_world(pos).truncate());
+    let first = points.next()?;
+    Some(points.fold((first, first), |(min, max), p| (min.min(p), max.max(p))))
+}
+
+// min et max sont les centres des cellules des coins, colors est indexé par l'état
+pub fn rasterize(cells: &[(uVec3, u8)], grid: GridType, min: Vec2, max: Vec2, cellSize: u32, colors: &[[u8; 4]], background: [u8; 4]) -> Result<Raster, String>{
+    let width = ((max.x - min.x + 1.) * cellSize as f32).round().max(1.) as u32;
+    let height = ((max.y - min.y + 1.) * cellSize as f32).round().max(1.) as u32;
+    if width > MAX_EXPORT_SIZE || height > MAX_EXPORT_SIZE {
+        return Err(format!("image too large ({} x {}, at most {} pixels per side)", width, height, MAX_EXPORT_SIZE));
+    }
+    let mut pixels: Vec<u8> = background.repeat((width * height) as usize);
+    for (pos, state) in cells{
+        let p = grid.to_world(pos).truncate();
+        let x0 = ((p.x - min.x) * cellSize as f32).round() as i64;
+        let y0 = ((max.y - p.y) * cellSize as f32).round() as i64;
+        let color = colors.get(*state as usize).copied().unwrap_or([255, 255, 255, 255]);
+        for y in y0.max(0)..(y0 + cellSize as i64).min(height as i64) {
+            for x in x0.max(0)..(x0 + cellSize as i64).min(width as i64) {
+                let i = (y as usize * width as usize + x as usize) * 4;
+                pixels[i..i + 4].copy_from_slice(&color);
+            }
+        }
+    }
+    Ok(Raster{
+        width,
+        height,
+        pixels,
+    })
+}
+
+pub fn write_png(path: &str, raster: &Raster) -> Result<(), String>{
+    let file = File::create(path).map_err(|e| format!("cannot create {}: {}", path, e))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), raster.width, raster.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer.write_image_data(&raster.pixels).map_err(|e| e.to_string())
+}
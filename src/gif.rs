@@ -0,0 +1,280 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use bevy::utils::HashMap;
+use crate::export::Raster;
+
+// encodeur GIF89a minimal : une palette globale construite à partir des couleurs des images (256 au plus),
+// compression LZW, boucle infinie (extension NETSCAPE2.0) et délai par image en centièmes de seconde.
+// La transparence est ignorée, les cellules sont dessinées sur un fond opaque.
+
+const MAX_CODE: u16 = 4096;
+
+pub fn write_gif(path: &str, frames: &[Raster], delay: u16) -> Result<(), String>{
+    let Some(first) = frames.first() else {
+        return Err(String::from("no frame to write"));
+    };
+    if first.width > u16::MAX as u32 || first.height > u16::MAX as u32 {
+        return Err(format!("image too large for a GIF ({} x {})", first.width, first.height));
+    }
+    if frames.iter().any(|f| f.width != first.width || f.height != first.height) {
+        return Err(String::from("every frame needs the same size"));
+    }
+
+    let mut palette: Vec<[u8; 3]> = Vec::new();
+    let mut indices: HashMap<[u8; 3], u8> = HashMap::new();
+    for frame in frames{
+        for pixel in frame.pixels.chunks_exact(4){
+            let color = [pixel[0], pixel[1], pixel[2]];
+            if !indices.contains_key(&color) {
+                if palette.len() == 256 {
+                    return Err(String::from("more than 256 colors, cannot write a GIF"));
+                }
+                indices.insert(color, palette.len() as u8);
+                palette.push(color);
+            }
+        }
+    }
+    // la table de couleurs a 2^(n+1) entrées, avec au moins 4 entrées pour le LZW
+    let mut bits = 1;
+    while (1 << bits) < palette.len() {
+        bits += 1;
+    }
+    let bits = bits.max(2);
+    palette.resize(1 << bits, [0, 0, 0]);
+
+    let mut out: Vec<u8> = Vec::new();
+    out.extend_from_slice(b"GIF89a");
+    out.extend_from_slice(&(first.width as u16).to_le_bytes());
+    out.extend_from_slice(&(first.height as u16).to_le_bytes());
+    out.push(0x80 | ((bits - 1) << 4) as u8 | (bits - 1) as u8);
+    out.push(0);    // couleur de fond
+    out.push(0);    // rapport largeur / hauteur des pixels
+    for color in palette.iter(){
+        out.extend_from_slice(color);
+    }
+    out.extend_from_slice(&[0x21, 0xFF, 11]);
+    out.extend_from_slice(b"NETSCAPE2.0");
+    out.extend_from_slice(&[3, 1, 0, 0, 0]);
+
+    for frame in frames{
+        // contrôle graphique : délai, l'image précédente reste affichée
+        out.extend_from_slice(&[0x21, 0xF9, 4, 0x04]);
+        out.extend_from_slice(&delay.to_le_bytes());
+        out.extend_from_slice(&[0, 0]);
+        // descripteur d'image, sans table de couleurs locale
+        out.push(0x2C);
+        out.extend_from_slice(&[0, 0, 0, 0]);
+        out.extend_from_slice(&(frame.width as u16).to_le_bytes());
+        out.extend_from_slice(&(frame.height as u16).to_le_bytes());
+        out.push(0);
+        let pixels: Vec<u8> = frame.pixels.chunks_exact(4).map(|p| indices[&[p[0], p[1], p[2]]]).collect();
+        out.push(bits as u8);
+        for block in lzw(&pixels, bits).chunks(255){
+            out.push(block.len() as u8);
+            out.extend_from_slice(block);
+        }
+        out.push(0);
+    }
+    out.push(0x3B);
+
+    let file = File::create(path).map_err(|e| format!("cannot create {}: {}", path, e))?;
+    let mut writer = BufWriter::new(file);
+    writer.write_all(&out).map_err(|e| format!("cannot write {}: {}", path, e))
+}
+
+// compression LZW à taille de code variable, bits écrits à partir du poids faible
+fn lzw(pixels: &[u8], minCodeSize: u32) -> Vec<u8>{
+    let clear = 1u16 << minCodeSize;
+    let end = clear + 1;
+    let mut codeSize = minCodeSize + 1;
+    let mut next = end + 1;
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+
+    let mut out: Vec<u8> = Vec::new();
+    let mut buffer: u32 = 0;
+    let mut count: u32 = 0;
+    let mut emit = |code: u16, size: u32, out: &mut Vec<u8>|{
+        buffer |= (code as u32) << count;
+        count += size;
+        while count >= 8 {
+            out.push(buffer as u8);
+            buffer >>= 8;
+            count -= 8;
+        }
+    };
+
+    emit(clear, codeSize, &mut out);
+    let mut current: Option<u16> = None;
+    for pixel in pixels{
+        let Some(prefix) = current else {
+            current = Some(*pixel as u16);
+            continue;
+        };
+        if let Some(code) = table.get(&(prefix, *pixel)) {
+            current = Some(*code);
+            continue;
+        }
+        emit(prefix, codeSize, &mut out);
+        if next == MAX_CODE {
+            // table pleine : on repart de zéro
+            emit(clear, codeSize, &mut out);
+            table.clear();
+            codeSize = minCodeSize + 1;
+            next = end + 1;
+        } else {
+            if next >= 1 << codeSize {
+                codeSize += 1;
+            }
+            table.insert((prefix, *pixel), next);
+            next += 1;
+        }
+        current = Some(*pixel as u16);
+    }
+    if let Some(prefix) = current {
+        emit(prefix, codeSize, &mut out);
+    }
+    emit(end, codeSize, &mut out);
+    // 7 bits à zéro pour écrire le dernier octet incomplet
+    emit(0, 7, &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    // décodeur LZW écrit d'après la spécification GIF89a : (pixels, plus grande taille de code lue, nombre de codes clear)
+    fn unlzw(data: &[u8], minCodeSize: u32) -> (Vec<u8>, u32, usize){
+        let clear = 1usize << minCodeSize;
+        let end = clear + 1;
+        let mut table: Vec<Vec<u8>> = Vec::new();
+        let mut codeSize = minCodeSize + 1;
+        let mut largest = codeSize;
+        let mut clears = 0;
+        let mut previous: Option<usize> = None;
+        let mut pixels: Vec<u8> = Vec::new();
+        let (mut buffer, mut count, mut position) = (0u32, 0u32, 0usize);
+        loop {
+            while count < codeSize {
+                buffer |= (data[position] as u32) << count;
+                position += 1;
+                count += 8;
+            }
+            let code = (buffer & ((1 << codeSize) - 1)) as usize;
+            buffer >>= codeSize;
+            count -= codeSize;
+            largest = largest.max(codeSize);
+
+            if code == clear {
+                table = (0..clear + 2).map(|c| vec![c as u8]).collect();
+                codeSize = minCodeSize + 1;
+                previous = None;
+                clears += 1;
+                continue;
+            }
+            if code == end {
+                return (pixels, largest, clears);
+            }
+            let entry = match previous {
+                None => table[code].clone(),
+                Some(p) => {
+                    let entry = if code < table.len() {
+                        table[code].clone()
+                    } else {
+                        assert_eq!(code, table.len(), "code {} not in the table", code);
+                        let mut e = table[p].clone();
+                        e.push(table[p][0]);
+                        e
+                    };
+                    if table.len() < MAX_CODE as usize {
+                        let mut added = table[p].clone();
+                        added.push(entry[0]);
+                        table.push(added);
+                        if table.len() == 1 << codeSize && codeSize < 12 {
+                            codeSize += 1;
+                        }
+                    }
+                    entry
+                }
+            };
+            pixels.extend_from_slice(&entry);
+            previous = Some(code);
+        }
+    }
+
+    fn noise(length: usize, colors: u32) -> Vec<u8>{
+        let mut seed: u32 = 7;
+        (0..length).map(|_| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            ((seed >> 16) % colors) as u8
+        }).collect()
+    }
+
+    #[test]
+    fn lzw_round_trip(){
+        // peu de pixels : pas de changement de taille
+        let (pixels, largest, clears) = unlzw(&lzw(&[0, 1], 2), 2);
+        assert_eq!((pixels, largest, clears), (vec![0, 1], 3, 1));
+        // la quatrième entrée remplit la table de 8 codes : le dernier pixel est lu sur 4 bits
+        let (pixels, largest, clears) = unlzw(&lzw(&[0, 1, 1, 0], 2), 2);
+        assert_eq!((pixels, largest, clears), (vec![0, 1, 1, 0], 4, 1));
+        // aplat : les chaînes s'allongent, la taille monte sans remplir la table
+        let flat = vec![3u8; 5000];
+        let (pixels, largest, clears) = unlzw(&lzw(&flat, 2), 2);
+        assert_eq!(pixels, flat);
+        assert_eq!((largest, clears), (7, 1));
+        // bruit sur 4 et 256 couleurs : la table se remplit, code clear et retour à la taille de départ
+        for (colors, minCodeSize) in [(4, 2), (256, 8)]{
+            let input = noise(40000, colors);
+            let (pixels, largest, clears) = unlzw(&lzw(&input, minCodeSize), minCodeSize);
+            assert!(pixels == input);
+            assert_eq!(largest, 12);
+            assert!(clears > 2, "{} clear codes", clears);
+        }
+    }
+
+    #[test]
+    fn write_frames(){
+        let colors = [[0, 0, 0], [255, 255, 255], [200, 40, 40]];
+        let frames: Vec<Raster> = (0..2).map(|f| Raster{
+            width: 3,
+            height: 2,
+            pixels: (0..6).flat_map(|i| { let c = colors[(i + f) % 3]; [c[0], c[1], c[2], 255] }).collect(),
+        }).collect();
+        let path = std::env::temp_dir().join("gameoflife_write_frames.gif");
+        let path = path.to_string_lossy().to_string();
+        write_gif(&path, &frames, 12).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(&bytes[..6], b"GIF89a");
+        assert_eq!(&bytes[6..10], &[3, 0, 2, 0]);
+        // 3 couleurs : table de 4 entrées
+        assert_eq!(bytes[10], 0x80 | (1 << 4) | 1);
+        assert_eq!(&bytes[13..25], &[0, 0, 0, 255, 255, 255, 200, 40, 40, 0, 0, 0]);
+        assert_eq!(&bytes[25..44], b"\x21\xFF\x0BNETSCAPE2.0\x03\x01\x00\x00\x00");
+
+        let mut position = 44;
+        for frame in frames.iter(){
+            assert_eq!(&bytes[position..position + 8], &[0x21, 0xF9, 4, 0x04, 12, 0, 0, 0]);
+            position += 8;
+            assert_eq!(bytes[position], 0x2C);
+            assert_eq!(&bytes[position + 5..position + 9], &[3, 0, 2, 0]);
+            position += 10;
+            let minCodeSize = bytes[position] as u32;
+            position += 1;
+            let mut data: Vec<u8> = Vec::new();
+            while bytes[position] != 0 {
+                let length = bytes[position] as usize;
+                data.extend_from_slice(&bytes[position + 1..position + 1 + length]);
+                position += 1 + length;
+            }
+            position += 1;
+            let expected: Vec<u8> = frame.pixels.chunks_exact(4).map(|p| colors.iter().position(|c| c[..] == p[..3]).unwrap() as u8).collect();
+            assert_eq!(unlzw(&data, minCodeSize).0, expected);
+        }
+        assert_eq!(&bytes[position..], &[0x3B]);
+
+        assert!(write_gif(&path, &[], 12).is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use bevy::math::{Vec2, Vec3};
+use crate::uVec3::uVec3;
+
+// type de réseau : carré (par défaut), hexagonal (suffixe H) ou triangulaire (suffixe L, comme LifeViewer)
+// hexagonal : coordonnées axiales (q, r) rangées dans x, y
+// triangulaire : la cellule (x, y) pointe vers le haut si x + y est pair, vers le bas sinon
+// cubique : Life 3D (notation de Bays), z est la couche, éditée tranche par tranche comme une grille carrée
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GridType{
+    Square,
+    Hexagonal,
+    Triangular,
+    Cubic,
+}
+
+const SQRT3_2: f32 = 0.866_025_4;
+
+const SQUARE_NEIGHBOURS: [(i32, i32); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+const HEX_NEIGHBOURS: [(i32, i32); 6] = [(1, 0), (-1, 0), (0, 1), (0, -1), (1, -1), (-1, 1)];
+// triangle pointant vers le haut : 3 voisins dans la ligne du dessus, 4 dans la sienne, 5 dans celle du dessous (sa base)
+const TRIANGLE_UP_NEIGHBOURS: [(i32, i32); 12] = [
+    (-1, 1), (0, 1), (1, 1),
+    (-2, 0), (-1, 0), (1, 0), (2, 0),
+    (-2, -1), (-1, -1), (0, -1), (1, -1), (2, -1),
+];
+const TRIANGLE_DOWN_NEIGHBOURS: [(i32, i32); 12] = [
+    (-2, 1), (-1, 1), (0, 1), (1, 1), (2, 1),
+    (-2, 0), (-1, 0), (1, 0), (2, 0),
+    (-1, -1), (0, -1), (1, -1),
+];
+
+impl GridType{
+    pub fn neighbours(&self, pos: &uVec3) -> &'static [(i32, i32)]{
+        match self {
+            GridType::Square | GridType::Cubic => &SQUARE_NEIGHBOURS,
+            GridType::Hexagonal => &HEX_NEIGHBOURS,
+            GridType::Triangular => if isUpTriangle(pos) { &TRIANGLE_UP_NEIGHBOURS } else { &TRIANGLE_DOWN_NEIGHBOURS },
+        }
+    }
+
+    // décalage maximal d'un voisin, en x comme en y
+    pub fn reach(&self) -> i32{
+        match self {
+            GridType::Triangular => 2,
+            _ => 1,
+        }
+    }
+
+    pub fn neighbour_count(&self) -> usize{
+        match self {
+            GridType::Square => 8,
+            GridType::Hexagonal => 6,
+            GridType::Triangular => 12,
+            GridType::Cubic => 26,
+        }
+    }
+
+    pub fn suffix(&self) -> &'static str{
+        match self {
+            GridType::Square | GridType::Cubic => "",
+            GridType::Hexagonal => "H",
+            GridType::Triangular => "L",
+        }
+    }
+
+    // centre de la cellule sur le plan 2D (z = 0, la couche d'une cellule 3D ne compte pas ici)
+    pub fn to_world(&self, pos: &uVec3) -> Vec3{
+        match self {
+            GridType::Square | GridType::Cubic => Vec3::new(pos.x as f32, pos.y as f32, 0.),
+            GridType::Hexagonal => Vec3::new(pos.x as f32 + pos.y as f32 * 0.5, pos.y as f32 * SQRT3_2, 0.),
+            GridType::Triangular => {
+                let centroid = if isUpTriangle(pos) { SQRT3_2 / 3. } else { SQRT3_2 * 2. / 3. };
+                Vec3::new(pos.x as f32 * 0.5, pos.y as f32 * SQRT3_2 + centroid, 0.)
+            }
+        }
+    }
+
+    // cellule sous un point du monde
+    pub fn from_world(&self, world: Vec2) -> uVec3{
+        match self {
+            GridType::Square | GridType::Cubic => uVec3::new(world.x.round() as i32, world.y.round() as i32, 0),
+            GridType::Hexagonal => {
+                // arrondi en coordonnées cubiques
+                let r = world.y / SQRT3_2;
+                let q = world.x - r * 0.5;
+                let s = -q - r;
+                let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+                let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+                if dq > dr && dq > ds {
+                    rq = -rr - rs;
+                } else if dr > ds {
+                    rr = -rq - rs;
+                }
+                uVec3::new(rq as i32, rr as i32, 0)
+            }
+            GridType::Triangular => {
+                let y = (world.y / SQRT3_2).floor() as i32;
+                let fy = world.y / SQRT3_2 - y as f32;
+                let fx = world.x * 2.;
+                // dans la ligne, les arêtes obliques passent par les x entiers (en demi-unités)
+                let base = fx.floor() as i32;
+                for x in [base - 1, base, base + 1] {
+                    let up = (x + y).rem_euclid(2) == 0;
+                    let local = fx - (x as f32 - 1.);
+                    // triangle de base 2 (en demi-unités) et hauteur 1
+                    let inside = if up {
+                        local >= fy && local <= 2. - fy
+                    } else {
+                        local >= 1. - fy && local <= 1. + fy
+                    };
+                    if inside {
+                        return uVec3::new(x, y, 0);
+                    }
+                }
+                uVec3::new(base, y, 0)
+            }
+        }
+    }
+}
+
+pub fn isUpTriangle(pos: &uVec3) -> bool{
+    (pos.x + pos.y).rem_euclid(2) == 0
+}
+
+// sommets du triangle (vers le haut) centré sur son centre de gravité, côté 1
+pub fn triangle_vertices() -> [Vec2; 3]{
+    [Vec2::new(0., SQRT3_2 * 2. / 3.), Vec2::new(-0.5, -SQRT3_2 / 3.), Vec2::new(0.5, -SQRT3_2 / 3.)]
+}
+
+pub fn hexagon_radius() -> f32{
+    // hexagone pointe en haut, distance entre centres voisins = 1
+    1. / 3f32.sqrt()
+}
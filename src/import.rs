@@ -0,0 +1,135 @@
+use std::fs::File;
+use crate::uVec3::uVec3;
+
+// import d'une image PNG comme motif : un pixel par cellule (après mise à l'échelle),
+// la ligne 0 de l'image est en haut, le y du monde va vers le haut.
+// Règles à 2 états : pixel vivant si sa luminance est sous le seuil (dessin noir sur fond blanc),
+// avec tramage de Floyd-Steinberg en option. Règles à plus de 2 états : chaque pixel prend l'état
+// dont la couleur à l'écran est la plus proche (la couleur 0 étant le fond).
+
+pub const MAX_IMPORT_SIZE: u32 = 4096;
+
+pub struct ImportSettings{
+    pub threshold: f32,     // luminance entre 0 et 1
+    pub invert: bool,       // les pixels clairs deviennent vivants
+    pub scale: f32,         // cellules par pixel
+    pub dither: bool,
+}
+
+impl Default for ImportSettings{
+    fn default() -> ImportSettings{
+        ImportSettings{
+            threshold: 0.5,
+            invert: false,
+            scale: 1.,
+            dither: false,
+        }
+    }
+}
+
+pub struct Bitmap{
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,    // RGBA
+}
+
+// n'importe quel PNG (palette, gris, 16 bits ...) converti en RGBA 8 bits
+pub fn read_png(path: &str) -> Result<Bitmap, String>{
+    let file = File::open(path).map_err(|e| format!("cannot open {}: {}", path, e))?;
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(|e| format!("invalid PNG {}: {}", path, e))?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(|e| format!("invalid PNG {}: {}", path, e))?;
+    let bytes = &buffer[..info.buffer_size()];
+    let pixels: Vec<u8> = match info.color_type {
+        png::ColorType::Rgba => bytes.to_vec(),
+        png::ColorType::Rgb => bytes.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => bytes.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => bytes.iter().flat_map(|g| [*g, *g, *g, 255]).collect(),
+        png::ColorType::Indexed => return Err(format!("unexpected indexed colors in {}", path)),
+    };
+    Ok(Bitmap{
+        width: info.width,
+        height: info.height,
+        pixels,
+    })
+}
+
+// taille en cellules de l'image importée
+pub fn cell_size(bitmap: &Bitmap, settings: &ImportSettings) -> (u32, u32){
+    ((bitmap.width as f32 * settings.scale).round().max(1.) as u32, (bitmap.height as f32 * settings.scale).round().max(1.) as u32)
+}
+
+// cellules de l'image, le coin haut gauche en origin ; colors est indexé par l'état (export_colors)
+pub fn to_cells(bitmap: &Bitmap, settings: &ImportSettings, colors: &[[u8; 4]], origin: uVec3) -> Result<Vec<(uVec3, u8)>, String>{
+    if settings.scale <= 0. {
+        return Err(String::from("scale must be positive"));
+    }
+    let (width, height) = cell_size(bitmap, settings);
+    if width > MAX_IMPORT_SIZE || height > MAX_IMPORT_SIZE {
+        return Err(format!("image too large ({} x {} cells, at most {} per side)", width, height, MAX_IMPORT_SIZE));
+    }
+    // plus proche voisin
+    let pixel = |x: u32, y: u32| -> [u8; 4]{
+        let sx = ((x as f32 + 0.5) / settings.scale) as u32;
+        let sy = ((y as f32 + 0.5) / settings.scale) as u32;
+        let i = ((sy.min(bitmap.height - 1) * bitmap.width + sx.min(bitmap.width - 1)) * 4) as usize;
+        [bitmap.pixels[i], bitmap.pixels[i + 1], bitmap.pixels[i + 2], bitmap.pixels[i + 3]]
+    };
+
+    let mut cells: Vec<(uVec3, u8)> = Vec::new();
+    let at = |x: u32, y: u32| uVec3::new(origin.x + x as i32, origin.y - y as i32, origin.z);
+    if colors.len() > 2 {
+        for y in 0..height {
+            for x in 0..width {
+                let p = pixel(x, y);
+                if p[3] < 128 {
+                    continue;
+                }
+                let state = (0..colors.len()).min_by_key(|s| {
+                    let c = colors[*s];
+                    (0..3).map(|i| (c[i] as i32 - p[i] as i32).pow(2)).sum::<i32>()
+                }).unwrap();
+                if state != 0 {
+                    cells.push((at(x, y), state as u8));
+                }
+            }
+        }
+        return Ok(cells);
+    }
+
+    // luminance, les pixels transparents comptent comme le fond
+    let mut luminance: Vec<f32> = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let p = pixel(x, y);
+            let l = (0.2126 * p[0] as f32 + 0.7152 * p[1] as f32 + 0.0722 * p[2] as f32) / 255.;
+            luminance.push(if p[3] < 128 { 1. } else if settings.invert { 1. - l } else { l });
+        }
+    }
+    for y in 0..height {
+        for x in 0..width {
+            let i = (y * width + x) as usize;
+            let alive = luminance[i] < settings.threshold;
+            if alive {
+                cells.push((at(x, y), 1));
+            }
+            if settings.dither {
+                // l'erreur est répartie sur les pixels pas encore traités : 7/16, 3/16, 5/16, 1/16
+                let error = luminance[i] - if alive { 0. } else { 1. };
+                let mut spread = |dx: i32, dy: u32, weight: f32|{
+                    let nx = x as i32 + dx;
+                    if nx >= 0 && nx < width as i32 && y + dy < height {
+                        luminance[((y + dy) * width) as usize + nx as usize] += error * weight;
+                    }
+                };
+                spread(1, 0, 7. / 16.);
+                spread(-1, 1, 3. / 16.);
+                spread(0, 1, 5. / 16.);
+                spread(1, 1, 1. / 16.);
+            }
+        }
+    }
+    Ok(cells)
+}
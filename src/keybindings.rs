@@ -0,0 +1,349 @@
+use std::time::SystemTime;
+use bevy::input::gamepad::GamepadButtonType;
+use bevy::prelude::KeyCode;
+use bevy::reflect::{DynamicEnum, DynamicVariant, FromReflect, TypeInfo, Typed, VariantInfo};
+use bevy::utils::HashMap;
+
+// actions du clavier et de la manette : chaque action a une liste de raccourcis, remplaçables par le fichier
+// keybindings.ron (rechargé quand il change), par exemple :
+//   {
+//       "PanUp": ["W", "ArrowUp"],
+//       "Run": ["Space", "Gamepad:South"],
+//       "ExportView": ["Ctrl+Shift+E"],
+//   }
+// Touches : noms des KeyCode de Bevy (KeyW, Digit1, ArrowUp, PageUp, F1 ...) ou une lettre / un chiffre seul.
+// Ce sont des positions physiques, nommées d'après le clavier QWERTY : "W" est la touche Z d'un clavier AZERTY,
+// les déplacements WASD par défaut tombent donc déjà sur ZQSD. L'aide F1 affiche ces mêmes noms.
+// Modificateurs Ctrl, Shift et Alt : un raccourci ne s'active que si ce sont exactement ceux enfoncés.
+// Manette : "Gamepad:" + nom d'un GamepadButtonType (South, East, DPadUp, LeftTrigger, Start ...).
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action{
+    PanUp,
+    PanDown,
+    PanLeft,
+    PanRight,
+    ZoomIn,
+    ZoomOut,
+    Run,
+    Step,
+    Clear,
+    Seed,           // planeur, une cellule en 1D, une tache avec Lenia
+    Benchmark,
+    ToggleVsync,
+    CycleRule,
+    CycleTheme,
+    ToggleGrid,
+    FitView,
+    Follow,
+    Brush(u8),
+    SaveBookmark(u8),
+    RecallBookmark(u8),
+    SavePattern,
+    LoadPattern,
+    SaveMacrocell,
+    LoadMacrocell,
+    ImportImage,
+    ImportImageInverted,
+    ExportPng,
+    ExportView,
+    RecordGif,
+    RecordPngs,
+    Identify,
+    ToggleObjects,
+    ObjectDistance,
+    Copy,
+    Cut,
+    Paste,
+    Cancel,
+    Toggle3D,
+    LayerUp,
+    LayerDown,
+    OrbitLeft,
+    OrbitRight,
+    OrbitUp,
+    OrbitDown,
+    Help,
+    CommandPalette,
+}
+
+impl Action{
+    pub fn all() -> Vec<Action>{
+        let mut actions = vec![
+            Action::PanUp, Action::PanDown, Action::PanLeft, Action::PanRight, Action::ZoomIn, Action::ZoomOut,
+            Action::Run, Action::Step, Action::Clear, Action::Seed, Action::Benchmark, Action::ToggleVsync,
+            Action::CycleRule, Action::CycleTheme, Action::ToggleGrid, Action::FitView, Action::Follow,
+        ];
+        actions.extend((1..10).map(Action::Brush));
+        actions.extend((1..10).map(Action::SaveBookmark));
+        actions.extend((1..10).map(Action::RecallBookmark));
+        actions.extend([
+            Action::SavePattern, Action::LoadPattern, Action::SaveMacrocell, Action::LoadMacrocell,
+            Action::ImportImage, Action::ImportImageInverted, Action::ExportPng, Action::ExportView,
+            Action::RecordGif, Action::RecordPngs, Action::Identify, Action::ToggleObjects, Action::ObjectDistance,
+            Action::Copy, Action::Cut, Action::Paste, Action::Cancel,
+            Action::Toggle3D, Action::LayerUp, Action::LayerDown, Action::OrbitLeft, Action::OrbitRight, Action::OrbitUp, Action::OrbitDown,
+            Action::Help, Action::CommandPalette,
+        ]);
+        actions
+    }
+
+    // nom utilisé dans keybindings.ron : "PanUp", "Brush3", "SaveBookmark1" ...
+    pub fn name(&self) -> String{
+        match self {
+            Action::Brush(i) => format!("Brush{}", i),
+            Action::SaveBookmark(i) => format!("SaveBookmark{}", i),
+            Action::RecallBookmark(i) => format!("RecallBookmark{}", i),
+            action => format!("{:?}", action),
+        }
+    }
+
+    // texte de l'aide (F1) et de la palette de commandes (Ctrl+P)
+    pub fn description(&self) -> String{
+        match self {
+            Action::PanUp => String::from("Move the camera up"),
+            Action::PanDown => String::from("Move the camera down"),
+            Action::PanLeft => String::from("Move the camera left"),
+            Action::PanRight => String::from("Move the camera right"),
+            Action::ZoomIn => String::from("Zoom in"),
+            Action::ZoomOut => String::from("Zoom out"),
+            Action::Run => String::from("Start / stop the simulation"),
+            Action::Step => String::from("Advance one generation while paused"),
+            Action::Clear => String::from("Clear the universe"),
+            Action::Seed => String::from("Place a glider (a single cell in 1D, a blob in Lenia)"),
+            Action::Benchmark => String::from("Place the benchmark pattern"),
+            Action::ToggleVsync => String::from("Toggle vertical sync"),
+            Action::CycleRule => String::from("Switch to the next preset rule"),
+            Action::CycleTheme => String::from("Switch to the next color theme"),
+            Action::ToggleGrid => String::from("Show / hide the grid"),
+            Action::FitView => String::from("Fit all living cells in the view"),
+            Action::Follow => String::from("Follow the object under the cursor"),
+            Action::Brush(i) => format!("Draw with state {}", i),
+            Action::SaveBookmark(i) => format!("Save the view as bookmark {}", i),
+            Action::RecallBookmark(i) => format!("Go to bookmark {}", i),
+            Action::SavePattern => String::from("Save the universe to pattern.rle"),
+            Action::LoadPattern => String::from("Load pattern.rle"),
+            Action::SaveMacrocell => String::from("Save the universe to pattern.mc"),
+            Action::LoadMacrocell => String::from("Load pattern.mc around the view"),
+            Action::ImportImage => String::from("Import pattern.png"),
+            Action::ImportImageInverted => String::from("Import pattern.png, inverted"),
+            Action::ExportPng => String::from("Export all living cells as a PNG"),
+            Action::ExportView => String::from("Export the view as a PNG"),
+            Action::RecordGif => String::from("Record the next generations as a GIF"),
+            Action::RecordPngs => String::from("Record the next generations as PNG files"),
+            Action::Identify => String::from("Print the apgcode of the selected object"),
+            Action::ToggleObjects => String::from("Show / hide object outlines"),
+            Action::ObjectDistance => String::from("Change the object connection distance"),
+            Action::Copy => String::from("Copy the selection as RLE"),
+            Action::Cut => String::from("Cut the selection as RLE"),
+            Action::Paste => String::from("Paste a pattern from the clipboard"),
+            Action::Cancel => String::from("Cancel the pasted pattern"),
+            Action::Toggle3D => String::from("Switch between the 3D view and layer editing"),
+            Action::LayerUp => String::from("Edit the layer above"),
+            Action::LayerDown => String::from("Edit the layer below"),
+            Action::OrbitLeft => String::from("Turn the 3D camera left"),
+            Action::OrbitRight => String::from("Turn the 3D camera right"),
+            Action::OrbitUp => String::from("Turn the 3D camera up"),
+            Action::OrbitDown => String::from("Turn the 3D camera down"),
+            Action::Help => String::from("Show / hide this help"),
+            Action::CommandPalette => String::from("Open the command palette"),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action>{
+        Action::all().into_iter().find(|action| action.name().eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Input{
+    Key(KeyCode),
+    Gamepad(GamepadButtonType),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Binding{
+    pub input: Input,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+// variante sans champ d'une énumération de Bevy, retrouvée par son nom grâce à la réflexion
+fn unit_variant<T: FromReflect + Typed>(name: &str) -> Option<T>{
+    let TypeInfo::Enum(info) = T::type_info() else {
+        return None;
+    };
+    let variant = info.iter().find(|v| v.name().eq_ignore_ascii_case(name))?;
+    if !matches!(variant, VariantInfo::Unit(_)) {
+        return None;
+    }
+    T::from_reflect(&DynamicEnum::new(variant.name(), DynamicVariant::Unit))
+}
+
+impl Binding{
+    pub fn key(key: KeyCode) -> Binding{
+        Binding{ input: Input::Key(key), ctrl: false, shift: false, alt: false }
+    }
+
+    pub fn parse(text: &str) -> Result<Binding, String>{
+        let mut binding = Binding::key(KeyCode::Space);
+        let mut parts: Vec<&str> = text.split('+').map(|p| p.trim()).collect();
+        // "Ctrl++" : la touche est le dernier '+'
+        let last = parts.pop().filter(|p| !p.is_empty()).ok_or(format!("invalid binding '{}'", text))?;
+        for part in parts{
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => binding.ctrl = true,
+                "shift" => binding.shift = true,
+                "alt" => binding.alt = true,
+                _ => return Err(format!("unknown modifier '{}' in '{}'", part, text)),
+            }
+        }
+        binding.input = if let Some(button) = last.strip_prefix("Gamepad:") {
+            Input::Gamepad(unit_variant::<GamepadButtonType>(button).ok_or(format!("unknown gamepad button '{}'", button))?)
+        } else {
+            let name = match last.chars().next() {
+                Some(c) if last.len() == 1 && c.is_ascii_alphabetic() => format!("Key{}", c.to_ascii_uppercase()),
+                Some(c) if last.len() == 1 && c.is_ascii_digit() => format!("Digit{}", c),
+                _ => last.to_string(),
+            };
+            Input::Key(unit_variant::<KeyCode>(&name).ok_or(format!("unknown key '{}'", last))?)
+        };
+        Ok(binding)
+    }
+
+    pub fn to_string(&self) -> String{
+        let mut text = String::new();
+        for (on, name) in [(self.ctrl, "Ctrl+"), (self.shift, "Shift+"), (self.alt, "Alt+")]{
+            if on {
+                text.push_str(name);
+            }
+        }
+        match self.input {
+            Input::Key(key) => {
+                let name = format!("{:?}", key);
+                text.push_str(name.strip_prefix("Key").or(name.strip_prefix("Digit")).unwrap_or(&name));
+            }
+            Input::Gamepad(button) => text.push_str(&format!("Gamepad:{:?}", button)),
+        }
+        text
+    }
+}
+
+// raccourcis d'une action pour l'aide et la palette
+pub fn bindings_text(bindings: &[Binding]) -> String{
+    if bindings.is_empty() {
+        return String::from("unbound");
+    }
+    bindings.iter().map(|b| b.to_string()).collect::<Vec<String>>().join(", ")
+}
+
+pub fn default_bindings() -> HashMap<Action, Vec<Binding>>{
+    let key = Binding::key;
+    let ctrl = |k: KeyCode| Binding{ ctrl: true, ..Binding::key(k) };
+    let shift = |k: KeyCode| Binding{ shift: true, ..Binding::key(k) };
+    let alt = |k: KeyCode| Binding{ alt: true, ..Binding::key(k) };
+    let ctrlShift = |k: KeyCode| Binding{ ctrl: true, shift: true, ..Binding::key(k) };
+    let pad = |b: GamepadButtonType| Binding{ input: Input::Gamepad(b), ctrl: false, shift: false, alt: false };
+    let digits = [KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4, KeyCode::Digit5, KeyCode::Digit6, KeyCode::Digit7, KeyCode::Digit8, KeyCode::Digit9];
+
+    let mut bindings: HashMap<Action, Vec<Binding>> = HashMap::new();
+    bindings.insert(Action::PanUp, vec![key(KeyCode::KeyW), pad(GamepadButtonType::DPadUp)]);
+    bindings.insert(Action::PanDown, vec![key(KeyCode::KeyS), pad(GamepadButtonType::DPadDown)]);
+    bindings.insert(Action::PanLeft, vec![key(KeyCode::KeyA), pad(GamepadButtonType::DPadLeft)]);
+    bindings.insert(Action::PanRight, vec![key(KeyCode::KeyD), pad(GamepadButtonType::DPadRight)]);
+    bindings.insert(Action::ZoomIn, vec![key(KeyCode::Equal), pad(GamepadButtonType::RightTrigger)]);
+    bindings.insert(Action::ZoomOut, vec![key(KeyCode::Minus), pad(GamepadButtonType::LeftTrigger)]);
+    bindings.insert(Action::Run, vec![key(KeyCode::Space), pad(GamepadButtonType::South)]);
+    bindings.insert(Action::Step, vec![key(KeyCode::KeyN), pad(GamepadButtonType::East)]);
+    bindings.insert(Action::Clear, vec![key(KeyCode::Backspace), pad(GamepadButtonType::Select)]);
+    bindings.insert(Action::Seed, vec![key(KeyCode::KeyG), pad(GamepadButtonType::North)]);
+    bindings.insert(Action::Benchmark, vec![key(KeyCode::KeyB)]);
+    bindings.insert(Action::ToggleVsync, vec![key(KeyCode::KeyV)]);
+    bindings.insert(Action::CycleRule, vec![key(KeyCode::KeyR), pad(GamepadButtonType::West)]);
+    bindings.insert(Action::CycleTheme, vec![ctrl(KeyCode::KeyT)]);
+    bindings.insert(Action::ToggleGrid, vec![key(KeyCode::KeyL)]);
+    bindings.insert(Action::FitView, vec![key(KeyCode::KeyF), pad(GamepadButtonType::Start)]);
+    bindings.insert(Action::Follow, vec![key(KeyCode::KeyT)]);
+    for i in 0..digits.len() {
+        bindings.insert(Action::Brush(i as u8 + 1), vec![key(digits[i])]);
+        bindings.insert(Action::SaveBookmark(i as u8 + 1), vec![ctrl(digits[i])]);
+        bindings.insert(Action::RecallBookmark(i as u8 + 1), vec![alt(digits[i])]);
+    }
+    bindings.insert(Action::SavePattern, vec![ctrl(KeyCode::KeyS)]);
+    bindings.insert(Action::LoadPattern, vec![ctrl(KeyCode::KeyO)]);
+    bindings.insert(Action::SaveMacrocell, vec![ctrl(KeyCode::KeyM)]);
+    bindings.insert(Action::LoadMacrocell, vec![ctrlShift(KeyCode::KeyM)]);
+    bindings.insert(Action::ImportImage, vec![ctrl(KeyCode::KeyI)]);
+    bindings.insert(Action::ImportImageInverted, vec![ctrlShift(KeyCode::KeyI)]);
+    bindings.insert(Action::ExportPng, vec![ctrl(KeyCode::KeyE)]);
+    bindings.insert(Action::ExportView, vec![ctrlShift(KeyCode::KeyE)]);
+    bindings.insert(Action::RecordGif, vec![ctrl(KeyCode::KeyG)]);
+    bindings.insert(Action::RecordPngs, vec![ctrlShift(KeyCode::KeyG)]);
+    bindings.insert(Action::Identify, vec![ctrl(KeyCode::KeyK)]);
+    bindings.insert(Action::ToggleObjects, vec![key(KeyCode::KeyO)]);
+    bindings.insert(Action::ObjectDistance, vec![shift(KeyCode::KeyO)]);
+    bindings.insert(Action::Copy, vec![ctrl(KeyCode::KeyC)]);
+    bindings.insert(Action::Cut, vec![ctrl(KeyCode::KeyX)]);
+    bindings.insert(Action::Paste, vec![ctrl(KeyCode::KeyV)]);
+    bindings.insert(Action::Cancel, vec![key(KeyCode::Escape)]);
+    bindings.insert(Action::Toggle3D, vec![key(KeyCode::Tab)]);
+    bindings.insert(Action::LayerUp, vec![key(KeyCode::PageUp)]);
+    bindings.insert(Action::LayerDown, vec![key(KeyCode::PageDown)]);
+    bindings.insert(Action::OrbitLeft, vec![key(KeyCode::ArrowLeft)]);
+    bindings.insert(Action::OrbitRight, vec![key(KeyCode::ArrowRight)]);
+    bindings.insert(Action::OrbitUp, vec![key(KeyCode::ArrowUp)]);
+    bindings.insert(Action::OrbitDown, vec![key(KeyCode::ArrowDown)]);
+    bindings.insert(Action::Help, vec![key(KeyCode::F1)]);
+    bindings.insert(Action::CommandPalette, vec![ctrl(KeyCode::KeyP)]);
+    bindings
+}
+
+// raccourcis par défaut, remplacés action par action par ceux du fichier (liste vide = action désactivée)
+pub fn load(path: &str) -> Result<HashMap<Action, Vec<Binding>>, String>{
+    let mut bindings = default_bindings();
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(bindings),
+        Err(e) => return Err(format!("cannot read {}: {}", path, e)),
+    };
+    let entries: HashMap<String, Vec<String>> = ron::from_str(&text).map_err(|e| format!("invalid {}: {}", path, e))?;
+    for (name, list) in entries{
+        let action = Action::from_name(&name).ok_or(format!("unknown action '{}' in {}", name, path))?;
+        let list: Vec<Binding> = list.iter().map(|b| Binding::parse(b)).collect::<Result<_, _>>()?;
+        bindings.insert(action, list);
+    }
+    Ok(bindings)
+}
+
+// date de modification du fichier, pour le recharger quand il change
+pub fn modified(path: &str) -> Option<SystemTime>{
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn parse_bindings(){
+        assert_eq!(Binding::parse("W"), Ok(Binding::key(KeyCode::KeyW)));
+        assert_eq!(Binding::parse("w"), Ok(Binding::key(KeyCode::KeyW)));
+        assert_eq!(Binding::parse("7"), Ok(Binding::key(KeyCode::Digit7)));
+        assert_eq!(Binding::parse("PageUp"), Ok(Binding::key(KeyCode::PageUp)));
+        assert_eq!(Binding::parse("ctrl + Shift+E"), Ok(Binding{ ctrl: true, shift: true, ..Binding::key(KeyCode::KeyE) }));
+        assert_eq!(Binding::parse("Alt+F1"), Ok(Binding{ alt: true, ..Binding::key(KeyCode::F1) }));
+        assert_eq!(Binding::parse("Gamepad:South"), Ok(Binding{ input: Input::Gamepad(GamepadButtonType::South), ..Binding::key(KeyCode::Space) }));
+        assert_eq!(Binding::parse("Ctrl+Gamepad:dpadup"), Ok(Binding{ input: Input::Gamepad(GamepadButtonType::DPadUp), ctrl: true, shift: false, alt: false }));
+        assert!(Binding::parse("Hyper+W").unwrap_err().contains("unknown modifier"));
+        assert!(Binding::parse("Banana").unwrap_err().contains("unknown key"));
+        assert!(Binding::parse("Gamepad:Jump").unwrap_err().contains("unknown gamepad button"));
+        // variante avec un champ : pas un nom de touche
+        assert!(Binding::parse("Gamepad:Other").is_err());
+        assert!(Binding::parse("Ctrl+").is_err());
+        assert!(Binding::parse("").is_err());
+        for text in ["Ctrl+Shift+E", "Alt+7", "W", "Gamepad:South", "ArrowUp"]{
+            assert_eq!(Binding::parse(text).unwrap().to_string(), text);
+        }
+    }
+}
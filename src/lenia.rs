@@ -0,0 +1,271 @@
+use std::f32::consts::PI;
+
+// automate continu de type Lenia / SmoothLife sur une grille bornée (torique) de LENIA_SIZE x LENIA_SIZE.
+// "Lenia,R13,T10,M0.15,S0.015,Ggauss" : rayon du noyau R, pas de temps 1/T,
+// fonction de croissance centrée sur M de largeur S (gauss, poly ou step).
+// A(t + 1/T) = clamp(A + G(K * A) / T, 0, 1), la convolution K * A est faite par FFT.
+
+pub const LENIA_SIZE: usize = 256;   // puissance de 2 pour la FFT
+pub const MAX_LENIA_RADIUS: usize = 64;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Growth{
+    Gaussian,
+    Polynomial,
+    Step,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct LeniaParams{
+    pub radius: usize,
+    pub steps: f32,     // T : nombre de pas par unité de temps
+    pub mu: f32,
+    pub sigma: f32,
+    pub growth: Growth,
+}
+
+impl LeniaParams{
+    pub fn isLenia(rulestring: &str) -> bool{
+        rulestring.to_ascii_lowercase().starts_with("lenia")
+    }
+
+    pub fn parse(rulestring: &str) -> Result<LeniaParams, String>{
+        let mut params = LeniaParams{
+            radius: 13,
+            steps: 10.,
+            mu: 0.15,
+            sigma: 0.015,
+            growth: Growth::Gaussian,
+        };
+        for field in rulestring.split(',').skip(1){
+            let field = field.trim();
+            if field.is_empty() {
+                continue;
+            }
+            let key = field.chars().next().unwrap();
+            let value = &field[key.len_utf8()..];
+            let number = || value.parse::<f32>().map_err(|_| format!("invalid value '{}' in '{}'", field, rulestring));
+            match key.to_ascii_uppercase() {
+                'R' => params.radius = number()? as usize,
+                'T' => params.steps = number()?,
+                'M' => params.mu = number()?,
+                'S' => params.sigma = number()?,
+                'G' => params.growth = match value.to_ascii_lowercase().as_str() {
+                    "gauss" => Growth::Gaussian,
+                    "poly" => Growth::Polynomial,
+                    "step" => Growth::Step,
+                    _ => return Err(format!("unknown growth function '{}' (gauss, poly or step)", value)),
+                },
+                _ => return Err(format!("unknown field '{}' in '{}'", field, rulestring)),
+            }
+        }
+        if params.radius < 1 || params.radius > MAX_LENIA_RADIUS {
+            return Err(format!("kernel radius must be between 1 and {}", MAX_LENIA_RADIUS));
+        }
+        if params.steps < 1. || params.sigma <= 0. {
+            return Err(format!("T must be at least 1 and S positive ('{}')", rulestring));
+        }
+        Ok(params)
+    }
+
+    pub fn to_string(&self) -> String{
+        let growth = match self.growth {
+            Growth::Gaussian => "gauss",
+            Growth::Polynomial => "poly",
+            Growth::Step => "step",
+        };
+        format!("Lenia,R{},T{},M{},S{},G{}", self.radius, self.steps, self.mu, self.sigma, growth)
+    }
+
+    fn growth(&self, u: f32) -> f32{
+        let d = u - self.mu;
+        match self.growth {
+            Growth::Gaussian => 2. * (-d * d / (2. * self.sigma * self.sigma)).exp() - 1.,
+            Growth::Polynomial => 2. * (1. - d * d / (9. * self.sigma * self.sigma)).max(0.).powi(4) - 1.,
+            Growth::Step => if d.abs() <= self.sigma { 1. } else { -1. },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct Complex{
+    pub re: f32,
+    pub im: f32,
+}
+
+impl Complex{
+    fn mul(self, other: Complex) -> Complex{
+        Complex{ re: self.re * other.re - self.im * other.im, im: self.re * other.im + self.im * other.re }
+    }
+}
+
+// FFT radix 2 en place, n doit être une puissance de 2
+pub fn fft(data: &mut [Complex], inverse: bool){
+    let n = data.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let angle = 2. * PI / len as f32 * if inverse { 1. } else { -1. };
+        let step = Complex{ re: angle.cos(), im: angle.sin() };
+        for start in (0..n).step_by(len) {
+            let mut w = Complex{ re: 1., im: 0. };
+            for k in 0..len / 2 {
+                let u = data[start + k];
+                let v = data[start + k + len / 2].mul(w);
+                data[start + k] = Complex{ re: u.re + v.re, im: u.im + v.im };
+                data[start + k + len / 2] = Complex{ re: u.re - v.re, im: u.im - v.im };
+                w = w.mul(step);
+            }
+        }
+        len <<= 1;
+    }
+    if inverse {
+        for c in data.iter_mut(){
+            c.re /= n as f32;
+            c.im /= n as f32;
+        }
+    }
+}
+
+// FFT 2D : toutes les lignes, puis toutes les colonnes
+pub fn fft2d(data: &mut [Complex], size: usize, inverse: bool){
+    for row in data.chunks_mut(size){
+        fft(row, inverse);
+    }
+    let mut column: Vec<Complex> = vec![Complex::default(); size];
+    for x in 0..size {
+        for y in 0..size {
+            column[y] = data[y * size + x];
+        }
+        fft(&mut column, inverse);
+        for y in 0..size {
+            data[y * size + x] = column[y];
+        }
+    }
+}
+
+// cellules rangées ligne par ligne, la ligne 0 en haut (comme l'image affichée)
+pub struct LeniaWorld{
+    pub params: LeniaParams,
+    pub cells: Vec<f32>,
+    kernel: Vec<Complex>,   // transformée de Fourier du noyau normalisé
+}
+
+impl LeniaWorld{
+    pub fn new(params: LeniaParams) -> LeniaWorld{
+        LeniaWorld{
+            params,
+            cells: vec![0.; LENIA_SIZE * LENIA_SIZE],
+            kernel: kernel_fft(&params),
+        }
+    }
+
+    pub fn set_params(&mut self, params: LeniaParams){
+        if params != self.params {
+            self.kernel = kernel_fft(&params);
+            self.params = params;
+        }
+    }
+
+    pub fn step(&mut self){
+        let mut field: Vec<Complex> = self.cells.iter().map(|a| Complex{ re: *a, im: 0. }).collect();
+        fft2d(&mut field, LENIA_SIZE, false);
+        for (f, k) in field.iter_mut().zip(self.kernel.iter()){
+            *f = f.mul(*k);
+        }
+        fft2d(&mut field, LENIA_SIZE, true);
+        let dt = 1. / self.params.steps;
+        for (a, u) in self.cells.iter_mut().zip(field.iter()){
+            *a = (*a + dt * self.params.growth(u.re)).clamp(0., 1.);
+        }
+    }
+
+    // remplit (ou efface si value = None) un disque, avec un bruit pseudo-aléatoire
+    pub fn paint(&mut self, col: i32, row: i32, radius: i32, value: Option<f32>, seed: &mut u32){
+        for dy in -radius..radius + 1 {
+            for dx in -radius..radius + 1 {
+                if dx * dx + dy * dy > radius * radius {
+                    continue;
+                }
+                let x = (col + dx).rem_euclid(LENIA_SIZE as i32) as usize;
+                let y = (row + dy).rem_euclid(LENIA_SIZE as i32) as usize;
+                self.cells[y * LENIA_SIZE + x] = match value {
+                    Some(v) => v * random(seed),
+                    None => 0.,
+                };
+            }
+        }
+    }
+
+    pub fn clear(&mut self){
+        self.cells.iter_mut().for_each(|a| *a = 0.);
+    }
+
+    // pixels RGBA de l'image, ligne 0 en haut
+    pub fn write_pixels(&self, pixels: &mut [u8]){
+        for (i, a) in self.cells.iter().enumerate(){
+            let [r, g, b] = colormap(*a);
+            pixels[i * 4] = r;
+            pixels[i * 4 + 1] = g;
+            pixels[i * 4 + 2] = b;
+            pixels[i * 4 + 3] = 255;
+        }
+    }
+}
+
+// noyau "exponentiel" de Lenia : exp(4 - 1 / (r (1 - r))) pour r = distance / R dans ]0, 1[
+fn kernel_fft(params: &LeniaParams) -> Vec<Complex>{
+    let mut kernel: Vec<Complex> = vec![Complex::default(); LENIA_SIZE * LENIA_SIZE];
+    let radius = params.radius as i32;
+    let mut total = 0.;
+    for dy in -radius..radius + 1 {
+        for dx in -radius..radius + 1 {
+            let r = ((dx * dx + dy * dy) as f32).sqrt() / radius as f32;
+            if r <= 0. || r >= 1. {
+                continue;
+            }
+            let k = (4. - 1. / (r * (1. - r))).exp();
+            let x = dx.rem_euclid(LENIA_SIZE as i32) as usize;
+            let y = dy.rem_euclid(LENIA_SIZE as i32) as usize;
+            kernel[y * LENIA_SIZE + x].re = k;
+            total += k;
+        }
+    }
+    for k in kernel.iter_mut(){
+        k.re /= total;
+    }
+    fft2d(&mut kernel, LENIA_SIZE, false);
+    kernel
+}
+
+// xorshift, suffisant pour semer des taches
+pub fn random(seed: &mut u32) -> f32{
+    *seed ^= *seed << 13;
+    *seed ^= *seed >> 17;
+    *seed ^= *seed << 5;
+    (*seed >> 8) as f32 / (1 << 24) as f32
+}
+
+// dégradé noir -> bleu -> vert -> jaune -> blanc
+pub fn colormap(a: f32) -> [u8; 3]{
+    const STOPS: [[f32; 3]; 5] = [[0., 0., 0.], [0.1, 0.1, 0.6], [0.1, 0.7, 0.5], [0.95, 0.85, 0.2], [1., 1., 1.]];
+    let t = a.clamp(0., 1.) * (STOPS.len() - 1) as f32;
+    let i = (t as usize).min(STOPS.len() - 2);
+    let f = t - i as f32;
+    let mut rgb = [0u8; 3];
+    for c in 0..3 {
+        rgb[c] = ((STOPS[i][c] + (STOPS[i + 1][c] - STOPS[i][c]) * f) * 255.) as u8;
+    }
+    rgb
+}
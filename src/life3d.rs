@@ -0,0 +1,42 @@
+use bevy::prelude::Entity;
+use bevy::utils::HashMap;
+use crate::rules::Rule;
+use crate::uVec3::uVec3;
+use crate::settings::update_count_limit;
+
+// Life 3D sur 26 voisins : chaque cellule vivante ajoute 1 au compteur de ses voisins,
+// puis naissance / survie d'après les compteurs. Comme pour ltl, pas de cellules stables.
+pub fn step(
+    rule: &Rule,
+    tileMap: &HashMap<uVec3, (Option<Entity>, i32, u8)>,
+    stableTileMap: &HashMap<uVec3, (Option<Entity>, i32, u8)>,
+    inTileMapRemove: &mut Vec<Entity>
+) -> HashMap<uVec3, (Option<Entity>, i32, u8)>{
+    let mut counts: HashMap<uVec3, usize> = HashMap::new();
+    for pos in tileMap.keys().chain(stableTileMap.keys()){
+        for dx in -1..2 {
+            for dy in -1..2 {
+                for dz in -1..2 {
+                    if !(dx == 0 && dy == 0 && dz == 0) {
+                        *counts.entry(uVec3::new(pos.x + dx, pos.y + dy, pos.z + dz)).or_insert(0) += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut newTileMap: HashMap<uVec3, (Option<Entity>, i32, u8)> = HashMap::new();
+    for (pos, (entity, _, _)) in tileMap.iter().chain(stableTileMap.iter()){
+        if rule.survival[*counts.get(pos).unwrap_or(&0)] {
+            newTileMap.insert(*pos, (*entity, update_count_limit(), 1));
+        } else if let Some(e) = entity {
+            inTileMapRemove.push(*e);
+        }
+    }
+    for (pos, count) in counts{
+        if rule.birth[count] && !tileMap.contains_key(&pos) && !stableTileMap.contains_key(&pos) {
+            newTileMap.insert(pos, (None, update_count_limit(), 1));
+        }
+    }
+    newTileMap
+}
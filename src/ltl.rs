@@ -0,0 +1,132 @@
+use bevy::prelude::Entity;
+use bevy::utils::HashMap;
+use crate::rules::{Neighbourhood, Rule};
+use crate::uVec3::uVec3;
+use crate::settings::update_count_limit;
+
+// Larger than Life : le plan est découpé en blocs de BLOCK x BLOCK cellules. Pour chaque bloc proche d'une
+// cellule vivante, les cellules vivantes du bloc élargi de la portée sont recopiées dans une grille dense,
+// puis comptées par sommes préfixes : en 2D pour Moore (O(1) par cellule), par ligne pour von Neumann
+// et le voisinage circulaire (O(portée) par cellule, avec les demi-largeurs de chaque ligne).
+// Pas de cellules stables ici, tout revient dans current_state.
+
+const BLOCK: i32 = 32;
+
+pub fn step(
+    rule: &Rule,
+    tileMap: &HashMap<uVec3, (Option<Entity>, i32, u8)>,
+    stableTileMap: &HashMap<uVec3, (Option<Entity>, i32, u8)>,
+    inTileMapRemove: &mut Vec<Entity>
+) -> HashMap<uVec3, (Option<Entity>, i32, u8)>{
+    let mut newTileMap: HashMap<uVec3, (Option<Entity>, i32, u8)> = HashMap::new();
+    if tileMap.is_empty() && stableTileMap.is_empty() {
+        return newTileMap;
+    }
+
+    let range = rule.range;
+    let rowWidths: Vec<i32> = (-range..range+1).map(|dy| rule.neighbourhood.row_width(dy, range)).collect();
+    // un bloc au moins aussi large que la portée : seuls les 8 blocs voisins comptent
+    let block = BLOCK.max(range);
+
+    // cellules vivantes rangées par bloc ; les cellules mourantes (états > 1) vieillissent sans compter de voisins
+    let mut live: HashMap<(i32, i32), Vec<(i32, i32)>> = HashMap::new();
+    for (pos, (entity, _, state)) in tileMap.iter().chain(stableTileMap.iter()){
+        if *state == 1 {
+            live.entry((pos.x.div_euclid(block), pos.y.div_euclid(block))).or_default().push((pos.x, pos.y));
+            continue;
+        }
+        let newState = if *state + 1 >= rule.states { 0 } else { *state + 1 };
+        if newState == 0 {
+            if let Some(e) = entity {
+                inTileMapRemove.push(*e);
+            }
+        } else {
+            newTileMap.insert(*pos, (*entity, update_count_limit(), newState));
+        }
+    }
+
+    let mut candidates: Vec<(i32, i32)> = live.keys()
+        .flat_map(|(bx, by)| (-1..2).flat_map(move |dy| (-1..2).map(move |dx| (bx + dx, by + dy))))
+        .collect();
+    candidates.sort_unstable();
+    candidates.dedup();
+
+    // grille du bloc élargi : size x size cellules, sommes préfixes sur width = size + 1 colonnes
+    let size = (block + 2 * range) as usize;
+    let width = size + 1;
+    let mut grid: Vec<bool> = vec![false; size * size];
+    let mut rows: Vec<u32> = vec![0; size * width];
+    let mut area: Vec<u32> = vec![0; width * width];
+    for (bx, by) in candidates{
+        let (x0, y0) = (bx * block - range, by * block - range);
+        grid.iter_mut().for_each(|g| *g = false);
+        let mut any = false;
+        for dy in -1..2 {
+            for dx in -1..2 {
+                for (x, y) in live.get(&(bx + dx, by + dy)).into_iter().flatten(){
+                    let (lx, ly) = (x - x0, y - y0);
+                    if lx >= 0 && ly >= 0 && (lx as usize) < size && (ly as usize) < size {
+                        grid[ly as usize * size + lx as usize] = true;
+                        any = true;
+                    }
+                }
+            }
+        }
+        if !any {
+            continue;
+        }
+        for y in 0..size {
+            for x in 0..size {
+                rows[y * width + x + 1] = rows[y * width + x] + grid[y * size + x] as u32;
+            }
+        }
+        if rule.neighbourhood == Neighbourhood::Moore {
+            for y in 0..size {
+                for x in 1..width {
+                    area[(y + 1) * width + x] = area[y * width + x] + rows[y * width + x];
+                }
+            }
+        }
+
+        let r = range as usize;
+        for ly in r..r + block as usize {
+            for lx in r..r + block as usize {
+                // nombre de voisins vivants, la cellule elle-même comprise
+                let count = if rule.neighbourhood == Neighbourhood::Moore {
+                    let (xa, xb, ya, yb) = (lx - r, lx + r + 1, ly - r, ly + r + 1);
+                    area[yb * width + xb] + area[ya * width + xa] - area[ya * width + xb] - area[yb * width + xa]
+                } else {
+                    rowWidths.iter().enumerate().map(|(i, w)| {
+                        let row = (ly + i - r) * width;
+                        rows[row + lx + *w as usize + 1] - rows[row + lx - *w as usize]
+                    }).sum()
+                };
+                let alive = grid[ly * size + lx];
+                if count == 0 && !alive {
+                    continue;
+                }
+                let pos = uVec3::new(x0 + lx as i32, y0 + ly as i32, 0);
+                let old = tileMap.get(&pos).or(stableTileMap.get(&pos));
+                // les cellules mourantes ont déjà été traitées
+                if !alive && old.is_some() {
+                    continue;
+                }
+                let entity = old.and_then(|(e, _, _)| *e);
+                let newState = if alive {
+                    let count = if rule.middle { count } else { count - 1 };
+                    if rule.survival[count as usize] { 1 } else if rule.states > 2 { 2 } else { 0 }
+                } else if rule.birth[count as usize] { 1 } else { 0 };
+
+                if newState == 0 {
+                    if let Some(e) = entity {
+                        inTileMapRemove.push(e);
+                    }
+                } else {
+                    newTileMap.insert(pos, (entity, update_count_limit(), newState));
+                }
+            }
+        }
+    }
+
+    newTileMap
+}
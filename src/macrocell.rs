@@ -0,0 +1,460 @@
+use bevy::utils::HashMap;
+use crate::uVec3::uVec3;
+
+// format macrocell de Golly (.mc) : l'univers est stocké comme un quadtree dont les nœuds identiques
+// ne sont écrits qu'une fois, ce qui garde compacts des motifs de plusieurs milliards de cellules.
+// "[M2] ..." puis "#R règle", puis un nœud par ligne, numérotés à partir de 1 (0 = nœud vide) :
+//   feuille 8x8 à 2 états : ".*$" (. morte, * vivante, $ fin de ligne)
+//   feuille 2x2 multi-états : "1 no ne so se" (états des 4 cellules)
+//   nœud de niveau k (côté 2^k) : "k no ne so se" (numéros des 4 enfants)
+// Le dernier nœud est la racine, centrée sur l'origine. Dans Golly le y va vers le bas, ici vers le haut.
+
+pub const MAX_LEVEL: u32 = 100;
+
+#[derive(Clone)]
+enum Node{
+    Leaf{ level: u32, cells: Vec<u8> },         // 2^level x 2^level états, ligne du haut en premier
+    Branch{ level: u32, children: [usize; 4] },  // no, ne, so, se
+}
+
+impl Node{
+    fn level(&self) -> u32{
+        match self {
+            Node::Leaf{ level, .. } | Node::Branch{ level, .. } => *level,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Macrocell{
+    pub rule: Option<String>,
+    nodes: Vec<Node>,       // nodes[i] est le nœud numéro i + 1
+    root: usize,            // 0 si l'univers est vide
+}
+
+impl Macrocell{
+    pub fn read(text: &str) -> Result<Macrocell, String>{
+        let mut lines = text.lines();
+        if !lines.next().map_or(false, |l| l.starts_with("[M2]")) {
+            return Err(String::from("not a macrocell file: missing [M2] header"));
+        }
+        let mut rule: Option<String> = None;
+        let mut nodes: Vec<Node> = Vec::new();
+        for line in lines{
+            let line = line.trim();
+            if let Some(r) = line.strip_prefix("#R") {
+                rule = Some(r.trim().to_string());
+                continue;
+            }
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let number = nodes.len() + 1;
+            if line.starts_with(['.', '*', '$']) {
+                let mut cells = vec![0u8; 64];
+                let (mut x, mut y) = (0, 0);
+                for c in line.chars(){
+                    match c {
+                        '.' => x += 1,
+                        '*' => {
+                            if x >= 8 || y >= 8 {
+                                return Err(format!("node {}: leaf larger than 8x8", number));
+                            }
+                            cells[y * 8 + x] = 1;
+                            x += 1;
+                        }
+                        '$' => {
+                            x = 0;
+                            y += 1;
+                        }
+                        _ => return Err(format!("node {}: unexpected '{}'", number, c)),
+                    }
+                }
+                nodes.push(Node::Leaf{ level: 3, cells });
+                continue;
+            }
+            let values: Vec<usize> = line.split_whitespace().map(|v| v.parse::<usize>()).collect::<Result<_, _>>()
+                .map_err(|_| format!("node {}: invalid line '{}'", number, line))?;
+            if values.len() != 5 {
+                return Err(format!("node {}: expected a level and 4 children", number));
+            }
+            let level = values[0] as u32;
+            if level == 1 {
+                if values[1..].iter().any(|s| *s > 255) {
+                    return Err(format!("node {}: state out of range", number));
+                }
+                nodes.push(Node::Leaf{ level: 1, cells: values[1..].iter().map(|s| *s as u8).collect() });
+                continue;
+            }
+            if level < 2 || level > MAX_LEVEL {
+                return Err(format!("node {}: invalid level {}", number, level));
+            }
+            let children = [values[1], values[2], values[3], values[4]];
+            for child in children{
+                if child >= number || (child != 0 && nodes[child - 1].level() != level - 1) {
+                    return Err(format!("node {}: invalid child {}", number, child));
+                }
+            }
+            nodes.push(Node::Branch{ level, children });
+        }
+        Ok(Macrocell{
+            rule,
+            root: nodes.len(),
+            nodes,
+        })
+    }
+
+    fn node(&self, index: usize) -> Option<&Node>{
+        if index == 0 { None } else { self.nodes.get(index - 1) }
+    }
+
+    // nombre de cellules vivantes (peut dépasser ce qui tient dans une TileMap)
+    pub fn population(&self) -> u128{
+        let mut memo: Vec<u128> = Vec::with_capacity(self.nodes.len());
+        for node in self.nodes.iter(){
+            let count = match node {
+                Node::Leaf{ cells, .. } => cells.iter().filter(|s| **s != 0).count() as u128,
+                Node::Branch{ children, .. } => children.iter().map(|c| if *c == 0 { 0 } else { memo[*c - 1] }).fold(0u128, |a, b| a.saturating_add(b)),
+            };
+            memo.push(count);
+        }
+        if self.root == 0 { 0 } else { memo[self.root - 1] }
+    }
+
+    // cellules dont les coordonnées (y vers le haut) sont entre min et max inclus, au plus limit cellules
+    pub fn cells_in(&self, min: (i64, i64), max: (i64, i64), limit: usize) -> Result<Vec<(uVec3, u8)>, String>{
+        let mut cells: Vec<(uVec3, u8)> = Vec::new();
+        if let Some(root) = self.node(self.root) {
+            let half = 1i128 << (root.level() - 1);
+            // en coordonnées de Golly : y vers le bas
+            let (gmin, gmax) = ((min.0 as i128, -(max.1 as i128)), (max.0 as i128, -(min.1 as i128)));
+            self.collect(self.root, (-half, -half), gmin, gmax, limit, &mut cells)?;
+        }
+        Ok(cells)
+    }
+
+    fn collect(&self, index: usize, corner: (i128, i128), min: (i128, i128), max: (i128, i128), limit: usize, cells: &mut Vec<(uVec3, u8)>) -> Result<(), String>{
+        let Some(node) = self.node(index) else {
+            return Ok(());
+        };
+        let size = 1i128 << node.level();
+        if corner.0 > max.0 || corner.1 > max.1 || corner.0 + size <= min.0 || corner.1 + size <= min.1 {
+            return Ok(());
+        }
+        match node {
+            Node::Leaf{ cells: states, .. } => {
+                for (i, state) in states.iter().enumerate(){
+                    let (x, y) = (corner.0 + (i as i128 % size), corner.1 + (i as i128 / size));
+                    if *state == 0 || x < min.0 || x > max.0 || y < min.1 || y > max.1 {
+                        continue;
+                    }
+                    if cells.len() >= limit {
+                        return Err(format!("more than {} cells in the region", limit));
+                    }
+                    cells.push((uVec3::new(x as i32, -y as i32, 0), *state));
+                }
+            }
+            Node::Branch{ children, .. } => {
+                let half = size / 2;
+                let offsets = [(0, 0), (half, 0), (0, half), (half, half)];
+                for (child, (dx, dy)) in children.iter().zip(offsets){
+                    self.collect(*child, (corner.0 + dx, corner.1 + dy), min, max, limit, cells)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // quadtree des cellules (y vers le haut), les sous-arbres identiques sont partagés
+    pub fn from_cells(cells: &[(uVec3, u8)], rule: &str, multistate: bool) -> Macrocell{
+        let leafLevel = if multistate { 1 } else { 3 };
+        // coordonnées de Golly
+        let points: Vec<(i64, i64, u8)> = cells.iter().filter(|(_, s)| *s != 0).map(|(p, s)| (p.x as i64, -(p.y as i64), *s)).collect();
+        let extent = points.iter().map(|(x, y, _)| x.abs().max(y.abs()) + 1).max().unwrap_or(0);
+        let mut level = leafLevel;
+        while (1i64 << (level - 1)) < extent {
+            level += 1;
+        }
+        let mut builder = Builder{
+            nodes: Vec::new(),
+            known: HashMap::new(),
+            multistate,
+        };
+        let half = 1i64 << (level - 1);
+        let root = builder.build(level, (-half, -half), points);
+        Macrocell{
+            rule: Some(rule.to_string()),
+            nodes: builder.nodes,
+            root,
+        }
+    }
+
+    // copie de l'univers où la région [min, max] (y vers le haut) est remplacée par cells, les cellules de cells
+    // hors de la région sont posées par dessus ; les sous-arbres hors de la région sont repris sans être parcourus
+    pub fn merge(&self, min: (i64, i64), max: (i64, i64), cells: &[(uVec3, u8)], rule: &str, multistate: bool) -> Macrocell{
+        // des feuilles 2x2 dans le fichier d'origine ne peuvent pas redevenir des feuilles 8x8
+        let multistate = multistate || self.nodes.iter().any(|n| matches!(n, Node::Leaf{ level: 1, .. }));
+        let leafLevel = if multistate { 1 } else { 3 };
+        let points: Vec<(i64, i64, u8)> = cells.iter().filter(|(_, s)| *s != 0).map(|(p, s)| (p.x as i64, -(p.y as i64), *s)).collect();
+        let extent = points.iter().map(|(x, y, _)| x.abs().max(y.abs()) + 1).max().unwrap_or(0);
+        let mut level = self.node(self.root).map_or(leafLevel, |root| root.level()).max(leafLevel);
+        while (1i64 << (level - 1)) < extent {
+            level += 1;
+        }
+        let mut builder = Builder{
+            nodes: Vec::new(),
+            known: HashMap::new(),
+            multistate,
+        };
+        // en coordonnées de Golly : y vers le bas
+        let region = ((min.0, -max.1), (max.0, -min.1));
+        let half = 1i64 << (level - 1);
+        let root = builder.merge(self, level, (-half, -half), points, region, &mut HashMap::new());
+        Macrocell{
+            rule: Some(rule.to_string()),
+            nodes: builder.nodes,
+            root,
+        }
+    }
+
+    // nœud de l'arbre d'origine qui couvre exactement le carré (0 s'il est vide), None s'il faut descendre plus bas
+    fn node_at(&self, level: u32, corner: (i64, i64)) -> Option<usize>{
+        let Some(root) = self.node(self.root) else {
+            return Some(0);
+        };
+        let size = 1i128 << level;
+        let (mut index, mut nodeLevel) = (self.root, root.level());
+        let mut nodeCorner = (-(1i128 << (nodeLevel - 1)), -(1i128 << (nodeLevel - 1)));
+        let (x, y) = (corner.0 as i128, corner.1 as i128);
+        let nodeSize = 1i128 << nodeLevel;
+        if x >= nodeCorner.0 + nodeSize || y >= nodeCorner.1 + nodeSize || x + size <= nodeCorner.0 || y + size <= nodeCorner.1 {
+            return Some(0);
+        }
+        if level > nodeLevel {
+            return None;
+        }
+        loop {
+            if nodeLevel == level {
+                return Some(index);
+            }
+            let Some(Node::Branch{ children, .. }) = self.node(index) else {
+                return None;
+            };
+            let half = 1i128 << (nodeLevel - 1);
+            let (east, south) = (x >= nodeCorner.0 + half, y >= nodeCorner.1 + half);
+            index = children[east as usize + 2 * south as usize];
+            if index == 0 {
+                return Some(0);
+            }
+            nodeCorner = (nodeCorner.0 + if east { half } else { 0 }, nodeCorner.1 + if south { half } else { 0 });
+            nodeLevel -= 1;
+        }
+    }
+
+    pub fn write(&self) -> String{
+        let mut text = String::from("[M2] (GameOfLife2D)\n");
+        if let Some(rule) = &self.rule {
+            text.push_str(&format!("#R {}\n", rule));
+        }
+        for node in self.nodes.iter(){
+            match node {
+                Node::Leaf{ level: 1, cells } => text.push_str(&format!("1 {} {} {} {}\n", cells[0], cells[1], cells[2], cells[3])),
+                Node::Leaf{ cells, .. } => {
+                    let last = cells.iter().rposition(|s| *s != 0).map_or(0, |i| i / 8 + 1);
+                    for row in cells.chunks(8).take(last){
+                        let width = row.iter().rposition(|s| *s != 0).map_or(0, |i| i + 1);
+                        text.extend(row[..width].iter().map(|s| if *s != 0 { '*' } else { '.' }));
+                        text.push('$');
+                    }
+                    text.push('\n');
+                }
+                Node::Branch{ level, children } => text.push_str(&format!("{} {} {} {} {}\n", level, children[0], children[1], children[2], children[3])),
+            }
+        }
+        text
+    }
+}
+
+// fichier dont seule la région [min, max] (y vers le haut) a été chargée dans la TileMap (Ctrl+Shift+M) :
+// Ctrl+M y réécrit la région éditée au lieu de remplacer tout le fichier par la fenêtre
+pub struct Loaded{
+    pub universe: Macrocell,
+    pub min: (i64, i64),
+    pub max: (i64, i64),
+}
+
+struct Builder{
+    nodes: Vec<Node>,
+    known: HashMap<(u32, Vec<u8>, [usize; 4]), usize>,
+    multistate: bool,
+}
+
+impl Builder{
+    // les enfants sont ajoutés avant leur parent, comme le demande le format
+    fn build(&mut self, level: u32, corner: (i64, i64), points: Vec<(i64, i64, u8)>) -> usize{
+        if points.is_empty() {
+            return 0;
+        }
+        let size = 1i64 << level;
+        let leafLevel = if self.multistate { 1 } else { 3 };
+        let (key, node) = if level == leafLevel {
+            let mut cells = vec![0u8; (size * size) as usize];
+            for (x, y, state) in points{
+                cells[((y - corner.1) * size + (x - corner.0)) as usize] = if self.multistate { state } else { 1 };
+            }
+            ((level, cells.clone(), [0; 4]), Node::Leaf{ level, cells })
+        } else {
+            let half = size / 2;
+            let mut quadrants: [Vec<(i64, i64, u8)>; 4] = Default::default();
+            for (x, y, state) in points{
+                let q = (x >= corner.0 + half) as usize + 2 * (y >= corner.1 + half) as usize;
+                quadrants[q].push((x, y, state));
+            }
+            let mut children = [0usize; 4];
+            let offsets = [(0, 0), (half, 0), (0, half), (half, half)];
+            for (q, points) in quadrants.into_iter().enumerate(){
+                children[q] = self.build(level - 1, (corner.0 + offsets[q].0, corner.1 + offsets[q].1), points);
+            }
+            ((level, Vec::new(), children), Node::Branch{ level, children })
+        };
+        self.add(key, node)
+    }
+
+    fn add(&mut self, key: (u32, Vec<u8>, [usize; 4]), node: Node) -> usize{
+        if let Some(index) = self.known.get(&key) {
+            return *index;
+        }
+        self.nodes.push(node);
+        self.known.insert(key, self.nodes.len());
+        self.nodes.len()
+    }
+
+    fn branch(&mut self, level: u32, children: [usize; 4]) -> usize{
+        if children == [0; 4] {
+            return 0;
+        }
+        self.add((level, Vec::new(), children), Node::Branch{ level, children })
+    }
+
+    // sous-arbre de source recopié, les feuilles 8x8 redécoupées si le nouvel arbre a des feuilles 2x2
+    fn copy(&mut self, source: &Macrocell, index: usize, memo: &mut HashMap<usize, usize>) -> usize{
+        if let Some(copied) = memo.get(&index) {
+            return *copied;
+        }
+        let leafLevel = if self.multistate { 1 } else { 3 };
+        let copied = match source.node(index) {
+            None => 0,
+            Some(Node::Leaf{ level, cells }) if *level == leafLevel => self.add((*level, cells.clone(), [0; 4]), Node::Leaf{ level: *level, cells: cells.clone() }),
+            Some(Node::Leaf{ level, cells }) => {
+                let size = 1usize << level;
+                let points = cells.iter().enumerate().filter(|(_, s)| **s != 0).map(|(i, s)| ((i % size) as i64, (i / size) as i64, *s)).collect();
+                self.build(*level, (0, 0), points)
+            }
+            Some(Node::Branch{ level, children }) => {
+                let children = children.map(|c| self.copy(source, c, memo));
+                self.branch(*level, children)
+            }
+        };
+        memo.insert(index, copied);
+        copied
+    }
+
+    fn merge(&mut self, source: &Macrocell, level: u32, corner: (i64, i64), points: Vec<(i64, i64, u8)>, region: ((i64, i64), (i64, i64)), memo: &mut HashMap<usize, usize>) -> usize{
+        let size = 1i64 << level;
+        let ((x0, y0), (x1, y1)) = region;
+        if corner.0 >= x0 && corner.1 >= y0 && corner.0 + size - 1 <= x1 && corner.1 + size - 1 <= y1 {
+            return self.build(level, corner, points);
+        }
+        let outside = corner.0 > x1 || corner.1 > y1 || corner.0 + size <= x0 || corner.1 + size <= y0;
+        if outside && points.is_empty() {
+            if let Some(index) = source.node_at(level, corner) {
+                return self.copy(source, index, memo);
+            }
+        }
+        let leafLevel = if self.multistate { 1 } else { 3 };
+        if level == leafLevel {
+            // cellules d'origine hors de la région, puis les nouvelles par dessus
+            let mut cells: HashMap<(i64, i64), u8> = HashMap::new();
+            let mut old: Vec<(uVec3, u8)> = Vec::new();
+            if let Some(root) = source.node(source.root) {
+                let half = 1i128 << (root.level() - 1);
+                let square = ((corner.0 as i128, corner.1 as i128), ((corner.0 + size - 1) as i128, (corner.1 + size - 1) as i128));
+                let _ = source.collect(source.root, (-half, -half), square.0, square.1, usize::MAX, &mut old);
+            }
+            for (pos, state) in old{
+                let (x, y) = (pos.x as i64, -(pos.y as i64));
+                if x < x0 || x > x1 || y < y0 || y > y1 {
+                    cells.insert((x, y), state);
+                }
+            }
+            for (x, y, state) in points{
+                cells.insert((x, y), state);
+            }
+            return self.build(level, corner, cells.into_iter().map(|((x, y), s)| (x, y, s)).collect());
+        }
+        let half = size / 2;
+        let mut quadrants: [Vec<(i64, i64, u8)>; 4] = Default::default();
+        for (x, y, state) in points{
+            let q = (x >= corner.0 + half) as usize + 2 * (y >= corner.1 + half) as usize;
+            quadrants[q].push((x, y, state));
+        }
+        let mut children = [0usize; 4];
+        let offsets = [(0, 0), (half, 0), (0, half), (half, half)];
+        for (q, points) in quadrants.into_iter().enumerate(){
+            children[q] = self.merge(source, level - 1, (corner.0 + offsets[q].0, corner.1 + offsets[q].1), points, region, memo);
+        }
+        self.branch(level, children)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn sorted(mut cells: Vec<(uVec3, u8)>) -> Vec<(i32, i32, u8)>{
+        let mut cells: Vec<(i32, i32, u8)> = cells.drain(..).map(|(p, s)| (p.x, p.y, s)).collect();
+        cells.sort();
+        cells
+    }
+
+    fn cells(list: &[(i32, i32, u8)]) -> Vec<(uVec3, u8)>{
+        list.iter().map(|(x, y, s)| (uVec3::new(*x, *y, 0), *s)).collect()
+    }
+
+    const ALL: (i64, i64) = (i32::MAX as i64, i32::MAX as i64);
+    const NONE: (i64, i64) = (i32::MIN as i64, i32::MIN as i64);
+
+    #[test]
+    fn read_write_round_trip(){
+        // glider et bloc éloigné, puis cellules multi-états
+        let twoStates = cells(&[(0, 1, 1), (1, 0, 1), (-1, -1, 1), (0, -1, 1), (1, -1, 1), (1000, -700, 1), (1001, -700, 1), (1000, -701, 1), (1001, -701, 1)]);
+        let multistate = cells(&[(0, 0, 1), (1, 0, 2), (-3, 5, 3), (40, 40, 1)]);
+        for (list, multi) in [(twoStates, false), (multistate, true)]{
+            let text = Macrocell::from_cells(&list, "B3/S23", multi).write();
+            let universe = Macrocell::read(&text).unwrap();
+            assert_eq!(universe.rule.as_deref(), Some("B3/S23"));
+            assert_eq!(universe.population(), list.len() as u128);
+            assert_eq!(sorted(universe.cells_in(NONE, ALL, usize::MAX).unwrap()), sorted(list.clone()));
+            assert_eq!(Macrocell::read(&universe.write()).unwrap().write(), text);
+        }
+    }
+
+    #[test]
+    fn merge_keeps_cells_outside_the_window(){
+        let far = [(100_000, 100_000, 1), (100_001, 100_000, 1), (100_000, 99_999, 1), (100_001, 99_999, 1)];
+        let near = [(0, 0, 1), (1, 0, 1), (2, 0, 1), (-5, 3, 1)];
+        let universe = Macrocell::from_cells(&cells(&[&far[..], &near[..]].concat()), "B3/S23", false);
+        let (min, max) = ((-10, -10), (10, 10));
+        assert_eq!(universe.cells_in(min, max, usize::MAX).unwrap().len(), near.len());
+
+        // fenêtre éditée : le clignotant tourne, une cellule effacée, une autre posée hors de la fenêtre
+        let edited = [(1, 1, 1), (1, 0, 1), (1, -1, 1), (15, 0, 1)];
+        let merged = Macrocell::read(&universe.merge(min, max, &cells(&edited), "B3/S23", false).write()).unwrap();
+        assert_eq!(sorted(merged.cells_in(NONE, ALL, usize::MAX).unwrap()), sorted(cells(&[&far[..], &edited[..]].concat())));
+
+        // fichier à deux états réécrit avec une règle à plusieurs états : les feuilles sont redécoupées
+        let merged = universe.merge(min, max, &cells(&[(0, 0, 2)]), "23/3/3", true);
+        assert!(merged.write().lines().skip(2).all(|l| !l.starts_with(['.', '*', '$'])));
+        assert_eq!(sorted(merged.cells_in(NONE, ALL, usize::MAX).unwrap()), sorted(cells(&[&far[..], &[(0, 0, 2)]].concat())));
+    }
+}
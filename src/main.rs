@@ -1,5 +1,29 @@
+mod apgcode;
+mod cli;
+mod clipboard;
 mod components;
+mod export;
+mod gif;
+mod grid;
+mod import;
+mod keybindings;
+mod life3d;
+mod lenia;
+mod macrocell;
+mod ltl;
+mod objects;
+mod oned;
+mod palette;
+mod record;
+mod remote;
+mod rle;
+mod rules;
+mod settings;
+mod ruletable;
 mod systems;
+mod theme;
+mod topology;
+mod turmite;
 mod uVec3;
 
 use bevy::{
@@ -25,20 +49,83 @@ use bevy::window::{PresentMode, WindowTheme};
 
 const WHITE: Color = Color::linear_rgba(1., 1., 1., 1.);
 const INVISIBLE: Color = Color::linear_rgba(0., 0., 0., 0.);
-const CAMERA_SPEED: f32 = 500.0;
-const ZOOM_MULTIPLIER: f32 = 5.;
-const UPDATE_COUNT_LIMIT: i32 = 20;
-const UNSTBLE_CHANGER_LIMIT: i32 = 3;
+const PATTERN_FILE: &str = "pattern.rle";
+const PATTERNS_DIR: &str = "patterns";     // motifs proposés par la palette de commandes (Ctrl+P)
+const SETTINGS_FILE: &str = "settings.ron";     // réglages, rechargés quand le fichier change
+const KEYBINDINGS_FILE: &str = "keybindings.ron";  // raccourcis, rechargés quand le fichier change
+const IMAGE_FILE: &str = "pattern.png";     // image importée par Ctrl+I
+const MACROCELL_FILE: &str = "pattern.mc";
+const MAX_MACROCELL_CELLS: usize = 2_000_000;   // cellules chargées au plus depuis un fichier macrocell
+const MINIMAP_RESOLUTION: u32 = 128;    // pixels de l'image de la minimap
+const MINIMAP_SIZE: f32 = 200.;         // taille affichée, en pixels d'écran
+const MINIMAP_REFRESH: f32 = 0.25;      // secondes entre deux mises à jour
+const LOD_SCALE: f32 = 2.;              // au-delà de cette échelle de projection, affichage en blocs de densité
+const MAX_DENSITY_SIZE: u32 = 2048;     // taille maximale de l'image de densité
+const DENSITY_REFRESH: f32 = 0.25;      // secondes entre deux recalculs de l'image de densité
+const OBJECTS_REFRESH: f32 = 0.25;      // secondes entre deux séparations en objets (touche O)
 
 
 fn main() {
-    App::new()
+    // GameOfLife2D record <pattern.rle> <sortie> : enregistrement sans ouvrir de fenêtre
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map_or(false, |a| a == "record") {
+        if args[2..].iter().any(|a| a == "-h" || a == "--help") {
+            println!("{}", record::USAGE);
+            return;
+        }
+        match record::run_cli(&args[2..]) {
+            Ok(message) => println!("{}", message),
+            Err(e) => {
+                eprintln!("{}\n{}", e, record::USAGE);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    let mut options = match cli::parse(&args[1..]) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", cli::USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("{}\ntry 'GameOfLife2D --help'", e);
+            std::process::exit(1);
+        }
+    };
+    let settings = match settings::Settings::load(SETTINGS_FILE, &options.overrides) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    settings.apply();
+    if let Err(e) = options.load_pattern(&settings.import_settings(false)) {
+        eprintln!("{}\ntry 'GameOfLife2D --help'", e);
+        std::process::exit(1);
+    }
+    let remote = match options.remotePort {
+        Some(port) => match remote::start(port) {
+            Ok(remote) => Some(remote),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    let mut app = App::new();
+    if let Some(remote) = remote {
+        app.insert_resource(remote);
+    }
+    app
         .add_plugins((DefaultPlugins.set(WindowPlugin {
                             primary_window: Some(Window {
                                 title: "Game Of Life 2D".into(),
                                 name: Some("gameoflife2d.frisk197".into()),
-                                resolution: (1920., 1080.).into(),
-                                present_mode: PresentMode::AutoNoVsync,
+                                resolution: (settings.windowWidth, settings.windowHeight).into(),
+                                present_mode: if settings.vsync { PresentMode::AutoVsync } else { PresentMode::AutoNoVsync },
                                 // Tells Wasm to resize the window according to the available canvas
                                 fit_canvas_to_parent: true,
                                 // Tells Wasm not to override default event handling, like F5, Ctrl+R etc.
@@ -59,8 +146,19 @@ fn main() {
                       // LogDiagnosticsPlugin::default(),
                       // FrameTimeDiagnosticsPlugin,
         ))
-        .add_systems(Startup, (systems::setup_camera, systems::setup_batching, systems::setup_simulation, systems::setup_refresh_timer))
-        .add_systems(Update, (systems::camera_mouvement, systems::tile_placement.before(systems::display_tilemap), systems::display_tilemap.after(systems::hide_off_tiles), systems::run_simulation.after(systems::hide_off_tiles), systems::place_patterns.before(systems::display_tilemap), systems::toggle_vsync, systems::hide_off_tiles))
+        .insert_resource(settings::SettingsSource{
+            path: SETTINGS_FILE.to_string(),
+            overrides: options.overrides.clone(),
+            lastModified: settings::modified(SETTINGS_FILE),
+            lastCheck: 0.,
+        })
+        .insert_resource(settings)
+        .insert_resource(options)
+        .add_systems(Startup, (systems::setup_camera, systems::setup_batching, systems::setup_simulation, systems::setup_refresh_timer, systems::setup_lenia, systems::setup_minimap.after(systems::setup_camera), systems::setup_density, systems::setup_theme, systems::setup_grid_overlay, systems::setup_recorder, systems::setup_object_labels.after(systems::setup_camera), systems::setup_stamp, systems::setup_controls, systems::setup_help_overlay.after(systems::setup_camera), systems::setup_command_palette.after(systems::setup_camera), systems::apply_command_line.after(systems::setup_simulation)))
+        .add_systems(PreUpdate, systems::update_controls.after(InputSystem))
+        .add_systems(Update, (systems::camera_mouvement, systems::tile_placement.before(systems::display_tilemap), systems::display_tilemap.after(systems::hide_off_tiles), systems::run_simulation.after(systems::hide_off_tiles), systems::place_patterns.before(systems::display_tilemap), systems::toggle_vsync, systems::hide_off_tiles, systems::cycle_rule.before(systems::run_simulation), systems::select_brush.after(systems::cycle_rule), systems::update_state_palette.before(systems::display_tilemap), systems::save_load_pattern.before(systems::display_tilemap), systems::control_view3d.after(systems::cycle_rule), systems::display_cubes.after(systems::run_simulation)))
+        .add_systems(Update, (systems::place_ants.before(systems::run_simulation), systems::display_ants.after(systems::run_simulation), systems::edit_lenia.before(systems::run_lenia), systems::run_lenia.after(systems::cycle_rule).after(systems::run_simulation), systems::update_minimap.after(systems::camera_mouvement), systems::minimap_navigation.before(systems::camera_mouvement), systems::camera_shortcuts.after(systems::camera_mouvement).after(systems::run_simulation), systems::display_density.after(systems::display_tilemap), systems::cycle_theme.before(systems::update_state_palette), systems::update_grid_overlay.after(systems::camera_shortcuts), systems::export_png.after(systems::run_simulation), systems::select_record_region.before(systems::tile_placement), systems::record_animation, systems::import_image.before(systems::display_tilemap), systems::save_load_macrocell.before(systems::display_tilemap), systems::identify_object, systems::label_objects.after(systems::run_simulation), systems::clipboard_stamp.after(systems::tile_placement).before(systems::display_tilemap), systems::reload_settings.before(systems::camera_mouvement)))
+        .add_systems(Update, (systems::toggle_help, systems::command_palette.before(systems::clipboard_stamp), systems::remote_control.before(systems::run_simulation).before(systems::display_tilemap), systems::display_topology))
         .run();
 }
 
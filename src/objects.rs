@@ -0,0 +1,91 @@
+use bevy::math::Vec2;
+use bevy::utils::HashMap;
+use crate::apgcode;
+use crate::grid::GridType;
+use crate::rules::Rule;
+use crate::uVec3::uVec3;
+
+// séparation des cellules vivantes en objets : deux cellules sont dans le même objet
+// si elles sont à une distance (de Tchebychev) inférieure ou égale à `distance`, de proche en proche
+
+pub const MAX_CLASSIFIED_SIZE: usize = 2000;
+pub const MAX_CACHED_CLASSIFICATIONS: usize = 4096;
+
+pub struct Object{
+    pub cells: Vec<uVec3>,      // triées, pour comparer deux objets
+    pub min: (i32, i32),        // boîte englobante, en cellules
+    pub max: (i32, i32),
+    pub worldMin: Vec2,         // bords de la boîte dans le monde
+    pub worldMax: Vec2,
+}
+
+fn find(parents: &mut Vec<usize>, mut i: usize) -> usize{
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+pub fn label(cells: &[uVec3], distance: i32, grid: GridType) -> Vec<Object>{
+    let index: HashMap<(i32, i32), usize> = cells.iter().enumerate().map(|(i, p)| ((p.x, p.y), i)).collect();
+    let mut parents: Vec<usize> = (0..cells.len()).collect();
+    for (i, p) in cells.iter().enumerate(){
+        for dy in -distance..distance + 1 {
+            for dx in -distance..distance + 1 {
+                if let Some(j) = index.get(&(p.x + dx, p.y + dy)) {
+                    let (a, b) = (find(&mut parents, i), find(&mut parents, *j));
+                    if a != b {
+                        parents[a] = b;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<uVec3>> = HashMap::new();
+    for (i, p) in cells.iter().enumerate(){
+        let root = find(&mut parents, i);
+        groups.entry(root).or_default().push(*p);
+    }
+    let mut objects: Vec<Object> = groups.into_values().map(|mut cells| {
+        cells.sort_by_key(|p| (p.x, p.y, p.z));
+        let mut object = Object{
+            min: (i32::MAX, i32::MAX),
+            max: (i32::MIN, i32::MIN),
+            worldMin: Vec2::splat(f32::MAX),
+            worldMax: Vec2::splat(f32::MIN),
+            cells: Vec::new(),
+        };
+        for p in cells.iter(){
+            object.min = (object.min.0.min(p.x), object.min.1.min(p.y));
+            object.max = (object.max.0.max(p.x), object.max.1.max(p.y));
+            let w = grid.to_world(p).truncate();
+            object.worldMin = object.worldMin.min(w - Vec2::splat(0.5));
+            object.worldMax = object.worldMax.max(w + Vec2::splat(0.5));
+        }
+        object.cells = cells;
+        object
+    }).collect();
+    // ordre stable d'une mise à jour à l'autre, pour garder les couleurs
+    objects.sort_by_key(|o| (o.min.0, o.min.1));
+    objects
+}
+
+// cellules ramenées au coin (0, 0), dans l'ordre de object.cells : la classification ne dépend que de la forme
+pub fn shape(object: &Object) -> Vec<(i32, i32)>{
+    object.cells.iter().map(|p| (p.x - object.min.0, p.y - object.min.1)).collect()
+}
+
+// "still life", "oscillator", "spaceship" ou "unknown", suivi de l'apgcode quand il est connu
+pub fn classify(cells: &[uVec3], rule: &Rule) -> String{
+    if cells.len() > MAX_CLASSIFIED_SIZE {
+        return String::from("unknown (too large to classify)");
+    }
+    match apgcode::identify(cells, rule) {
+        Ok(code) if code.starts_with("xs") => format!("still life {}", code),
+        Ok(code) if code.starts_with("xp") => format!("oscillator {}", code),
+        Ok(code) => format!("spaceship {}", code),
+        Err(e) => format!("unknown ({})", e),
+    }
+}
@@ -0,0 +1,143 @@
+use bevy::prelude::Entity;
+use bevy::utils::{HashMap, HashSet};
+use crate::uVec3::uVec3;
+use crate::settings::update_count_limit;
+
+// automates cellulaires 1D de Wolfram, dessinés en diagramme espace-temps :
+// la génération suivante est écrite sur la ligne du dessous, l'historique reste dans les lignes du dessus.
+// W30 : règle élémentaire (voisins gauche, centre, droite -> bit 4*g + 2*c + d du code)
+// T20R2 : règle totaliste de portée 2 (bit s du code, s = nombre de cellules vivantes parmi les 2r+1)
+
+pub const MAX_RANGE_1D: i32 = 31;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Rule1D{
+    pub code: u64,
+    pub range: i32,
+    pub totalistic: bool,
+}
+
+impl Rule1D{
+    pub fn is1D(rulestring: &str) -> bool{
+        let upper = rulestring.to_ascii_uppercase();
+        (upper.starts_with('W') && upper.len() > 1 && upper[1..].chars().all(|c| c.is_ascii_digit()))
+            || (upper.starts_with('T') && upper.contains('R') && upper[1..].chars().all(|c| c.is_ascii_digit() || c == 'R'))
+    }
+
+    pub fn parse(rulestring: &str) -> Result<Rule1D, String>{
+        let upper = rulestring.to_ascii_uppercase();
+        let rule = if let Some(code) = upper.strip_prefix('W') {
+            let code: u64 = code.parse().map_err(|_| format!("invalid elementary rule '{}'", rulestring))?;
+            if code > 255 {
+                return Err(format!("elementary rules go from W0 to W255 ('{}')", rulestring));
+            }
+            Rule1D{ code, range: 1, totalistic: false }
+        } else {
+            let (code, range) = upper[1..].split_once('R').ok_or(format!("invalid totalistic rule '{}'", rulestring))?;
+            let code: u64 = code.parse().map_err(|_| format!("invalid code in '{}'", rulestring))?;
+            let range: i32 = range.parse().map_err(|_| format!("invalid range in '{}'", rulestring))?;
+            if range < 1 || range > MAX_RANGE_1D {
+                return Err(format!("range must be between 1 and {} ('{}')", MAX_RANGE_1D, rulestring));
+            }
+            if (2 * range + 2) < 64 && code >> (2 * range + 2) != 0 {
+                return Err(format!("code {} is too large for range {}", code, range));
+            }
+            Rule1D{ code, range, totalistic: true }
+        };
+        // comme B0 en 2D : un fond vide deviendrait entièrement vivant
+        if rule.code & 1 == 1 {
+            return Err(format!("rules where empty cells are born are not supported ('{}')", rulestring));
+        }
+        Ok(rule)
+    }
+
+    pub fn to_string(&self) -> String{
+        if self.totalistic {
+            format!("T{}R{}", self.code, self.range)
+        } else {
+            format!("W{}", self.code)
+        }
+    }
+
+    fn next_alive(&self, neighbours: &[bool]) -> bool{
+        let index = if self.totalistic {
+            neighbours.iter().filter(|alive| **alive).count()
+        } else {
+            neighbours.iter().fold(0, |acc, alive| acc * 2 + *alive as usize)
+        };
+        self.code >> index & 1 == 1
+    }
+
+    // calcule la ligne sous la ligne courante ; les lignes au-dessus passent dans la map stable.
+    // La ligne courante `row` est gardée dans la TileMap et avance d'une ligne à chaque pas, même quand elle s'est
+    // éteinte ; une ligne plus basse dans la map active (nouveau motif dessiné) la remplace.
+    // L'historique n'est parcouru que si `row` n'est pas encore connue et que la map active est vide.
+    pub fn step(
+        &self,
+        tileMap: &mut HashMap<uVec3, (Option<Entity>, i32, u8)>,
+        stableTileMap: &mut HashMap<uVec3, (Option<Entity>, i32, u8)>,
+        row: &mut Option<i32>,
+    ){
+        let lowest = tileMap.keys().map(|p| p.y).min();
+        let Some(current) = [lowest, *row].into_iter().flatten().min().or_else(|| stableTileMap.keys().map(|p| p.y).min()) else {
+            return;
+        };
+        let alive: HashSet<i32> = tileMap.keys().filter(|p| p.y == current && p.z == 0).map(|p| p.x).collect();
+        let history: Vec<uVec3> = tileMap.keys().copied().collect();
+        for pos in history{
+            let cell = tileMap.remove(&pos).unwrap();
+            stableTileMap.insert(pos, cell);
+        }
+        *row = Some(current - 1);
+        let mut candidates: HashSet<i32> = HashSet::new();
+        for x in alive.iter(){
+            for dx in -self.range..self.range + 1 {
+                candidates.insert(x + dx);
+            }
+        }
+        let mut neighbours: Vec<bool> = Vec::new();
+        for x in candidates{
+            neighbours.clear();
+            neighbours.extend((-self.range..self.range + 1).map(|dx| alive.contains(&(x + dx))));
+            if self.next_alive(&neighbours) {
+                tileMap.insert(uVec3::new(x, current - 1, 0), (None, update_count_limit(), 1));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn row_advances_after_dying_out(){
+        // W32 (101 -> vivante) : une cellule seule meurt au premier pas
+        let rule = Rule1D::parse("W32").unwrap();
+        let (mut tileMap, mut stableTileMap) = (HashMap::new(), HashMap::new());
+        tileMap.insert(uVec3::new(0, 0, 0), (None, 1, 1));
+        let mut row: Option<i32> = None;
+        for generation in 1..4 {
+            rule.step(&mut tileMap, &mut stableTileMap, &mut row);
+            assert_eq!(row, Some(-generation));
+            assert!(tileMap.is_empty());
+            assert_eq!(stableTileMap.len(), 1);
+        }
+    }
+
+    #[test]
+    fn rule_90_diagram(){
+        // triangle de Sierpinski : la ligne n a 2^(nombre de bits à 1 de n) cellules
+        let rule = Rule1D::parse("W90").unwrap();
+        let (mut tileMap, mut stableTileMap) = (HashMap::new(), HashMap::new());
+        tileMap.insert(uVec3::new(0, 0, 0), (None, 1, 1));
+        let mut row: Option<i32> = None;
+        for _ in 0..16 {
+            rule.step(&mut tileMap, &mut stableTileMap, &mut row);
+        }
+        for n in 0..17 {
+            let count = tileMap.keys().chain(stableTileMap.keys()).filter(|p| p.y == -n).count();
+            assert_eq!(count, 1 << (n as u32).count_ones(), "row {}", n);
+        }
+    }
+}
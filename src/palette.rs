@@ -0,0 +1,81 @@
+use bevy::utils::HashMap;
+use crate::keybindings;
+use crate::keybindings::{Action, Binding};
+
+// entrées de la palette de commandes : les actions (les mêmes que l'aide F1, avec leurs raccourcis actuels)
+// et les motifs du dossier patterns/, choisis par une recherche approximative
+
+pub const MAX_RESULTS: usize = 12;
+
+pub enum Command{
+    Action(Action),
+    Pattern(String),    // chemin du fichier
+}
+
+pub struct Entry{
+    pub label: String,
+    pub command: Command,
+}
+
+pub fn entries(bindings: &HashMap<Action, Vec<Binding>>, patternsDir: &str) -> Vec<Entry>{
+    let mut entries: Vec<Entry> = Action::all().into_iter()
+        .filter(|action| *action != Action::CommandPalette)
+        .map(|action| Entry{
+            label: format!("{}  [{}]", action.description(), keybindings::bindings_text(bindings.get(&action).map_or(&[], |b| &b[..]))),
+            command: Command::Action(action),
+        })
+        .collect();
+    let mut patterns: Vec<(String, String)> = std::fs::read_dir(patternsDir).into_iter().flatten().flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map_or(false, |e| ["rle", "cells", "mc"].contains(&e.to_string_lossy().to_ascii_lowercase().as_str())))
+        .map(|path| (path.file_stem().unwrap_or_default().to_string_lossy().to_string(), path.to_string_lossy().to_string()))
+        .collect();
+    patterns.sort();
+    for (name, path) in patterns{
+        entries.push(Entry{
+            label: format!("Pattern: {}", name),
+            command: Command::Pattern(path),
+        });
+    }
+    entries
+}
+
+// les lettres de la recherche doivent apparaître dans l'ordre ; les lettres qui se suivent
+// et celles en début de mot rapportent plus, les trous coûtent un peu
+pub fn score(query: &str, text: &str) -> Option<i32>{
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()){
+        let found = (position..text.len()).find(|i| text[*i] == c)?;
+        score += 1;
+        if previous.map_or(false, |p| p + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        score -= (found - position).min(10) as i32 / 2;
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+// indices des entrées trouvées, les meilleures d'abord (ordre d'origine à égalité)
+pub fn search(entries: &[Entry], query: &str) -> Vec<usize>{
+    let mut found: Vec<(i32, usize)> = entries.iter().enumerate()
+        .filter_map(|(i, entry)| score(query, &entry.label).map(|s| (s, i)))
+        .collect();
+    found.sort_by_key(|(s, i)| (-s, *i));
+    found.into_iter().map(|(_, i)| i).collect()
+}
+
+// lignes de l'aide F1
+pub fn help_lines(bindings: &HashMap<Action, Vec<Binding>>) -> Vec<String>{
+    let mut lines = vec![String::from("Letters are key positions on a QWERTY keyboard")];
+    lines.extend(Action::all().into_iter()
+        .map(|action| format!("{}: {}", action.description(), keybindings::bindings_text(bindings.get(&action).map_or(&[], |b| &b[..])))));
+    lines
+}
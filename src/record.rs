@@ -0,0 +1,177 @@
+use bevy::color::ColorToPacked;
+use bevy::math::Vec2;
+use bevy::prelude::Entity;
+use crate::components::TileMap;
+use crate::export;
+use crate::export::Raster;
+use crate::gif;
+use crate::rle;
+use crate::rules::Rule;
+use crate::systems;
+use crate::theme;
+use crate::uVec3::uVec3;
+use crate::settings::{Settings, update_count_limit};
+
+// enregistrement d'une animation : la simulation est rejouée sur une copie de la TileMap,
+// une image est calculée toutes les `step` générations entre `from` et `to` (incluses),
+// puis écrite en GIF (sortie en .gif) ou en suite de PNG numérotés (nom-00000.png, nom-00001.png ...)
+
+pub const MAX_RECORD_FRAMES: u32 = 2000;
+
+pub const USAGE: &str = "usage: GameOfLife2D record <pattern.rle|pattern.mc|image.png> <output.gif|output.png> [options]
+  --from N               first recorded generation (default 0)
+  --to N                 last recorded generation (default 100)
+  --step N               generations between two frames (default 1)
+  --delay MS             delay between two frames in milliseconds (default 100)
+  --cell N               pixels per cell (default 4)
+  --region X0,Y0,X1,Y1   cells of two opposite corners (default: everything the pattern reaches)
+  --theme NAME           color theme (dark or light)";
+
+pub struct RecordSettings{
+    pub from: u32,
+    pub to: u32,
+    pub step: u32,
+    pub delay: u32,         // millisecondes
+    pub cellSize: u32,
+    pub region: Option<(Vec2, Vec2)>,   // centres des cellules des coins, dans le monde
+    pub output: String,
+}
+
+impl RecordSettings{
+    pub fn isGif(&self) -> bool{
+        self.output.to_ascii_lowercase().ends_with(".gif")
+    }
+
+    pub fn frameCount(&self) -> u32{
+        if self.to < self.from { 0 } else { (self.to - self.from) / self.step.max(1) + 1 }
+    }
+}
+
+pub fn record(mut tileMap: TileMap, settings: &RecordSettings, colors: &[[u8; 4]], background: [u8; 4]) -> Result<String, String>{
+    if tileMap.rule.lenia.is_some() {
+        return Err(String::from("Lenia cannot be recorded"));
+    }
+    if settings.step == 0 || settings.cellSize == 0 {
+        return Err(String::from("step and cell size must be at least 1"));
+    }
+    if settings.to < settings.from {
+        return Err(format!("last generation {} is before the first one {}", settings.to, settings.from));
+    }
+    if settings.frameCount() > MAX_RECORD_FRAMES {
+        return Err(format!("too many frames ({}, at most {})", settings.frameCount(), MAX_RECORD_FRAMES));
+    }
+
+    let layer = tileMap.layer;
+    let mut frames: Vec<Vec<(uVec3, u8)>> = Vec::new();
+    let mut inTileMapRemove: Vec<Entity> = Vec::new();
+    for generation in 0..settings.to + 1 {
+        if generation >= settings.from && (generation - settings.from) % settings.step == 0 {
+            frames.push(systems::living_cells(&tileMap).into_iter().filter(|(pos, _)| pos.z == layer).collect());
+        }
+        if generation < settings.to {
+            step_copy(&mut tileMap, &mut inTileMapRemove);
+        }
+    }
+
+    let grid = tileMap.rule.grid;
+    let (min, max) = match settings.region {
+        Some(region) => region,
+        None => frames.iter().filter_map(|cells| export::bounding_box(cells, grid))
+            .reduce(|(min, max), (a, b)| (min.min(a), max.max(b)))
+            .ok_or(String::from("nothing to record"))?,
+    };
+    let rasters: Vec<Raster> = frames.iter()
+        .map(|cells| export::rasterize(cells, grid, min, max, settings.cellSize, colors, background))
+        .collect::<Result<_, _>>()?;
+
+    if settings.isGif() {
+        gif::write_gif(&settings.output, &rasters, (settings.delay / 10).min(u16::MAX as u32) as u16)?;
+        Ok(format!("saved {} ({} frames)", settings.output, rasters.len()))
+    } else {
+        let stem = settings.output.strip_suffix(".png").unwrap_or(&settings.output);
+        for (i, raster) in rasters.iter().enumerate(){
+            export::write_png(&format!("{}-{:05}.png", stem, i), raster)?;
+        }
+        Ok(format!("saved {}-00000.png to {}-{:05}.png", stem, stem, rasters.len() - 1))
+    }
+}
+
+// les entités n'ont pas de sens dans la copie, elles sont juste oubliées
+fn step_copy(tileMap: &mut TileMap, inTileMapRemove: &mut Vec<Entity>){
+    systems::step_generation(tileMap, inTileMapRemove);
+    inTileMapRemove.clear();
+}
+
+// mode sans fenêtre : GameOfLife2D record <pattern.rle> <sortie> [options]
+pub fn run_cli(args: &[String]) -> Result<String, String>{
+    let mut positional: Vec<&String> = Vec::new();
+    let mut settings = RecordSettings{
+        from: 0,
+        to: 100,
+        step: 1,
+        delay: 100,
+        cellSize: 4,
+        region: None,
+        output: String::new(),
+    };
+    let mut corners: Option<(uVec3, uVec3)> = None;
+    let mut themeName = theme::THEMES[0].name;
+    let mut i = 0;
+    while i < args.len() {
+        let arg = &args[i];
+        if !arg.starts_with("--") {
+            positional.push(arg);
+            i += 1;
+            continue;
+        }
+        let value = args.get(i + 1).ok_or(format!("missing value for {}", arg))?;
+        let number = || value.parse::<u32>().map_err(|_| format!("invalid value '{}' for {}", value, arg));
+        match arg.as_str() {
+            "--from" => settings.from = number()?,
+            "--to" => settings.to = number()?,
+            "--step" => settings.step = number()?,
+            "--delay" => settings.delay = number()?,
+            "--cell" => settings.cellSize = number()?,
+            "--region" => {
+                let values: Vec<i32> = value.split(',').map(|v| v.trim().parse::<i32>()).collect::<Result<_, _>>()
+                    .map_err(|_| format!("invalid region '{}'", value))?;
+                if values.len() != 4 {
+                    return Err(format!("invalid region '{}': expected X0,Y0,X1,Y1", value));
+                }
+                corners = Some((uVec3::new(values[0], values[1], 0), uVec3::new(values[2], values[3], 0)));
+            }
+            "--theme" => {
+                themeName = theme::THEMES.iter().map(|t| t.name).find(|name| name == value)
+                    .ok_or(format!("unknown theme '{}'", value))?;
+            }
+            _ => return Err(format!("unknown option {}", arg)),
+        }
+        i += 2;
+    }
+    let [input, output] = positional[..] else {
+        return Err(String::from("expected a pattern file and an output file"));
+    };
+    settings.output = output.clone();
+
+    // les images sont importées avec les réglages de settings.ron, comme dans la fenêtre
+    let image = Settings::load(crate::SETTINGS_FILE, &[])?.import_settings(false);
+    let pattern = rle::read_file(input, &image)?;
+    let rule = match &pattern.rule {
+        Some(rulestring) => Rule::parse(rulestring)?,
+        None => Rule::life(),
+    };
+    let mut tileMap = TileMap::new(rule);
+    for (pos, state) in pattern.cells{
+        if state < tileMap.rule.states {
+            tileMap.current_state.insert(pos, (None, update_count_limit(), state));
+        }
+    }
+    if let Some((a, b)) = corners {
+        let (a, b) = (tileMap.rule.grid.to_world(&a).truncate(), tileMap.rule.grid.to_world(&b).truncate());
+        settings.region = Some((a.min(b), a.max(b)));
+    }
+
+    let theme = theme::THEMES.iter().find(|t| t.name == themeName).unwrap();
+    let colors = systems::export_colors(&tileMap.rule, theme);
+    record(tileMap, &settings, &colors, theme.background.to_srgba().to_u8_array())
+}
@@ -0,0 +1,167 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use bevy::prelude::Resource;
+use crossbeam_channel::{Receiver, Sender};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+// contrôle à distance (--remote PORT) : une requête JSON par ligne sur 127.0.0.1:PORT, une réponse par ligne.
+//   {"cmd": "load_pattern", "path": "patterns/glider.rle"}      ou "rle": "x = 3, y = 3\nbo$2bo$3o!", "x": 10, "y": -4   (texte RLE, .cells ou apgcode)
+//   {"cmd": "set_cell", "x": 0, "y": 0, "state": 1}             state 0 pour effacer
+//   {"cmd": "step", "n": 10}
+//   {"cmd": "run"}  {"cmd": "pause"}  {"cmd": "clear"}
+//   {"cmd": "get_population"}
+//   {"cmd": "get_cells", "x0": -10, "y0": -10, "x1": 10, "y1": 10}   -> [[x, y, state], ...]
+//   {"cmd": "set_rule", "rule": "B36/S23"}
+// Un champ "id" facultatif est renvoyé tel quel. Réponses : {"ok": true, "result": ...} ou {"ok": false, "error": "..."}.
+// Les commandes sont exécutées par le système remote_control, entre deux frames ; un "step" long est réparti
+// sur plusieurs frames et les requêtes suivantes attendent sa réponse.
+
+pub const MAX_STEPS: u32 = 100_000;
+pub const STEP_BUDGET: f32 = 0.008;       // secondes de calcul par frame pour un "step", réparti sur plusieurs frames
+pub const MAX_CELLS: usize = 1_000_000;    // cellules renvoyées au plus par get_cells
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "cmd", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Command{
+    LoadPattern{ path: Option<String>, rle: Option<String>, #[serde(default)] x: i32, #[serde(default)] y: i32 },
+    SetCell{ x: i32, y: i32, #[serde(default = "alive")] state: u8 },
+    Step{ #[serde(default = "one")] n: u32 },
+    Run,
+    Pause,
+    Clear,
+    GetPopulation,
+    GetCells{ x0: i32, y0: i32, x1: i32, y1: i32 },
+    SetRule{ rule: String },
+}
+
+fn alive() -> u8{
+    1
+}
+
+fn one() -> u32{
+    1
+}
+
+pub struct Request{
+    pub command: Command,
+    pub reply: Sender<Result<Value, String>>,
+}
+
+#[derive(Resource)]
+pub struct RemoteControl{
+    pub requests: Receiver<Request>,
+    pub stepping: Option<(u32, Sender<Result<Value, String>>)>,    // générations restantes du "step" en cours
+}
+
+pub fn start(port: u16) -> Result<RemoteControl, String>{
+    let listener = TcpListener::bind(("127.0.0.1", port)).map_err(|e| format!("cannot listen on port {}: {}", port, e))?;
+    let (sender, requests) = crossbeam_channel::unbounded::<Request>();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten(){
+            let sender = sender.clone();
+            std::thread::spawn(move || serve(stream, sender));
+        }
+    });
+    println!("remote control listening on 127.0.0.1:{}", port);
+    Ok(RemoteControl{ requests, stepping: None })
+}
+
+fn answer(id: Option<Value>, result: Result<Value, String>) -> String{
+    let mut response = match result {
+        Ok(result) => json!({ "ok": true, "result": result }),
+        Err(error) => json!({ "ok": false, "error": error }),
+    };
+    if let Some(id) = id {
+        response["id"] = id;
+    }
+    response.to_string()
+}
+
+// une ligne de requête : son "id" éventuel et la commande
+fn parse(line: &str) -> (Option<Value>, Result<Command, String>){
+    match serde_json::from_str::<Value>(line) {
+        Err(e) => (None, Err(format!("invalid JSON: {}", e))),
+        Ok(mut value) => {
+            let id = value.as_object_mut().and_then(|o| o.remove("id"));
+            (id, serde_json::from_value::<Command>(value).map_err(|e| format!("invalid command: {}", e)))
+        }
+    }
+}
+
+fn serve(stream: TcpStream, sender: Sender<Request>){
+    let Ok(writer) = stream.try_clone() else {
+        return;
+    };
+    serve_lines(BufReader::new(stream), writer, sender);
+}
+
+// une connexion : chaque ligne attend la réponse de l'application avant de lire la suivante
+fn serve_lines(reader: impl BufRead, mut writer: impl Write, sender: Sender<Request>){
+    for line in reader.lines(){
+        let Ok(line) = line else {
+            return;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (id, command) = parse(&line);
+        let result = match command {
+            Err(e) => Err(e),
+            Ok(command) => {
+                let (reply, response) = crossbeam_channel::bounded(1);
+                if sender.send(Request{ command, reply }).is_err() {
+                    return;
+                }
+                response.recv().unwrap_or(Err(String::from("the application is closing")))
+            }
+        };
+        if writeln!(writer, "{}", answer(id, result)).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn parse_commands(){
+        let (id, command) = parse(r#"{"cmd": "set_cell", "x": 3, "y": -2, "id": 7}"#);
+        assert_eq!(id, Some(json!(7)));
+        assert!(matches!(command, Ok(Command::SetCell{ x: 3, y: -2, state: 1 })));
+        assert!(matches!(parse(r#"{"cmd": "step"}"#).1, Ok(Command::Step{ n: 1 })));
+        assert!(matches!(parse(r#"{"cmd": "load_pattern", "rle": "bo$2bo$3o!", "x": 4}"#).1, Ok(Command::LoadPattern{ path: None, rle: Some(_), x: 4, y: 0 })));
+        assert!(matches!(parse(r#"{"cmd": "get_cells", "x0": -1, "y0": -1, "x1": 1, "y1": 1}"#).1, Ok(Command::GetCells{ x0: -1, y0: -1, x1: 1, y1: 1 })));
+        assert!(parse("{\"cmd\": ").1.unwrap_err().starts_with("invalid JSON"));
+        assert!(parse(r#"{"cmd": "fly"}"#).1.unwrap_err().starts_with("invalid command"));
+        assert!(parse(r#"{"cmd": "step", "n": 2, "count": 3}"#).1.is_err());
+        assert!(parse(r#"{"cmd": "set_cell", "x": 0}"#).1.is_err());
+    }
+
+    #[test]
+    fn replies_in_order(){
+        let (sender, requests) = crossbeam_channel::unbounded::<Request>();
+        let app = std::thread::spawn(move || {
+            for request in requests.iter(){
+                let result = match request.command {
+                    Command::GetPopulation => Ok(json!(5)),
+                    Command::Step{ n } => Ok(json!({ "population": n })),
+                    _ => Err(String::from("unsupported")),
+                };
+                request.reply.send(result).unwrap();
+            }
+        });
+        let input = "{\"cmd\": \"get_population\", \"id\": \"a\"}\n\n{\"cmd\": \"step\", \"n\": 3}\nnot json\n{\"cmd\": \"clear\"}\n";
+        let mut output: Vec<u8> = Vec::new();
+        serve_lines(input.as_bytes(), &mut output, sender);
+        app.join().unwrap();
+        let lines: Vec<Value> = String::from_utf8(output).unwrap().lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], json!({ "ok": true, "result": 5, "id": "a" }));
+        assert_eq!(lines[1], json!({ "ok": true, "result": { "population": 3 } }));
+        assert_eq!(lines[2]["ok"], json!(false));
+        assert_eq!(lines[3], json!({ "ok": false, "error": "unsupported" }));
+    }
+}
@@ -0,0 +1,275 @@
+use crate::apgcode;
+use crate::import;
+use crate::macrocell::Macrocell;
+use crate::uVec3::uVec3;
+
+// format RLE de Golly / LifeWiki : "x = 3, y = 3, rule = B3/S23" puis les lignes de cellules.
+// Le réseau est gardé dans le suffixe de la règle (B2/S34H, B45/S34L).
+// Les lignes du fichier vont de haut en bas, le y du monde va vers le haut.
+// Life 3D : "z = profondeur" dans l'en-tête et une couche par bloc, séparées par '/'.
+
+pub struct Pattern{
+    pub cells: Vec<(uVec3, u8)>,
+    pub rule: Option<String>,
+}
+
+const LINE_LENGTH: usize = 70;
+
+pub fn write(cells: &[(uVec3, u8)], rule: &str) -> String{
+    if cells.is_empty() {
+        return format!("x = 0, y = 0, rule = {}\n!\n", rule);
+    }
+    let minX = cells.iter().map(|(p, _)| p.x).min().unwrap();
+    let maxX = cells.iter().map(|(p, _)| p.x).max().unwrap();
+    let minY = cells.iter().map(|(p, _)| p.y).min().unwrap();
+    let maxY = cells.iter().map(|(p, _)| p.y).max().unwrap();
+    let minZ = cells.iter().map(|(p, _)| p.z).min().unwrap();
+    let maxZ = cells.iter().map(|(p, _)| p.z).max().unwrap();
+    let width = (maxX - minX + 1) as usize;
+    let height = (maxY - minY + 1) as usize;
+    let depth = (maxZ - minZ + 1) as usize;
+    let multistate = cells.iter().any(|(_, s)| *s > 1);
+
+    let mut layers: Vec<Vec<Vec<u8>>> = vec![vec![vec![0; width]; height]; depth];
+    for (pos, state) in cells{
+        layers[(pos.z - minZ) as usize][(maxY - pos.y) as usize][(pos.x - minX) as usize] = *state;
+    }
+
+    // suites (nombre, symbole), les fins de ligne vides sont fusionnées dans les '$'
+    // et les couches de Life 3D sont séparées par des '/', comme dans le 3D.lua de Golly
+    let mut runs: Vec<(usize, String)> = Vec::new();
+    let mut pendingLayers = 0;
+    for rows in layers.iter(){
+        let mut pendingRows = 0;
+        for row in rows.iter(){
+            let end = row.iter().rposition(|s| *s != 0).map_or(0, |i| i + 1);
+            if end == 0 {
+                pendingRows += 1;
+                continue;
+            }
+            if pendingLayers > 0 {
+                runs.push((pendingLayers, String::from("/")));
+                pendingLayers = 0;
+                pendingRows = 0;
+            }
+            if pendingRows > 0 {
+                runs.push((pendingRows, String::from("$")));
+            }
+            let mut x = 0;
+            while x < end {
+                let state = row[x];
+                let mut n = 1;
+                while x + n < end && row[x + n] == state {
+                    n += 1;
+                }
+                runs.push((n, state_symbol(state, multistate)));
+                x += n;
+            }
+            pendingRows = 1;
+        }
+        pendingLayers += 1;
+    }
+    runs.push((1, String::from("!")));
+
+    let mut text = if depth > 1 {
+        format!("x = {}, y = {}, z = {}, rule = {}\n", width, height, depth, rule)
+    } else {
+        format!("x = {}, y = {}, rule = {}\n", width, height, rule)
+    };
+    let mut line = String::new();
+    for (n, symbol) in runs{
+        let item = if n > 1 { format!("{}{}", n, symbol) } else { symbol };
+        if line.len() + item.len() > LINE_LENGTH {
+            text.push_str(&line);
+            text.push('\n');
+            line.clear();
+        }
+        line.push_str(&item);
+    }
+    text.push_str(&line);
+    text.push('\n');
+    text
+}
+
+pub fn read(text: &str) -> Result<Pattern, String>{
+    let mut rule: Option<String> = None;
+    let mut cells: Vec<(uVec3, u8)> = Vec::new();
+    let mut headerSeen = false;
+    let (mut x, mut y, mut z) = (0i32, 0i32, 0i32);
+    let mut count: Option<usize> = None;
+    let mut prefix: Option<char> = None;
+
+    'lines: for line in text.lines(){
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if !headerSeen && line.starts_with('x') {
+            headerSeen = true;
+            // la règle est le dernier champ et peut contenir des virgules (R5,C0,M1,...)
+            if let Some(start) = line.find("rule") {
+                if let Some((_, value)) = line[start..].split_once('=') {
+                    rule = Some(value.trim().to_string());
+                }
+            }
+            continue;
+        }
+        headerSeen = true;
+        for c in line.chars(){
+            if c.is_ascii_digit() {
+                count = Some(count.unwrap_or(0) * 10 + c.to_digit(10).unwrap() as usize);
+                continue;
+            }
+            let n = count.take().unwrap_or(1) as i32;
+            let state: u8 = match c {
+                'b' | '.' => 0,
+                'o' => 1,
+                'A'..='X' => {
+                    let high = prefix.take().map_or(0, |p| (p as u32 - 'p' as u32 + 1) * 24);
+                    let s = high + (c as u32 - 'A' as u32 + 1);
+                    if s > 255 {
+                        return Err(format!("state {} is out of range", s));
+                    }
+                    s as u8
+                }
+                'p'..='y' => {
+                    prefix = Some(c);
+                    count = if n > 1 { Some(n as usize) } else { None };
+                    continue;
+                }
+                '$' => {
+                    y -= n;
+                    x = 0;
+                    continue;
+                }
+                '/' => {
+                    z += n;
+                    y = 0;
+                    x = 0;
+                    continue;
+                }
+                '!' => break 'lines,
+                c if c.is_whitespace() => continue,
+                _ => return Err(format!("unexpected character '{}' in RLE", c)),
+            };
+            if state != 0 {
+                for i in 0..n {
+                    cells.push((uVec3::new(x + i, y, z), state));
+                }
+            }
+            x += n;
+        }
+    }
+
+    Ok(Pattern{
+        cells,
+        rule,
+    })
+}
+
+fn state_symbol(state: u8, multistate: bool) -> String{
+    if !multistate {
+        return String::from(if state == 0 { "b" } else { "o" });
+    }
+    if state == 0 {
+        return String::from(".");
+    }
+    let s = state as u32 - 1;
+    let letter = char::from_u32('A' as u32 + s % 24).unwrap();
+    if s < 24 {
+        letter.to_string()
+    } else {
+        format!("{}{}", char::from_u32('p' as u32 + s / 24 - 1).unwrap(), letter)
+    }
+}
+
+// format texte de LifeWiki (.cells) : lignes "!" en commentaire, '.' morte, 'O' ou '*' vivante
+pub fn read_plaintext(text: &str) -> Result<Pattern, String>{
+    let mut cells: Vec<(uVec3, u8)> = Vec::new();
+    let mut y = 0;
+    for line in text.lines(){
+        let line = line.trim_end();
+        if line.starts_with('!') {
+            continue;
+        }
+        for (x, c) in line.chars().enumerate(){
+            match c {
+                'O' | '*' => cells.push((uVec3::new(x as i32, y, 0), 1)),
+                '.' => {}
+                c if c.is_whitespace() => {}
+                _ => return Err(format!("unexpected character '{}' in plaintext pattern", c)),
+            }
+        }
+        y -= 1;
+    }
+    Ok(Pattern{
+        cells,
+        rule: None,
+    })
+}
+
+// RLE, .cells ou apgcode (xq4_153 ...) selon le contenu, pour du texte collé depuis Golly, le forum ou Catagolue
+pub fn read_any(text: &str) -> Result<Pattern, String>{
+    let trimmed = text.trim();
+    if apgcode::isApgcode(trimmed) {
+        return Ok(Pattern{
+            cells: apgcode::decode(trimmed)?.into_iter().map(|pos| (pos, 1)).collect(),
+            rule: None,
+        });
+    }
+    let plaintext = text.lines().map(|l| l.trim()).filter(|l| !l.is_empty() && !l.starts_with('!'))
+        .all(|l| l.chars().all(|c| c == '.' || c == 'O' || c == '*'));
+    if plaintext { read_plaintext(text) } else { read(text) }
+}
+
+// fichier de motif selon son extension : RLE ou .cells, macrocell (.mc, en entier) ou image PNG importée avec image
+pub fn read_file(path: &str, image: &import::ImportSettings) -> Result<Pattern, String>{
+    let lower = path.to_ascii_lowercase();
+    if lower.ends_with(".png") {
+        let bitmap = import::read_png(path)?;
+        return Ok(Pattern{
+            cells: import::to_cells(&bitmap, image, &[], uVec3::new(0, 0, 0))?,
+            rule: None,
+        });
+    }
+    let text = std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+    if lower.ends_with(".mc") {
+        let universe = Macrocell::read(&text)?;
+        let limit = i32::MAX as i64;
+        return Ok(Pattern{
+            cells: universe.cells_in((-limit, -limit), (limit, limit), crate::MAX_MACROCELL_CELLS)?,
+            rule: universe.rule,
+        });
+    }
+    read_any(&text)
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn sorted(cells: &[(uVec3, u8)]) -> Vec<(i32, i32, i32, u8)>{
+        let mut cells: Vec<(i32, i32, i32, u8)> = cells.iter().map(|(p, s)| (p.x, p.y, p.z, *s)).collect();
+        cells.sort();
+        cells
+    }
+
+    #[test]
+    fn read_write_round_trip(){
+        let texts = [
+            // Gosper glider gun : lignes de plus de 70 caractères une fois réécrites
+            "x = 36, y = 9, rule = B3/S23\n24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4bobo$10bo5bo7bo$11bo3bo$12b2o!\n",
+            // plusieurs états
+            "x = 4, y = 2, rule = 345/2/4\n.ABC$2C.A!\n",
+            // Life 3D : deux couches
+            "x = 2, y = 2, z = 2, rule = B5/S45\n2o$bo/o$2o!\n",
+        ];
+        for text in texts{
+            let pattern = read(text).unwrap();
+            let rule = pattern.rule.clone().unwrap();
+            let again = read(&write(&pattern.cells, &rule)).unwrap();
+            assert_eq!(again.rule.as_deref(), Some(rule.as_str()));
+            assert_eq!(sorted(&again.cells), sorted(&pattern.cells), "{}", text);
+        }
+    }
+}
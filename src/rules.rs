@@ -0,0 +1,842 @@
+// règles de type Life et Generations (Sxx/Bxx/C), isotropes non totalistes (B2-a/S12, notation de Hensel),
+// sur réseau hexagonal (B2/S34H) ou triangulaire (B45/S34L), Larger than Life (R5,C0,M1,S34..58,B34..45,NM)
+// et Life 3D en notation de Bays (4555 : survie entre 4 et 5 voisins, naissance entre 5 et 5, sur 26 voisins)
+// Tout autre nom (WireWorld) désigne une table de règles Golly chargée depuis rules/<nom>.rule
+// RL, LLRR ou {{{1,2,0},{0,8,0}}} sont des turmites (fourmi de Langton), voir turmite.rs
+// W30 et T20R2 sont des automates 1D de Wolfram, voir oned.rs
+// Lenia,R13,T10,M0.15,S0.015,Ggauss est un automate continu, voir lenia.rs
+
+use std::sync::Arc;
+use crate::grid::GridType;
+use crate::ruletable::{RuleTable, TableNeighbourhood};
+use crate::turmite::Turmite;
+use crate::oned::Rule1D;
+use crate::lenia::LeniaParams;
+// l'état 0 est mort, l'état 1 est vivant, les états 2..states sont les états "mourants" (réfractaires)
+
+pub const PRESETS: [&str; 22] = [
+    "B3/S23",       // Conway's Life
+    "B36/S23",      // HighLife
+    "/2/3",         // Brian's Brain
+    "345/2/4",      // Star Wars
+    "12/34/3",      // Frogs
+    "B34/S34",      // 34 Life
+    "B2-a/S12",     // Snowflakes
+    "B3/S23-a4i",   // isotrope non totaliste proche de Life
+    "B2/S34H",      // Life hexagonal
+    "B45/S34L",     // réseau triangulaire
+    "4555",         // Life 3D de Bays
+    "5766",         // Life 3D de Bays
+    "R5,C0,M1,S34..58,B34..45,NM",  // Bosco's Rule (bugs)
+    "R7,C0,M1,S65..114,B65..95,NC",   // Larger than Life circulaire
+    "WireWorld",    // table de règles (rules/WireWorld.rule)
+    "RL",           // fourmi de Langton
+    "LLRR",         // turmite symétrique
+    "W30",          // règle 30 (chaotique)
+    "W110",         // règle 110 (universelle)
+    "T20R2",        // 1D totaliste de portée 2
+    "Lenia,R13,T10,M0.15,S0.015,Ggauss",    // Lenia (paramètres d'Orbium)
+    "Lenia,R10,T5,M0.26,S0.036,Gpoly",      // Lenia à croissance polynomiale
+];
+
+pub const MAX_RANGE: i32 = 50;
+
+// notation de Hensel, même disposition que Golly : bit 4 = cellule du milieu
+// 0 1 2
+// 3 4 5
+// 6 7 8
+// pour 5 voisins et plus, les lettres sont celles de 8 - n sur le voisinage inversé
+const HENSEL_LETTERS: [&str; 5] = ["", "ce", "ceaikn", "ceaiknjqry", "ceaiknjqrytwz"];
+const HENSEL_NEIGHBOURHOODS: [&[u16]; 5] = [
+    &[0],
+    &[1, 2],
+    &[5, 10, 3, 40, 33, 68],
+    &[69, 42, 11, 7, 98, 13, 14, 70, 41, 97],
+    &[325, 170, 15, 45, 99, 71, 106, 102, 43, 101, 105, 78, 108],
+];
+const NEIGHBOUR_BITS: u16 = 0x1ef;
+const MIDDLE_BIT: u16 = 0x10;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Neighbourhood{
+    Moore,
+    VonNeumann,
+    Circular,
+}
+
+impl Neighbourhood{
+    pub fn contains(&self, dx: i32, dy: i32, range: i32) -> bool{
+        match self {
+            Neighbourhood::Moore => dx.abs() <= range && dy.abs() <= range,
+            Neighbourhood::VonNeumann => dx.abs() + dy.abs() <= range,
+            // distance euclidienne < range + 1/2, comme LifeViewer
+            Neighbourhood::Circular => dx * dx + dy * dy <= range * range + range,
+        }
+    }
+
+    // demi-largeur de la ligne dy du voisinage
+    pub fn row_width(&self, dy: i32, range: i32) -> i32{
+        match self {
+            Neighbourhood::Moore => range,
+            Neighbourhood::VonNeumann => range - dy.abs(),
+            Neighbourhood::Circular => {
+                let mut w = 0;
+                while Neighbourhood::Circular.contains(w + 1, dy, range) {
+                    w += 1;
+                }
+                w
+            }
+        }
+    }
+
+    // nombre de cellules du voisinage, sans la cellule du milieu
+    pub fn size(&self, range: i32) -> usize{
+        let mut size = 0;
+        for dy in -range..range+1 {
+            size += (2 * self.row_width(dy, range) + 1) as usize;
+        }
+        size - 1
+    }
+
+    fn letter(&self) -> char{
+        match self {
+            Neighbourhood::Moore => 'M',
+            Neighbourhood::VonNeumann => 'N',
+            Neighbourhood::Circular => 'C',
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Rule{
+    pub birth: Vec<bool>,       // indexé par le nombre de voisins vivants
+    pub survival: Vec<bool>,
+    pub states: u8,
+    pub range: i32,
+    pub neighbourhood: Neighbourhood,
+    pub middle: bool,           // la cellule du milieu compte dans ses propres voisins (M1)
+    pub isotropic: Option<Vec<bool>>,   // table de 512 entrées (voisinage 3x3 -> vivante), règles non totalistes
+    pub grid: GridType,
+    pub table: Option<Arc<RuleTable>>,  // règle définie par un fichier .rule, évaluée par ruletable::step
+    pub turmite: Option<Arc<Turmite>>,  // les cellules ne changent que sous les fourmis de TileMap.ants
+    pub wolfram: Option<Rule1D>,        // automate 1D, une ligne de plus à chaque génération
+    pub lenia: Option<LeniaParams>,     // automate continu, hors de TileMap (composant Lenia)
+}
+
+impl Rule{
+    pub fn life() -> Rule{
+        Rule::parse("B3/S23").unwrap()
+    }
+
+    // les règles de portée 1 sur Moore passent par le moteur creux (checkArround), les autres par ltl::step
+    pub fn isLargerThanLife(&self) -> bool{
+        self.range > 1 || self.neighbourhood != Neighbourhood::Moore || self.middle
+    }
+
+    // neighbours : voisinage 3x3 dans la disposition de Golly, sans la cellule du milieu
+    pub fn next_alive(&self, alive: bool, count: usize, neighbours: u16) -> bool{
+        match &self.isotropic {
+            Some(table) => table[(neighbours | if alive { MIDDLE_BIT } else { 0 }) as usize],
+            None => if alive { self.survival[count] } else { self.birth[count] },
+        }
+    }
+
+    pub fn parse(rulestring: &str) -> Result<Rule, String>{
+        let trimmed = rulestring.trim();
+        if LeniaParams::isLenia(trimmed) {
+            return Rule::parse_lenia(trimmed);
+        }
+        if Rule1D::is1D(trimmed) {
+            return Rule::parse_1d(trimmed);
+        }
+        if Turmite::isTurmite(trimmed) {
+            return Rule::parse_turmite(trimmed);
+        }
+        if trimmed.len() > 1 && trimmed.starts_with(['R', 'r']) && trimmed.contains(',') {
+            return Rule::parse_ltl(trimmed);
+        }
+        if !trimmed.is_empty() && !trimmed.contains('/') && trimmed.chars().all(|c| c.is_ascii_digit() || c == ',') {
+            return Rule::parse_bays(trimmed);
+        }
+        Rule::parse_life(trimmed).or_else(|e| {
+            if !trimmed.is_empty() && trimmed.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') && !trimmed.contains('/') {
+                Rule::parse_table(trimmed)
+            } else {
+                Err(e)
+            }
+        })
+    }
+
+    fn parse_lenia(rulestring: &str) -> Result<Rule, String>{
+        Ok(Rule{
+            birth: vec![false; 9],
+            survival: vec![false; 9],
+            states: 2,
+            range: 1,
+            neighbourhood: Neighbourhood::Moore,
+            middle: false,
+            isotropic: None,
+            grid: GridType::Square,
+            table: None,
+            turmite: None,
+            wolfram: None,
+            lenia: Some(LeniaParams::parse(rulestring)?),
+        })
+    }
+
+    fn parse_1d(rulestring: &str) -> Result<Rule, String>{
+        Ok(Rule{
+            birth: vec![false; 9],
+            survival: vec![false; 9],
+            states: 2,
+            range: 1,
+            neighbourhood: Neighbourhood::Moore,
+            middle: false,
+            isotropic: None,
+            grid: GridType::Square,
+            table: None,
+            turmite: None,
+            wolfram: Some(Rule1D::parse(rulestring)?),
+            lenia: None,
+        })
+    }
+
+    fn parse_turmite(rulestring: &str) -> Result<Rule, String>{
+        let turmite = Turmite::parse(rulestring)?;
+        Ok(Rule{
+            birth: vec![false; 9],
+            survival: vec![false; 9],
+            states: turmite.colors,
+            range: 1,
+            neighbourhood: Neighbourhood::Moore,
+            middle: false,
+            isotropic: None,
+            grid: GridType::Square,
+            table: None,
+            turmite: Some(Arc::new(turmite)),
+            wolfram: None,
+            lenia: None,
+        })
+    }
+
+    fn parse_table(name: &str) -> Result<Rule, String>{
+        let table = RuleTable::load(name)?;
+        Ok(Rule{
+            birth: vec![false; 9],
+            survival: vec![false; 9],
+            states: table.states,
+            range: 1,
+            neighbourhood: Neighbourhood::Moore,
+            middle: false,
+            isotropic: None,
+            grid: if table.neighbourhood == TableNeighbourhood::Hexagonal { GridType::Hexagonal } else { GridType::Square },
+            table: Some(Arc::new(table)),
+            turmite: None,
+            wolfram: None,
+            lenia: None,
+        })
+    }
+
+    // les tables de règles et les turmites dessinent l'état choisi au clavier plutôt que des cellules vivantes
+    pub fn usesBrush(&self) -> bool{
+        self.table.is_some() || self.turmite.is_some()
+    }
+
+    pub fn is3D(&self) -> bool{
+        self.grid == GridType::Cubic
+    }
+
+    // "4555" ou "4,5,5,5" : survie entre El et Eu voisins, naissance entre Fl et Fu
+    fn parse_bays(rulestring: &str) -> Result<Rule, String>{
+        let values: Vec<&str> = if rulestring.contains(',') {
+            rulestring.split(',').collect()
+        } else {
+            (0..rulestring.len()).map(|i| &rulestring[i..i+1]).collect()
+        };
+        if values.len() != 4 {
+            return Err(format!("invalid 3D rule '{}': expected 4 values (El Eu Fl Fu)", rulestring));
+        }
+        let mut bounds = [0usize; 4];
+        for (i, v) in values.iter().enumerate(){
+            bounds[i] = v.trim().parse().map_err(|_| format!("invalid value '{}' in rule '{}'", v, rulestring))?;
+        }
+        let size = GridType::Cubic.neighbour_count() + 1;
+        let rule = Rule{
+            survival: interval_to_counts(Some((bounds[0], bounds[1])), size, rulestring)?,
+            birth: interval_to_counts(Some((bounds[2], bounds[3])), size, rulestring)?,
+            states: 2,
+            range: 1,
+            neighbourhood: Neighbourhood::Moore,
+            middle: false,
+            isotropic: None,
+            grid: GridType::Cubic,
+            table: None,
+            turmite: None,
+            wolfram: None,
+            lenia: None,
+        };
+        rule.check_b0(rulestring)
+    }
+
+    // accepte "B3/S23", "S23/B3", "23/3", "B2/S/C3", "345/2/4", "/2/3", "B2/S/G3"
+    fn parse_life(rulestring: &str) -> Result<Rule, String>{
+        let mut birth: Option<&str> = None;
+        let mut survival: Option<&str> = None;
+        let mut states: Option<u8> = None;
+
+        // suffixe de réseau : B2/S34H (hexagonal), B45/S34L (triangulaire)
+        let (body, grid) = match rulestring.chars().last().map(|c| c.to_ascii_uppercase()) {
+            Some('H') => (&rulestring[..rulestring.len() - 1], GridType::Hexagonal),
+            Some('L') => (&rulestring[..rulestring.len() - 1], GridType::Triangular),
+            _ => (rulestring, GridType::Square),
+        };
+
+        let parts: Vec<&str> = body.split('/').collect();
+        if parts.len() < 2 || parts.len() > 3 {
+            return Err(format!("invalid rule '{}': expected 2 or 3 parts separated by '/'", rulestring));
+        }
+
+        for (index, part) in parts.iter().enumerate(){
+            let part = part.trim();
+            let (kind, digits) = match part.chars().next() {
+                Some(c) if c.is_ascii_alphabetic() => (c.to_ascii_uppercase(), &part[1..]),
+                // sans lettre, l'ordre est S/B/C comme dans Golly
+                _ => (['S', 'B', 'C'][index], part),
+            };
+            match kind {
+                'B' => set_once(&mut birth, digits, 'B', rulestring)?,
+                'S' => set_once(&mut survival, digits, 'S', rulestring)?,
+                'C' | 'G' => set_once(&mut states, parse_states(digits, rulestring)?, 'C', rulestring)?,
+                _ => return Err(format!("unknown section '{}' in rule '{}'", part, rulestring)),
+            }
+        }
+
+        let mut rule = Rule{
+            birth: Vec::new(),
+            survival: Vec::new(),
+            states: states.unwrap_or(2),
+            range: 1,
+            neighbourhood: Neighbourhood::Moore,
+            middle: false,
+            isotropic: None,
+            grid,
+            table: None,
+            turmite: None,
+            wolfram: None,
+            lenia: None,
+        };
+
+        if grid != GridType::Square {
+            rule.birth = parse_counts(birth.unwrap_or(""), grid.neighbour_count(), rulestring)?;
+            rule.survival = parse_counts(survival.unwrap_or(""), grid.neighbour_count(), rulestring)?;
+            return rule.check_b0(rulestring);
+        }
+
+        let birth = parse_hensel(birth.unwrap_or(""), rulestring)?;
+        let survival = parse_hensel(survival.unwrap_or(""), rulestring)?;
+        let totalistic = birth.iter().chain(survival.iter()).all(|letters| letters.iter().all(|l| *l == letters[0]));
+        rule.birth = birth.iter().map(|letters| letters.iter().any(|l| *l)).collect();
+        rule.survival = survival.iter().map(|letters| letters.iter().any(|l| *l)).collect();
+        if !totalistic {
+            rule.isotropic = Some(hensel_table(&birth, &survival));
+        }
+        rule.check_b0(rulestring)
+    }
+
+    // syntaxe Golly : R<portée>,C<états>,M<0|1>,S<min>..<max>,B<min>..<max>,N<M|N|C>
+    fn parse_ltl(rulestring: &str) -> Result<Rule, String>{
+        let mut range: Option<i32> = None;
+        let mut states: Option<u8> = None;
+        let mut middle: Option<bool> = None;
+        let mut survival: Option<(usize, usize)> = None;
+        let mut birth: Option<(usize, usize)> = None;
+        let mut neighbourhood: Option<Neighbourhood> = None;
+
+        for part in rulestring.split(','){
+            let part = part.trim();
+            let kind = part.chars().next().map(|c| c.to_ascii_uppercase());
+            let value = part.get(1..).unwrap_or("");
+            match kind {
+                Some('R') => {
+                    let r: i32 = value.parse().map_err(|_| format!("invalid range '{}' in rule '{}'", value, rulestring))?;
+                    if r < 1 || r > MAX_RANGE {
+                        return Err(format!("range must be between 1 and {} in rule '{}'", MAX_RANGE, rulestring));
+                    }
+                    set_once(&mut range, r, 'R', rulestring)?;
+                }
+                // C0 et C1 veulent dire 2 états
+                Some('C') => {
+                    let n = if value == "0" || value == "1" { 2 } else { parse_states(value, rulestring)? };
+                    set_once(&mut states, n, 'C', rulestring)?;
+                }
+                Some('M') => match value {
+                    "0" => set_once(&mut middle, false, 'M', rulestring)?,
+                    "1" => set_once(&mut middle, true, 'M', rulestring)?,
+                    _ => return Err(format!("invalid middle flag '{}' in rule '{}'", value, rulestring)),
+                },
+                // S ou B vide : aucun compte
+                Some('S') if value.is_empty() => set_once(&mut survival, (1, 0), 'S', rulestring)?,
+                Some('B') if value.is_empty() => set_once(&mut birth, (1, 0), 'B', rulestring)?,
+                Some('S') => set_once(&mut survival, parse_interval(value, rulestring)?, 'S', rulestring)?,
+                Some('B') => set_once(&mut birth, parse_interval(value, rulestring)?, 'B', rulestring)?,
+                Some('N') => {
+                    let n = match value.to_ascii_uppercase().as_str() {
+                        "M" => Neighbourhood::Moore,
+                        "N" => Neighbourhood::VonNeumann,
+                        "C" => Neighbourhood::Circular,
+                        _ => return Err(format!("unknown neighbourhood '{}' in rule '{}'", value, rulestring)),
+                    };
+                    set_once(&mut neighbourhood, n, 'N', rulestring)?;
+                }
+                _ => return Err(format!("unknown section '{}' in rule '{}'", part, rulestring)),
+            }
+        }
+
+        let range = range.ok_or(format!("missing range in rule '{}'", rulestring))?;
+        let neighbourhood = neighbourhood.unwrap_or(Neighbourhood::Moore);
+        let middle = middle.unwrap_or(false);
+        // +1 pour le compte 0, +1 pour la cellule du milieu
+        let size = neighbourhood.size(range) + 2;
+        let mut rule = Rule{
+            birth: interval_to_counts(birth, size, rulestring)?,
+            survival: interval_to_counts(survival, size, rulestring)?,
+            states: states.unwrap_or(2),
+            range,
+            neighbourhood,
+            middle,
+            isotropic: None,
+            grid: GridType::Square,
+            table: None,
+            turmite: None,
+            wolfram: None,
+            lenia: None,
+        };
+        // R1,M0,NM est une règle Life ordinaire
+        if !rule.isLargerThanLife() {
+            rule.birth.truncate(9);
+            rule.survival.truncate(9);
+        }
+        rule.check_b0(rulestring)
+    }
+
+    fn check_b0(self, rulestring: &str) -> Result<Rule, String>{
+        if self.birth[0] {
+            return Err(format!("B0 rules are not supported ('{}')", rulestring));
+        }
+        Ok(self)
+    }
+
+    pub fn to_string(&self) -> String{
+        if let Some(table) = &self.table {
+            return table.name.clone();
+        }
+        if let Some(turmite) = &self.turmite {
+            return turmite.name.clone();
+        }
+        if let Some(wolfram) = &self.wolfram {
+            return wolfram.to_string();
+        }
+        if let Some(lenia) = &self.lenia {
+            return lenia.to_string();
+        }
+        if self.is3D() {
+            let (el, eu) = counts_bounds(&self.survival);
+            let (fl, fu) = counts_bounds(&self.birth);
+            if eu < 10 && fu < 10 {
+                return format!("{}{}{}{}", el, eu, fl, fu);
+            }
+            return format!("{},{},{},{}", el, eu, fl, fu);
+        }
+        if self.isLargerThanLife() {
+            return format!("R{},C{},M{},S{},B{},N{}",
+                self.range,
+                if self.states > 2 { self.states } else { 0 },
+                if self.middle { 1 } else { 0 },
+                counts_to_interval(&self.survival),
+                counts_to_interval(&self.birth),
+                self.neighbourhood.letter());
+        }
+
+        let mut s = String::from("B");
+        if self.grid != GridType::Square {
+            s.push_str(&format!("{}/S{}", counts_to_list(&self.birth), counts_to_list(&self.survival)));
+        } else {
+            for i in 0..self.birth.len() {
+                s.push_str(&self.count_to_string(i, false));
+            }
+            s.push_str("/S");
+            for i in 0..self.survival.len() {
+                s.push_str(&self.count_to_string(i, true));
+            }
+        }
+        if self.states > 2 {
+            s.push_str(&format!("/C{}", self.states));
+        }
+        s.push_str(self.grid.suffix());
+        s
+    }
+}
+
+impl Rule{
+    // "3", "2-a", "4i" ... ou rien si le compte n'est pas dans la règle
+    fn count_to_string(&self, count: usize, alive: bool) -> String{
+        let letters = hensel_letters(count);
+        let present: Vec<bool> = (0..letters.len().max(1)).map(|l| self.next_alive(alive, count, hensel_neighbourhood(count, l))).collect();
+        let n = present.iter().filter(|p| **p).count();
+        if n == 0 {
+            return String::new();
+        }
+        if n == present.len() {
+            return count.to_string();
+        }
+        let (negate, wanted) = if n * 2 > present.len() { ("-", false) } else { ("", true) };
+        let chosen: String = letters.chars().zip(present.iter()).filter(|(_, p)| **p == wanted).map(|(c, _)| c).collect();
+        format!("{}{}{}", count, negate, chosen)
+    }
+}
+
+fn hensel_letters(count: usize) -> &'static str{
+    HENSEL_LETTERS[count.min(8 - count)]
+}
+
+// voisinage représentatif de la lettre n° letter pour ce compte
+fn hensel_neighbourhood(count: usize, letter: usize) -> u16{
+    if count <= 4 {
+        HENSEL_NEIGHBOURHOODS[count][letter]
+    } else {
+        !HENSEL_NEIGHBOURHOODS[8 - count][letter] & NEIGHBOUR_BITS
+    }
+}
+
+// rotation d'un quart de tour et symétrie gauche/droite du voisinage 3x3
+fn rotate(neighbours: u16) -> u16{
+    let mut rotated = 0;
+    for r in 0..3 {
+        for c in 0..3 {
+            if neighbours & (1 << (r * 3 + c)) != 0 {
+                rotated |= 1 << (c * 3 + 2 - r);
+            }
+        }
+    }
+    rotated
+}
+
+fn reflect(neighbours: u16) -> u16{
+    let mut reflected = 0;
+    for r in 0..3 {
+        for c in 0..3 {
+            if neighbours & (1 << (r * 3 + c)) != 0 {
+                reflected |= 1 << (r * 3 + 2 - c);
+            }
+        }
+    }
+    reflected
+}
+
+// table de 512 entrées à partir des lettres de naissance et de survie, en appliquant les 8 symétries
+fn hensel_table(birth: &Vec<Vec<bool>>, survival: &Vec<Vec<bool>>) -> Vec<bool>{
+    let mut table = vec![false; 512];
+    for count in 0..9 {
+        for letter in 0..birth[count].len() {
+            let mut neighbours = hensel_neighbourhood(count, letter);
+            for _ in 0..4 {
+                for n in [neighbours, reflect(neighbours)] {
+                    table[n as usize] = birth[count][letter];
+                    table[(n | MIDDLE_BIT) as usize] = survival[count][letter];
+                }
+                neighbours = rotate(neighbours);
+            }
+        }
+    }
+    table
+}
+
+// "23" -> comptes 2 et 3 avec toutes leurs lettres, "2-a3ce" -> 2 sauf a, 3 avec c et e seulement
+fn parse_hensel(digits: &str, rulestring: &str) -> Result<Vec<Vec<bool>>, String>{
+    let mut counts: Vec<Vec<bool>> = vec![Vec::new(); 9];
+    let mut current: Option<usize> = None;
+    let mut negate = false;
+    let mut explicit = false;
+    for c in digits.chars(){
+        if let Some(d) = c.to_digit(10) {
+            let d = d as usize;
+            if d > 8 {
+                return Err(format!("invalid neighbour count '{}' in rule '{}'", c, rulestring));
+            }
+            if !counts[d].is_empty() {
+                return Err(format!("duplicate neighbour count {} in rule '{}'", d, rulestring));
+            }
+            counts[d] = vec![true; hensel_letters(d).len().max(1)];
+            current = Some(d);
+            negate = false;
+            explicit = false;
+        } else if c == '-' && current.is_some() && !explicit {
+            negate = true;
+        } else {
+            let count = current.ok_or(format!("letter '{}' without neighbour count in rule '{}'", c, rulestring))?;
+            let index = hensel_letters(count).find(c.to_ascii_lowercase())
+                .ok_or(format!("invalid letter '{}' for {} neighbours in rule '{}'", c, count, rulestring))?;
+            // première lettre : on part de "aucune" (ou de "toutes" après un '-')
+            if !explicit {
+                counts[count] = vec![negate; counts[count].len()];
+                explicit = true;
+            }
+            counts[count][index] = !negate;
+        }
+    }
+    for count in 0..9 {
+        counts[count].resize(hensel_letters(count).len().max(1), false);
+    }
+    Ok(counts)
+}
+
+// règles totalistes des réseaux hexagonal et triangulaire : un chiffre par compte (B45/S34L),
+// ou des comptes séparés par des virgules pour aller au-delà de 9 voisins (B4,10/S3,11,12L)
+fn parse_counts(digits: &str, max: usize, rulestring: &str) -> Result<Vec<bool>, String>{
+    let mut counts = vec![false; max + 1];
+    let list: Vec<&str> = if digits.contains(',') {
+        digits.split(',').collect()
+    } else {
+        digits.char_indices().map(|(i, c)| &digits[i..i + c.len_utf8()]).collect()
+    };
+    for count in list{
+        match count.trim().parse::<usize>() {
+            Ok(d) if d <= max => {
+                if counts[d] {
+                    return Err(format!("duplicate neighbour count {} in rule '{}'", d, rulestring));
+                }
+                counts[d] = true;
+            }
+            _ => return Err(format!("invalid neighbour count '{}' in rule '{}'", count, rulestring)),
+        }
+    }
+    Ok(counts)
+}
+
+// inverse de parse_counts, avec des virgules seulement si un compte a deux chiffres
+fn counts_to_list(counts: &[bool]) -> String{
+    let present: Vec<String> = (0..counts.len()).filter(|i| counts[*i]).map(|i| i.to_string()).collect();
+    let separator = if present.iter().any(|c| c.len() > 1) { "," } else { "" };
+    present.join(separator)
+}
+
+fn parse_states(digits: &str, rulestring: &str) -> Result<u8, String>{
+    let n: u8 = digits.parse().map_err(|_| format!("invalid state count '{}' in rule '{}'", digits, rulestring))?;
+    if n < 2 {
+        return Err(format!("rule '{}' needs at least 2 states", rulestring));
+    }
+    Ok(n)
+}
+
+// "34..58", "34-58" ou "34"
+fn parse_interval(value: &str, rulestring: &str) -> Result<(usize, usize), String>{
+    let (min, max) = match value.split_once("..").or_else(|| value.split_once('-')) {
+        Some((a, b)) => (a, b),
+        None => (value, value),
+    };
+    let min: usize = min.trim().parse().map_err(|_| format!("invalid interval '{}' in rule '{}'", value, rulestring))?;
+    let max: usize = max.trim().parse().map_err(|_| format!("invalid interval '{}' in rule '{}'", value, rulestring))?;
+    if min > max {
+        return Err(format!("empty interval '{}' in rule '{}'", value, rulestring));
+    }
+    Ok((min, max))
+}
+
+fn interval_to_counts(interval: Option<(usize, usize)>, size: usize, rulestring: &str) -> Result<Vec<bool>, String>{
+    let mut counts = vec![false; size];
+    if let Some((min, max)) = interval {
+        if min > max {
+            return Ok(counts);
+        }
+        if max >= size {
+            return Err(format!("count {} is larger than the neighbourhood in rule '{}'", max, rulestring));
+        }
+        for i in min..max+1 {
+            counts[i] = true;
+        }
+    }
+    Ok(counts)
+}
+
+fn counts_bounds(counts: &Vec<bool>) -> (usize, usize){
+    (counts.iter().position(|c| *c).unwrap_or(0), counts.iter().rposition(|c| *c).unwrap_or(0))
+}
+
+fn counts_to_interval(counts: &Vec<bool>) -> String{
+    let min = counts.iter().position(|c| *c);
+    let max = counts.iter().rposition(|c| *c);
+    match (min, max) {
+        (Some(min), Some(max)) => format!("{}..{}", min, max),
+        _ => String::new(),
+    }
+}
+
+fn set_once<T>(slot: &mut Option<T>, value: T, name: char, rulestring: &str) -> Result<(), String>{
+    if slot.is_some() {
+        return Err(format!("section {} appears twice in rule '{}'", name, rulestring));
+    }
+    *slot = Some(value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::components::TileMap;
+    use crate::settings::update_count_limit;
+    use crate::systems::step_generation;
+    use crate::uVec3::uVec3;
+
+    fn counts(list: &[usize], size: usize) -> Vec<bool>{
+        (0..size).map(|i| list.contains(&i)).collect()
+    }
+
+    // (x, y, état) triés, après quelques générations
+    fn run(rulestring: &str, cells: &[(i32, i32, u8)], generations: usize) -> Vec<(i32, i32, u8)>{
+        let mut tileMap = TileMap::new(Rule::parse(rulestring).unwrap());
+        for (x, y, state) in cells{
+            tileMap.current_state.insert(uVec3::new(*x, *y, 0), (None, update_count_limit(), *state));
+        }
+        for _ in 0..generations {
+            step_generation(&mut tileMap, &mut Vec::new());
+        }
+        let mut result: Vec<(i32, i32, u8)> = tileMap.current_state.iter().chain(tileMap.stable_current_state.iter())
+            .map(|(pos, (_, _, state))| (pos.x, pos.y, *state))
+            .collect();
+        result.sort();
+        result
+    }
+
+    fn moved(cells: &[(i32, i32, u8)], dx: i32, dy: i32) -> Vec<(i32, i32, u8)>{
+        let mut result: Vec<(i32, i32, u8)> = cells.iter().map(|(x, y, s)| (x + dx, y + dy, *s)).collect();
+        result.sort();
+        result
+    }
+
+    const GLIDER: [(i32, i32, u8); 5] = [(1, 2, 1), (2, 1, 1), (0, 0, 1), (1, 0, 1), (2, 0, 1)];
+
+    #[test]
+    fn generations(){
+        let rule = Rule::parse("/2/3").unwrap();
+        assert_eq!(rule.states, 3);
+        assert_eq!(rule.birth, counts(&[2], 9));
+        assert_eq!(rule.survival, counts(&[], 9));
+        assert_eq!(rule.to_string(), "B2/S/C3");
+        let rule = Rule::parse("345/2/4").unwrap();
+        assert_eq!((rule.states, rule.to_string()), (4, String::from("B2/S345/C4")));
+        assert!(Rule::parse("B2/S/G3").unwrap() == Rule::parse("B2/S/C3").unwrap());
+        assert!(Rule::parse("B2/S/C1").is_err());
+        assert!(Rule::parse("B2/S/C3/C4").is_err());
+
+        // vaisseau de Brian's Brain : deux cellules allumées suivies de deux mourantes, une case par génération
+        let ship = [(0, 0, 1), (1, 0, 1), (0, -1, 2), (1, -1, 2)];
+        assert_eq!(run("/2/3", &ship, 1), moved(&ship, 0, 1));
+        assert_eq!(run("/2/3", &ship, 4), moved(&ship, 0, 4));
+    }
+
+    #[test]
+    fn hensel(){
+        let rule = Rule::parse("B3/S23-a4i").unwrap();
+        assert!(rule.isotropic.is_some());
+        assert_eq!(rule.to_string(), "B3/S23-a4i");
+        assert_eq!(Rule::parse("B2-a/S12").unwrap().to_string(), "B2-a/S12");
+        assert_eq!(Rule::parse("B3/S2aceikn3").unwrap().to_string(), "B3/S23");
+        assert!(Rule::parse("B3/S23").unwrap().isotropic.is_none());
+        assert!(Rule::parse("B3/S2x").is_err());
+        assert!(Rule::parse("B3/S9").is_err());
+
+        // chaque cellule du bloc a trois voisines en coin (3a) : stable en Life, il disparait ici
+        let block = [(0, 0, 1), (0, 1, 1), (1, 0, 1), (1, 1, 1)];
+        assert_eq!(run("B3/S23", &block, 1), block.to_vec());
+        assert_eq!(run("B3/S23-a4i", &block, 1), vec![]);
+        // cellule entourée en 4i (deux dominos verticaux de part et d'autre) : elle survit
+        let fourI = [(0, 0, 1), (-1, 1, 1), (1, 1, 1), (-1, 0, 1), (1, 0, 1)];
+        assert!(!run("B3/S23", &fourI, 1).contains(&(0, 0, 1)));
+        assert!(run("B3/S23-a4i", &fourI, 1).contains(&(0, 0, 1)));
+        // le planeur n'utilise ni 2a ni 4i
+        assert_eq!(run("B3/S23-a4i", &GLIDER, 4), moved(&GLIDER, 1, -1));
+    }
+
+    #[test]
+    fn larger_than_life(){
+        let rule = Rule::parse("R5,C0,M1,S34..58,B34..45,NM").unwrap();
+        assert_eq!((rule.range, rule.states, rule.middle, rule.neighbourhood), (5, 2, true, Neighbourhood::Moore));
+        assert_eq!(rule.survival, counts(&(34..59).collect::<Vec<usize>>(), rule.survival.len()));
+        assert_eq!(rule.birth, counts(&(34..46).collect::<Vec<usize>>(), rule.survival.len()));
+        assert_eq!(rule.to_string(), "R5,C0,M1,S34..58,B34..45,NM");
+        assert_eq!(Rule::parse("r2,c3,m0,s2-3,b3,nn").unwrap().to_string(), "R2,C3,M0,S2..3,B3..3,NN");
+        assert!(Rule::parse("R5,C0,M1,S34..58,B34..45,NX").is_err());
+        assert!(Rule::parse("R1,C0,M1,S2..10,B3,NM").is_err());
+        assert!(Rule::parse("R2,C0,M0,S5..3,B3,NM").is_err());
+        assert!(Rule::parse("R2,R3,S2,B3").is_err());
+
+        // Life écrite en Larger than Life (la cellule du milieu compte) : le planeur avance pareil
+        assert_eq!(run("R1,C0,M1,S3..4,B3..3,NM", &GLIDER, 4), moved(&GLIDER, 1, -1));
+
+        // portée 2 : même résultat qu'un comptage direct, pour les trois voisinages et avec des états mourants
+        let mut seed: u32 = 12345;
+        let mut cells: Vec<(i32, i32, u8)> = Vec::new();
+        for x in 0..24 {
+            for y in 0..24 {
+                seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                if (seed >> 16).is_multiple_of(3) {
+                    cells.push((x, y, 1 + ((seed >> 20) % 2) as u8));
+                }
+            }
+        }
+        for rulestring in ["R2,C3,M1,S6..11,B7..9,NM", "R2,C3,M0,S3..5,B4..5,NN", "R2,C0,M0,S5..8,B6..7,NC"]{
+            let rule = Rule::parse(rulestring).unwrap();
+            let cells: Vec<(i32, i32, u8)> = cells.iter().map(|(x, y, s)| (*x, *y, (*s).min(rule.states - 1))).collect();
+            let state = |x: i32, y: i32| cells.iter().find(|c| c.0 == x && c.1 == y).map_or(0, |c| c.2);
+            let mut expected: Vec<(i32, i32, u8)> = Vec::new();
+            for x in -3..28 {
+                for y in -3..28 {
+                    let s = state(x, y);
+                    let mut count = 0;
+                    for dx in -2..3 {
+                        for dy in -2..3 {
+                            if rule.neighbourhood.contains(dx, dy, 2) && (dx != 0 || dy != 0 || rule.middle) && state(x + dx, y + dy) == 1 {
+                                count += 1;
+                            }
+                        }
+                    }
+                    let next = match s {
+                        0 => if rule.birth[count] { 1 } else { 0 },
+                        1 => if rule.survival[count] { 1 } else if rule.states > 2 { 2 } else { 0 },
+                        _ => if s + 1 >= rule.states { 0 } else { s + 1 },
+                    };
+                    if next != 0 {
+                        expected.push((x, y, next));
+                    }
+                }
+            }
+            expected.sort();
+            assert_eq!(run(rulestring, &cells, 1), expected, "{}", rulestring);
+        }
+    }
+
+    #[test]
+    fn triangular_counts(){
+        let rule = Rule::parse("B45/S34L").unwrap();
+        assert!(rule.grid == GridType::Triangular);
+        assert_eq!(rule.birth, counts(&[4, 5], 13));
+        assert_eq!(rule.survival, counts(&[3, 4], 13));
+        assert_eq!(rule.to_string(), "B45/S34L");
+
+        // au-delà de 9 voisins, les comptes sont séparés par des virgules
+        let rule = Rule::parse("B4,10/S3,11,12L").unwrap();
+        assert_eq!(rule.birth, counts(&[4, 10], 13));
+        assert_eq!(rule.survival, counts(&[3, 11, 12], 13));
+        assert_eq!(rule.to_string(), "B4,10/S3,11,12L");
+        assert!(Rule::parse(&rule.to_string()).unwrap() == rule);
+        assert!(Rule::parse("S3, 11,12/B4,10L").unwrap() == rule);
+        assert_eq!(Rule::parse("B4,5/S3L").unwrap().to_string(), "B45/S3L");
+        assert_eq!(Rule::parse("B2,6/S34H").unwrap().to_string(), "B26/S34H");
+
+        assert!(Rule::parse("B4,13/S3L").is_err());
+        assert!(Rule::parse("B2,7/S3H").is_err());
+        assert!(Rule::parse("B4,4/S3L").is_err());
+        assert!(Rule::parse("B4,/S3L").is_err());
+        assert!(Rule::parse("B4a/S3L").is_err());
+    }
+}
@@ -0,0 +1,464 @@
+use std::path::Path;
+use std::sync::Mutex;
+use bevy::prelude::{Color, Entity};
+use bevy::utils::{HashMap, HashSet};
+use crate::uVec3::uVec3;
+use crate::settings::update_count_limit;
+
+// fichiers .rule de Golly : section @TABLE (n_states, neighborhood, symmetries, var, transitions)
+// et @COLORS (une couleur par état, ou un dégradé "s1 s2 r1 g1 b1 r2 g2 b2").
+// Les variables sont liées : un même nom dans une transition prend partout la même valeur.
+// La première transition qui correspond gagne, sinon la cellule garde son état.
+
+pub const RULES_DIRECTORY: &str = "rules";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TableNeighbourhood{
+    Moore,
+    VonNeumann,
+    Hexagonal,
+}
+
+// voisins dans l'ordre de Golly, y vers le haut
+const MOORE_OFFSETS: [(i32, i32); 8] = [(0, 1), (1, 1), (1, 0), (1, -1), (0, -1), (-1, -1), (-1, 0), (-1, 1)];
+const VON_NEUMANN_OFFSETS: [(i32, i32); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+// N, E, SE, S, W, NW de Golly sur notre réseau hexagonal axial
+const HEXAGONAL_OFFSETS: [(i32, i32); 6] = [(0, 1), (1, 0), (1, -1), (0, -1), (-1, 0), (-1, 1)];
+
+impl TableNeighbourhood{
+    pub fn offsets(&self) -> &'static [(i32, i32)]{
+        match self {
+            TableNeighbourhood::Moore => &MOORE_OFFSETS,
+            TableNeighbourhood::VonNeumann => &VON_NEUMANN_OFFSETS,
+            TableNeighbourhood::Hexagonal => &HEXAGONAL_OFFSETS,
+        }
+    }
+}
+
+struct Transition{
+    // une entrée par position (cellule, puis voisins) : valeurs possibles et variable liée éventuelle
+    inputs: Vec<(Vec<u8>, Option<usize>)>,
+    output: (u8, Option<usize>),
+}
+
+pub struct RuleTable{
+    pub name: String,
+    pub states: u8,
+    pub neighbourhood: TableNeighbourhood,
+    pub colors: Vec<Option<Color>>,
+    permute: bool,
+    symmetries: Vec<Vec<usize>>,
+    transitions: Vec<Transition>,
+    cache: Mutex<HashMap<Vec<u8>, u8>>,
+}
+
+impl PartialEq for RuleTable{
+    fn eq(&self, other: &RuleTable) -> bool{
+        self.name == other.name
+    }
+}
+
+impl RuleTable{
+    // cherche rules/<nom>.rule puis <nom>.rule
+    pub fn load(name: &str) -> Result<RuleTable, String>{
+        if name.is_empty() || name.contains(['/', '\\']) {
+            return Err(format!("invalid rule name '{}'", name));
+        }
+        for path in [Path::new(RULES_DIRECTORY).join(format!("{}.rule", name)), Path::new(".").join(format!("{}.rule", name))] {
+            if let Ok(text) = std::fs::read_to_string(&path) {
+                return RuleTable::parse(name, &text).map_err(|e| format!("{}: {}", path.display(), e));
+            }
+        }
+        Err(format!("unknown rule '{}' (no {}/{}.rule)", name, RULES_DIRECTORY, name))
+    }
+
+    pub fn parse(name: &str, text: &str) -> Result<RuleTable, String>{
+        let mut section = String::new();
+        let mut states: Option<u8> = None;
+        let mut neighbourhood = TableNeighbourhood::Moore;
+        let mut symmetries = String::from("none");
+        let mut variables: HashMap<String, Vec<u8>> = HashMap::new();
+        let mut transitionLines: Vec<(usize, String)> = Vec::new();
+        let mut colorLines: Vec<(usize, String)> = Vec::new();
+        let mut ruleName = name.to_string();
+
+        for (number, line) in text.lines().enumerate(){
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with('@') {
+                let mut words = line.split_whitespace();
+                section = words.next().unwrap().to_string();
+                if section == "@RULE" {
+                    if let Some(n) = words.next() {
+                        ruleName = n.to_string();
+                    }
+                }
+                continue;
+            }
+            match section.as_str() {
+                "@TABLE" => {
+                    if let Some((key, value)) = line.split_once(':') {
+                        let value = value.trim();
+                        match key.trim() {
+                            "n_states" => {
+                                let n: u8 = value.parse().map_err(|_| format!("line {}: invalid n_states '{}'", number + 1, value))?;
+                                if n < 2 {
+                                    return Err(format!("line {}: n_states must be at least 2", number + 1));
+                                }
+                                states = Some(n);
+                            }
+                            "neighborhood" => neighbourhood = match value {
+                                "Moore" => TableNeighbourhood::Moore,
+                                "vonNeumann" => TableNeighbourhood::VonNeumann,
+                                "hexagonal" => TableNeighbourhood::Hexagonal,
+                                _ => return Err(format!("line {}: unsupported neighborhood '{}'", number + 1, value)),
+                            },
+                            "symmetries" => symmetries = value.to_string(),
+                            _ => return Err(format!("line {}: unknown setting '{}'", number + 1, key.trim())),
+                        }
+                    } else if let Some(rest) = line.strip_prefix("var ") {
+                        let (var, values) = rest.split_once('=').ok_or(format!("line {}: invalid variable", number + 1))?;
+                        let values = values.trim().trim_start_matches('{').trim_end_matches('}');
+                        let mut parsed: Vec<u8> = Vec::new();
+                        for v in values.split(','){
+                            let v = v.trim();
+                            match variables.get(v) {
+                                // une variable peut être définie à partir d'une autre
+                                Some(other) => parsed.extend(other.iter()),
+                                None => parsed.push(v.parse().map_err(|_| format!("line {}: invalid value '{}'", number + 1, v))?),
+                            }
+                        }
+                        variables.insert(var.trim().to_string(), parsed);
+                    } else {
+                        transitionLines.push((number + 1, line.to_string()));
+                    }
+                }
+                "@COLORS" => colorLines.push((number + 1, line.to_string())),
+                _ => {}
+            }
+        }
+
+        let states = states.ok_or("missing n_states in @TABLE")?;
+        let neighbours = neighbourhood.offsets().len();
+        let (permute, symmetries) = symmetry_permutations(&symmetries, neighbourhood)?;
+
+        let mut transitions: Vec<Transition> = Vec::new();
+        for (number, line) in transitionLines{
+            let items: Vec<String> = if line.contains(',') {
+                line.split(',').map(|s| s.trim().to_string()).collect()
+            } else {
+                // forme compacte "0123..." quand tous les états tiennent sur un chiffre
+                line.chars().filter(|c| !c.is_whitespace()).map(|c| c.to_string()).collect()
+            };
+            if items.len() != neighbours + 2 {
+                return Err(format!("line {}: expected {} values, found {}", number, neighbours + 2, items.len()));
+            }
+            let mut bound: Vec<String> = Vec::new();
+            let mut inputs: Vec<(Vec<u8>, Option<usize>)> = Vec::new();
+            for item in items[..neighbours + 1].iter(){
+                if let Some(values) = variables.get(item) {
+                    let index = bound.iter().position(|b| b == item).unwrap_or_else(|| { bound.push(item.clone()); bound.len() - 1 });
+                    inputs.push((values.clone(), Some(index)));
+                } else {
+                    inputs.push((vec![parse_state(item, states, number)?], None));
+                }
+            }
+            let last = &items[neighbours + 1];
+            let output = match bound.iter().position(|b| b == last) {
+                Some(index) => (0, Some(index)),
+                None => (parse_state(last, states, number)?, None),
+            };
+            transitions.push(Transition{ inputs, output });
+        }
+
+        let mut colors: Vec<Option<Color>> = vec![None; states as usize];
+        for (number, line) in colorLines{
+            let values: Vec<u32> = line.split_whitespace().map(|v| v.parse::<u32>()).collect::<Result<_, _>>()
+                .map_err(|_| format!("line {}: invalid color", number))?;
+            let rgb = |r: u32, g: u32, b: u32| Color::srgb_u8(r.min(255) as u8, g.min(255) as u8, b.min(255) as u8);
+            match values.len() {
+                4 => if (values[0] as usize) < colors.len() {
+                    colors[values[0] as usize] = Some(rgb(values[1], values[2], values[3]));
+                },
+                8 => {
+                    let (s1, s2) = (values[0] as usize, values[1] as usize);
+                    for s in s1..(s2 + 1).min(colors.len()) {
+                        let t = if s2 > s1 { (s - s1) as f32 / (s2 - s1) as f32 } else { 0. };
+                        let mix = |a: u32, b: u32| (a as f32 + (b as f32 - a as f32) * t) as u32;
+                        colors[s] = Some(rgb(mix(values[2], values[5]), mix(values[3], values[6]), mix(values[4], values[7])));
+                    }
+                }
+                _ => return Err(format!("line {}: expected 'state r g b' or 's1 s2 r1 g1 b1 r2 g2 b2'", number)),
+            }
+        }
+
+        Ok(RuleTable{
+            name: ruleName,
+            states,
+            neighbourhood,
+            colors,
+            permute,
+            symmetries,
+            transitions,
+            cache: Mutex::new(HashMap::new()),
+        })
+    }
+
+    // key = [état de la cellule, voisins dans l'ordre de Golly]
+    pub fn next_state(&self, key: &Vec<u8>) -> u8{
+        if let Some(state) = self.cache.lock().unwrap().get(key) {
+            return *state;
+        }
+        let state = self.transitions.iter().find_map(|t| self.apply(t, key)).unwrap_or(key[0]);
+        self.cache.lock().unwrap().insert(key.clone(), state);
+        state
+    }
+
+    fn apply(&self, transition: &Transition, key: &Vec<u8>) -> Option<u8>{
+        let mut bindings: Vec<Option<u8>> = vec![None; transition.inputs.len()];
+        if !bind(&transition.inputs[0], key[0], &mut bindings) {
+            return None;
+        }
+        if self.permute {
+            let mut used = vec![false; key.len() - 1];
+            if !match_permuted(&transition.inputs[1..], &key[1..], &mut used, &mut bindings) {
+                return None;
+            }
+            return Some(output(transition, &bindings));
+        }
+        for permutation in self.symmetries.iter(){
+            let mut b = bindings.clone();
+            if permutation.iter().enumerate().all(|(i, p)| bind(&transition.inputs[i + 1], key[p + 1], &mut b)) {
+                return Some(output(transition, &b));
+            }
+        }
+        None
+    }
+}
+
+fn output(transition: &Transition, bindings: &Vec<Option<u8>>) -> u8{
+    match transition.output {
+        (_, Some(var)) => bindings[var].unwrap_or(0),
+        (state, None) => state,
+    }
+}
+
+fn bind(input: &(Vec<u8>, Option<usize>), value: u8, bindings: &mut Vec<Option<u8>>) -> bool{
+    if !input.0.contains(&value) {
+        return false;
+    }
+    if let Some(var) = input.1 {
+        match bindings[var] {
+            Some(bound) => return bound == value,
+            None => bindings[var] = Some(value),
+        }
+    }
+    true
+}
+
+// symétrie "permute" : n'importe quel voisin peut aller sur n'importe quelle entrée
+fn match_permuted(inputs: &[(Vec<u8>, Option<usize>)], values: &[u8], used: &mut Vec<bool>, bindings: &mut Vec<Option<u8>>) -> bool{
+    if inputs.is_empty() {
+        return true;
+    }
+    let mut tried: HashSet<u8> = HashSet::new();
+    for i in 0..values.len() {
+        if used[i] || !tried.insert(values[i]) {
+            continue;
+        }
+        let mut b = bindings.clone();
+        if bind(&inputs[0], values[i], &mut b) {
+            used[i] = true;
+            if match_permuted(&inputs[1..], values, used, &mut b) {
+                *bindings = b;
+                return true;
+            }
+            used[i] = false;
+        }
+    }
+    false
+}
+
+// permutations des voisins pour chaque symétrie (la première est l'identité)
+fn symmetry_permutations(name: &str, neighbourhood: TableNeighbourhood) -> Result<(bool, Vec<Vec<usize>>), String>{
+    let n = neighbourhood.offsets().len();
+    let rotate = |step: usize| -> Vec<Vec<usize>> {
+        (0..n / step).map(|k| (0..n).map(|i| (i + k * step) % n).collect()).collect()
+    };
+    let reflect = |permutations: Vec<Vec<usize>>| -> Vec<Vec<usize>> {
+        let mut all = permutations.clone();
+        for p in permutations{
+            all.push((0..n).map(|i| p[(n - i) % n]).collect());
+        }
+        all
+    };
+    // pas d'une rotation de 90° (ou 60° en hexagonal)
+    let quarter = match neighbourhood {
+        TableNeighbourhood::Moore => 2,
+        _ => 1,
+    };
+    let permutations = match (name, neighbourhood) {
+        ("none", _) => rotate(n),
+        ("permute", _) => return Ok((true, Vec::new())),
+        ("reflect_horizontal", _) | ("reflect", _) => reflect(rotate(n)),
+        ("rotate4", TableNeighbourhood::Moore) | ("rotate4", TableNeighbourhood::VonNeumann) => rotate(quarter),
+        ("rotate4reflect", TableNeighbourhood::Moore) | ("rotate4reflect", TableNeighbourhood::VonNeumann) => reflect(rotate(quarter)),
+        ("rotate8", TableNeighbourhood::Moore) => rotate(1),
+        ("rotate8reflect", TableNeighbourhood::Moore) => reflect(rotate(1)),
+        ("rotate2", TableNeighbourhood::Hexagonal) => rotate(3),
+        ("rotate3", TableNeighbourhood::Hexagonal) => rotate(2),
+        ("rotate6", TableNeighbourhood::Hexagonal) => rotate(1),
+        ("rotate6reflect", TableNeighbourhood::Hexagonal) => reflect(rotate(1)),
+        _ => return Err(format!("unsupported symmetries '{}' for {:?}", name, neighbourhood)),
+    };
+    Ok((false, permutations))
+}
+
+fn parse_state(item: &str, states: u8, line: usize) -> Result<u8, String>{
+    match item.parse::<u8>() {
+        Ok(s) if s < states => Ok(s),
+        _ => Err(format!("line {}: '{}' is neither a state nor a variable", line, item)),
+    }
+}
+
+// une génération : les cellules non nulles et leurs voisins sont évalués, le fond reste à l'état 0
+pub fn step(
+    table: &RuleTable,
+    tileMap: &HashMap<uVec3, (Option<Entity>, i32, u8)>,
+    stableTileMap: &HashMap<uVec3, (Option<Entity>, i32, u8)>,
+    inTileMapRemove: &mut Vec<Entity>
+) -> HashMap<uVec3, (Option<Entity>, i32, u8)>{
+    let offsets = table.neighbourhood.offsets();
+    let stateAt = |pos: &uVec3| tileMap.get(pos).or(stableTileMap.get(pos)).map_or(0, |(_, _, s)| *s);
+
+    let mut candidates: HashSet<uVec3> = HashSet::new();
+    for pos in tileMap.keys().chain(stableTileMap.keys()){
+        candidates.insert(*pos);
+        for (dx, dy) in offsets{
+            candidates.insert(uVec3::new(pos.x + dx, pos.y + dy, pos.z));
+        }
+    }
+
+    let mut newTileMap: HashMap<uVec3, (Option<Entity>, i32, u8)> = HashMap::new();
+    let mut key: Vec<u8> = vec![0; offsets.len() + 1];
+    for pos in candidates{
+        key[0] = stateAt(&pos);
+        for (i, (dx, dy)) in offsets.iter().enumerate(){
+            key[i + 1] = stateAt(&uVec3::new(pos.x + dx, pos.y + dy, pos.z));
+        }
+        let state = table.next_state(&key);
+        let entity = tileMap.get(&pos).or(stableTileMap.get(&pos)).and_then(|(e, _, _)| *e);
+        if state == 0 {
+            if let Some(e) = entity {
+                inTileMapRemove.push(e);
+            }
+        } else {
+            newTileMap.insert(pos, (entity, update_count_limit(), state));
+        }
+    }
+    newTileMap
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    const PAIRS: &str = "@RULE Pairs
+@TABLE
+n_states:3
+neighborhood:vonNeumann
+symmetries:rotate4
+var a={1,2}
+var b={a,0}
+# deux voisins côte à côte dans le même état
+0,a,a,b,b,a
+0,1,2,0,0,2
+@COLORS
+0 2 0 0 0 255 255 255
+1 255 0 0
+";
+
+    fn run(table: &RuleTable, cells: &[(i32, i32, u8)], generations: usize) -> HashMap<uVec3, (Option<Entity>, i32, u8)>{
+        let mut tileMap: HashMap<uVec3, (Option<Entity>, i32, u8)> = cells.iter().map(|(x, y, s)| (uVec3::new(*x, *y, 0), (None, 0, *s))).collect();
+        for _ in 0..generations {
+            tileMap = step(table, &tileMap, &HashMap::new(), &mut Vec::new());
+        }
+        tileMap
+    }
+
+    #[test]
+    fn parse_table(){
+        let table = RuleTable::parse("file", PAIRS).unwrap();
+        assert_eq!(table.name, "Pairs");
+        assert_eq!(table.states, 3);
+        assert_eq!(table.neighbourhood, TableNeighbourhood::VonNeumann);
+        // clé : cellule, puis N, E, S, W
+        assert_eq!(table.next_state(&vec![0, 1, 1, 0, 0]), 1);
+        assert_eq!(table.next_state(&vec![0, 2, 2, 1, 1]), 2);
+        // une variable prend la même valeur partout, b aussi bien que a
+        assert_eq!(table.next_state(&vec![0, 2, 2, 1, 0]), 0);
+        assert_eq!(table.next_state(&vec![0, 1, 2, 0, 0]), 2);
+        assert_eq!(table.next_state(&vec![0, 2, 1, 0, 0]), 0);
+        // rotations : E et S, W et N, mais pas N et S
+        assert_eq!(table.next_state(&vec![0, 0, 1, 1, 0]), 1);
+        assert_eq!(table.next_state(&vec![0, 2, 0, 0, 2]), 2);
+        assert_eq!(table.next_state(&vec![0, 1, 0, 1, 0]), 0);
+        assert_eq!(table.next_state(&vec![0, 0, 0, 1, 2]), 2);
+        // pas de transition : l'état ne change pas
+        assert_eq!(table.next_state(&vec![1, 1, 1, 0, 0]), 1);
+
+        // le reflet de N=1, E=2 n'est accepté qu'avec rotate4reflect
+        assert_eq!(table.next_state(&vec![0, 1, 0, 0, 2]), 0);
+        let reflected = RuleTable::parse("file", &PAIRS.replace("rotate4", "rotate4reflect")).unwrap();
+        assert_eq!(reflected.next_state(&vec![0, 1, 0, 0, 2]), 2);
+
+        // dégradé de 0 à 2, puis couleur de l'état 1 remplacée
+        assert!(table.colors[0] == Some(Color::srgb_u8(0, 0, 0)));
+        assert!(table.colors[1] == Some(Color::srgb_u8(255, 0, 0)));
+        assert!(table.colors[2] == Some(Color::srgb_u8(255, 255, 255)));
+    }
+
+    #[test]
+    fn parse_neighbourhoods(){
+        let hexagonal = RuleTable::parse("Hex", "@TABLE\nn_states:2\nneighborhood:hexagonal\nsymmetries:rotate6\n0,1,1,0,0,0,0,1\n").unwrap();
+        assert_eq!(hexagonal.neighbourhood, TableNeighbourhood::Hexagonal);
+        assert_eq!(hexagonal.next_state(&vec![0, 0, 0, 0, 1, 1, 0]), 1);
+        assert_eq!(hexagonal.next_state(&vec![0, 1, 0, 0, 0, 0, 1]), 1);
+        assert_eq!(hexagonal.next_state(&vec![0, 1, 0, 1, 0, 0, 0]), 0);
+
+        // forme compacte sans virgules
+        let moore = RuleTable::parse("Moore", "@TABLE\nn_states:2\nneighborhood:Moore\nsymmetries:rotate8\n0100000001\n").unwrap();
+        assert_eq!(moore.neighbourhood, TableNeighbourhood::Moore);
+        assert_eq!(moore.next_state(&vec![0, 0, 0, 0, 0, 0, 1, 0, 0]), 1);
+        assert_eq!(moore.next_state(&vec![0, 0, 0, 0, 0, 0, 0, 0, 0]), 0);
+
+        assert!(RuleTable::parse("x", "@TABLE\nn_states:2\nneighborhood:Margolus\n").is_err());
+        assert!(RuleTable::parse("x", "@TABLE\nn_states:2\nneighborhood:Moore\nsymmetries:rotate6\n").is_err());
+        assert!(RuleTable::parse("x", "@TABLE\nn_states:2\nneighborhood:vonNeumann\n0,1,0,0,1\n").is_err());
+        assert!(RuleTable::parse("x", "@TABLE\nn_states:2\nneighborhood:vonNeumann\n0,1,0,0,0,2\n").is_err());
+        assert!(RuleTable::parse("x", "@TABLE\nneighborhood:vonNeumann\n").is_err());
+        assert!(RuleTable::parse("x", "@TABLE\nn_states:2\n@COLORS\n1 255 0\n").is_err());
+    }
+
+    #[test]
+    fn wireworld_diode(){
+        let table = RuleTable::load("WireWorld").unwrap();
+        assert_eq!(table.states, 4);
+        assert!(table.colors[0].is_none());
+        assert!(table.colors[3] == Some(Color::srgb_u8(255, 128, 0)));
+
+        // fil de 0 à 11 coupé en x = 5, deux blocs de 2 x 2 de part et d'autre de la coupure
+        let mut wire: Vec<(i32, i32, u8)> = (0..12).filter(|x| *x != 5).map(|x| (x, 0, 3)).collect();
+        wire.extend([(4, 1, 3), (5, 1, 3), (4, -1, 3), (5, -1, 3)]);
+        let arrives = |head: i32, tail: i32, end: i32| -> Option<usize>{
+            let mut cells = wire.clone();
+            cells.retain(|(x, y, _)| !(*y == 0 && (*x == head || *x == tail)));
+            cells.extend([(head, 0, 1), (tail, 0, 2)]);
+            (1..40).find(|t| run(&table, &cells, *t).get(&uVec3::new(end, 0, 0)).is_some_and(|c| c.2 == 1))
+        };
+        assert_eq!(arrives(1, 0, 11), Some(10));
+        assert_eq!(arrives(10, 11, 0), None);
+    }
+}
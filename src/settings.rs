@@ -0,0 +1,175 @@
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::time::SystemTime;
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+use crate::import::ImportSettings;
+
+// réglages lus dans settings.ron au lancement et rechargés quand le fichier change, par exemple :
+//   (
+//       cameraSpeed: 800.,
+//       updateCountLimit: 40,
+//       vsync: true,
+//   )
+// Les champs absents gardent leur valeur par défaut. Ils peuvent aussi être changés en ligne de commande
+// avec --set nom=valeur, qui l'emporte sur le fichier (y compris après un rechargement).
+
+#[derive(Resource, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings{
+    pub cameraSpeed: f32,           // pixels par seconde pour le déplacement au clavier
+    pub zoomMultiplier: f32,        // sensibilité de la molette
+    pub updateCountLimit: i32,      // générations sans changement avant qu'une cellule passe dans la carte stable
+    pub unstableChangerLimit: i32,  // rayon des cellules stables réveillées autour d'un changement
+    pub windowWidth: f32,
+    pub windowHeight: f32,
+    pub vsync: bool,
+    pub pngCellSize: u32,           // pixels par cellule des exports PNG, 0 : 1 pour tout le motif, comme à l'écran pour la vue
+    pub recordFrom: u32,            // première génération enregistrée par Ctrl+G (0 : l'actuelle)
+    pub recordTo: u32,              // dernière génération enregistrée
+    pub recordStep: u32,            // générations entre deux images
+    pub recordDelay: u32,           // millisecondes entre deux images
+    pub importThreshold: f32,       // luminance sous laquelle un pixel importé est vivant (règles à 2 états)
+    pub importScale: f32,           // cellules par pixel des images importées
+    pub importDither: bool,         // tramage de Floyd-Steinberg à l'import
+}
+
+impl Default for Settings{
+    fn default() -> Settings{
+        Settings{
+            cameraSpeed: 500.,
+            zoomMultiplier: 5.,
+            updateCountLimit: 20,
+            unstableChangerLimit: 3,
+            windowWidth: 1920.,
+            windowHeight: 1080.,
+            vsync: false,
+            pngCellSize: 0,
+            recordFrom: 0,
+            recordTo: 100,
+            recordStep: 1,
+            recordDelay: 100,
+            importThreshold: 0.5,
+            importScale: 1.,
+            importDither: false,
+        }
+    }
+}
+
+fn parse<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String>{
+    value.trim().parse::<T>().map_err(|_| format!("invalid value '{}' for {}", value, name))
+}
+
+impl Settings{
+    pub fn validate(&self) -> Result<(), String>{
+        if !(self.cameraSpeed.is_finite() && self.cameraSpeed > 0.) {
+            return Err(format!("cameraSpeed must be positive, got {}", self.cameraSpeed));
+        }
+        if !(self.zoomMultiplier > 0. && self.zoomMultiplier <= 50.) {
+            return Err(format!("zoomMultiplier must be between 0 and 50, got {}", self.zoomMultiplier));
+        }
+        if self.updateCountLimit < 1 {
+            return Err(format!("updateCountLimit must be at least 1, got {}", self.updateCountLimit));
+        }
+        // les voisins directs doivent toujours être réveillés
+        if self.unstableChangerLimit < 1 || self.unstableChangerLimit > 16 {
+            return Err(format!("unstableChangerLimit must be between 1 and 16, got {}", self.unstableChangerLimit));
+        }
+        if !(self.windowWidth >= 100. && self.windowHeight >= 100. && self.windowWidth <= 16384. && self.windowHeight <= 16384.) {
+            return Err(format!("invalid window size {}x{}", self.windowWidth, self.windowHeight));
+        }
+        if self.pngCellSize > 64 {
+            return Err(format!("pngCellSize must be at most 64, got {}", self.pngCellSize));
+        }
+        if self.recordStep < 1 {
+            return Err(format!("recordStep must be at least 1, got {}", self.recordStep));
+        }
+        if self.recordTo < self.recordFrom {
+            return Err(format!("recordTo ({}) must not be before recordFrom ({})", self.recordTo, self.recordFrom));
+        }
+        if !(self.importThreshold >= 0. && self.importThreshold <= 1.) {
+            return Err(format!("importThreshold must be between 0 and 1, got {}", self.importThreshold));
+        }
+        if !(self.importScale > 0. && self.importScale <= 16.) {
+            return Err(format!("importScale must be between 0 and 16, got {}", self.importScale));
+        }
+        Ok(())
+    }
+
+    // "nom=valeur", comme passé à --set
+    pub fn set(&mut self, assignment: &str) -> Result<(), String>{
+        let (name, value) = assignment.split_once('=').ok_or(format!("expected name=value, got '{}'", assignment))?;
+        match name.trim() {
+            "cameraSpeed" => self.cameraSpeed = parse(name, value)?,
+            "zoomMultiplier" => self.zoomMultiplier = parse(name, value)?,
+            "updateCountLimit" => self.updateCountLimit = parse(name, value)?,
+            "unstableChangerLimit" => self.unstableChangerLimit = parse(name, value)?,
+            "windowWidth" => self.windowWidth = parse(name, value)?,
+            "windowHeight" => self.windowHeight = parse(name, value)?,
+            "vsync" => self.vsync = parse(name, value)?,
+            "pngCellSize" => self.pngCellSize = parse(name, value)?,
+            "recordFrom" => self.recordFrom = parse(name, value)?,
+            "recordTo" => self.recordTo = parse(name, value)?,
+            "recordStep" => self.recordStep = parse(name, value)?,
+            "recordDelay" => self.recordDelay = parse(name, value)?,
+            "importThreshold" => self.importThreshold = parse(name, value)?,
+            "importScale" => self.importScale = parse(name, value)?,
+            "importDither" => self.importDither = parse(name, value)?,
+            _ => return Err(format!("unknown setting '{}'", name.trim())),
+        }
+        Ok(())
+    }
+
+    // réglages du fichier (valeurs par défaut s'il n'existe pas) puis ceux de la ligne de commande
+    pub fn load(path: &str, overrides: &[String]) -> Result<Settings, String>{
+        let mut settings = match std::fs::read_to_string(path) {
+            Ok(text) => ron::from_str::<Settings>(&text).map_err(|e| format!("invalid {}: {}", path, e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Settings::default(),
+            Err(e) => return Err(format!("cannot read {}: {}", path, e)),
+        };
+        for assignment in overrides{
+            settings.set(assignment)?;
+        }
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    // réglages des images importées (Ctrl+I, --pattern image.png ...), invert vient du raccourci
+    pub fn import_settings(&self, invert: bool) -> ImportSettings{
+        ImportSettings{
+            threshold: self.importThreshold,
+            invert,
+            scale: self.importScale,
+            dither: self.importDither,
+        }
+    }
+
+    // les fonctions de simulation, appelées aussi hors de Bevy, lisent ces deux valeurs sans passer par la ressource
+    pub fn apply(&self){
+        UPDATE_COUNT_LIMIT.store(self.updateCountLimit, Ordering::Relaxed);
+        UNSTABLE_CHANGER_LIMIT.store(self.unstableChangerLimit, Ordering::Relaxed);
+    }
+}
+
+static UPDATE_COUNT_LIMIT: AtomicI32 = AtomicI32::new(20);
+static UNSTABLE_CHANGER_LIMIT: AtomicI32 = AtomicI32::new(3);
+
+pub fn update_count_limit() -> i32{
+    UPDATE_COUNT_LIMIT.load(Ordering::Relaxed)
+}
+
+pub fn unstable_changer_limit() -> i32{
+    UNSTABLE_CHANGER_LIMIT.load(Ordering::Relaxed)
+}
+
+// fichier surveillé et réglages de la ligne de commande à réappliquer à chaque rechargement
+#[derive(Resource)]
+pub struct SettingsSource{
+    pub path: String,
+    pub overrides: Vec<String>,
+    pub lastModified: Option<SystemTime>,
+    pub lastCheck: f32,
+}
+
+pub fn modified(path: &str) -> Option<SystemTime>{
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -1,20 +1,61 @@
 use std::collections::VecDeque;
 use std::process::Command;
+use std::sync::Arc;
 use bevy::asset::{Assets, Handle};
 use bevy::input::ButtonInput;
+use bevy::input::gamepad::GamepadButton;
+use bevy::input::ButtonState;
+use bevy::input::keyboard::{Key, KeyboardInput};
 use bevy::input::mouse::MouseWheel;
-use bevy::math::Vec3;
-use bevy::prelude::{default, Camera, Camera2dBundle, ColorMaterial, Commands, Entity, EventReader, GlobalTransform, In, KeyCode, Mesh, MouseButton, OrthographicProjection, Query, Rectangle, Res, ResMut, Time, Transform, Window, With, Without};
+use bevy::math::{IVec2, Quat, Vec2, Vec3};
+use bevy::color::ColorToPacked;
+use bevy::prelude::{default, BuildChildren, NodeBundle, UiRect, BackgroundColor, ClearColor, Gizmos, TextBundle, TextStyle, Text, Display, ImageBundle, PositionType, Style, TargetCamera, UiImage, Val, Image, Sprite, SpriteBundle, Camera, Camera3dBundle, Has, Color, Cuboid, DirectionalLightBundle, StandardMaterial, Visibility, PbrBundle, RegularPolygon, Triangle2d, Camera2dBundle, ColorMaterial, Commands, Entity, EventReader, GlobalTransform, In, KeyCode, Mesh, MouseButton, OrthographicProjection, Query, Rectangle, Res, ResMut, Time, Transform, Window, With, Without};
 use bevy::reflect::Array;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::render::texture::ImageSampler;
 use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
+use bevy::ui::RelativeCursorPosition;
 use bevy::utils::{HashMap, HashSet};
 use bevy::window::{PresentMode, PrimaryWindow};
-use crate::{CAMERA_SPEED, INVISIBLE, UNSTBLE_CHANGER_LIMIT, UPDATE_COUNT_LIMIT, WHITE, ZOOM_MULTIPLIER};
+use crossbeam_channel::TryRecvError;
+use serde_json::{json, Value};
+use crate::{LOD_SCALE, MAX_DENSITY_SIZE, DENSITY_REFRESH, OBJECTS_REFRESH, MINIMAP_REFRESH, MINIMAP_RESOLUTION, MINIMAP_SIZE, PATTERN_FILE, PATTERNS_DIR, KEYBINDINGS_FILE, IMAGE_FILE, MACROCELL_FILE, MAX_MACROCELL_CELLS, INVISIBLE, WHITE};
 use crate::components;
+use crate::rle;
+use crate::grid;
+use crate::life3d;
+use crate::apgcode;
+use crate::cli;
+use crate::clipboard;
+use crate::export;
+use crate::import;
+use crate::keybindings;
+use crate::keybindings::Action;
+use crate::lenia;
+use crate::macrocell::{self, Macrocell};
+use crate::objects;
+use crate::palette;
+use crate::ltl;
+use crate::record;
+use crate::remote;
+use crate::ruletable;
+use crate::theme;
+use crate::topology::Topology;
+use crate::settings;
+use crate::settings::{Settings, SettingsSource, unstable_changer_limit, update_count_limit};
+use crate::grid::GridType;
+use crate::rules;
+use crate::rules::Rule;
 use crate::components::*;
 use crate::uVec3::uVec3;
 
-pub fn setup_camera(mut commands: Commands){
+pub fn setup_camera(
+    mut commands: Commands,
+    mut meshAssets: ResMut<Assets<Mesh>>,
+    mut materialAssets: ResMut<Assets<StandardMaterial>>
+){
     let mut proj = OrthographicProjection::default();
     proj.near = -1.;
     proj.far = 5.;
@@ -26,7 +67,39 @@ pub fn setup_camera(mut commands: Commands){
             ..default()
         },
         components::MainCamera,
+        CameraControl{
+            bookmarks: [None; 9],
+            follow: None,
+            lastCursor: None,
+        },
+    ));
+
+    // vue 3D, inactive tant qu'on n'est pas en Life 3D
+    commands.spawn((
+        Camera3dBundle{
+            camera: Camera{
+                is_active: false,
+                order: 1,
+                ..default()
+            },
+            ..default()
+        },
+        View3D{
+            enabled: false,
+            yaw: 0.6,
+            pitch: 0.5,
+            distance: 40.,
+            target: Vec3::ZERO,
+        },
     ));
+    commands.spawn(DirectionalLightBundle{
+        transform: Transform::from_xyz(1., 2., 3.).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+    commands.spawn(CubeAssets{
+        mesh: meshAssets.add(Cuboid::from_size(Vec3::splat(0.9))),
+        material: materialAssets.add(StandardMaterial::from(WHITE)),
+    });
 }
 
 pub fn setup_batching(
@@ -38,70 +111,602 @@ pub fn setup_batching(
     commands.spawn((
         MaterialMesh2dBundle {
             mesh: meshAssets.add(Rectangle::default()).into(),
-            transform: Transform::from_xyz(0.,0.,-7.).with_scale(Vec3::splat(1.)),
+            transform: Transform::from_xyz(0.,0.,0.).with_scale(Vec3::splat(1.)),
             material: materialAssets.add(WHITE),
+            visibility: Visibility::Hidden,
             ..default()
         },
         ReferenceTile
     ));
 
+    commands.spawn(StatePalette{
+        materials: Vec::new(),
+        rule: String::new(),
+    });
+
+    // flèche des turmites, pointe vers le haut (direction 0)
+    commands.spawn(AntAssets{
+        mesh: meshAssets.add(Triangle2d::new(Vec2::new(0., 0.45), Vec2::new(-0.3, -0.35), Vec2::new(0.3, -0.35))).into(),
+        material: materialAssets.add(Color::linear_rgba(1., 0.1, 0.1, 1.)),
+    });
+
+    let [a, b, c] = grid::triangle_vertices();
+    commands.spawn(TileMeshes{
+        square: meshAssets.add(Rectangle::default()).into(),
+        hexagon: meshAssets.add(RegularPolygon::new(grid::hexagon_radius(), 6)).into(),
+        triangleUp: meshAssets.add(Triangle2d::new(a, b, c)).into(),
+        triangleDown: meshAssets.add(Triangle2d::new(-a, -b, -c)).into(),
+    });
+}
+
+// couleur d'un état : celle du @COLORS d'une table de règles si elle existe,
+// sinon blanc pour les vivantes, dégradé rouge -> bleu sombre pour les états mourants
+pub fn state_color(state: u8, rule: &Rule, theme: &theme::Theme) -> Color{
+    if let Some(color) = rule.table.as_ref().and_then(|t| t.colors.get(state as usize).copied().flatten()) {
+        return color;
+    }
+    let states = rule.states;
+    if state <= 1 || states <= 2 {
+        return theme.cell;
+    }
+    let t = (state - 2) as f32 / (states - 2).max(1) as f32;
+    Color::linear_rgba(1. - 0.9 * t, 0.4 - 0.3 * t, 0.1 + 0.3 * t, 1.)
+}
+
+// reconstruit les materials quand la règle ou le thème change
+pub fn update_state_palette(
+    tilemap_query: Query<&TileMap>,
+    theme_query: Query<&CurrentTheme>,
+    mut palette_query: Query<&mut StatePalette>,
+    mut materialAssets: ResMut<Assets<ColorMaterial>>
+){
+    let tileMap = tilemap_query.single();
+    let theme = &theme::THEMES[theme_query.single().index];
+    let mut palette = palette_query.single_mut();
+    let rule = format!("{} {}", tileMap.rule.to_string(), theme.name);
+    if palette.rule != rule {
+        palette.materials = (0..tileMap.rule.states).map(|state| materialAssets.add(state_color(state, &tileMap.rule, theme))).collect();
+        palette.rule = rule;
+    }
+}
+
+pub fn setup_theme(mut commands: Commands){
+    commands.spawn(CurrentTheme{
+        index: 0,
+    });
+    commands.insert_resource(ClearColor(theme::THEMES[0].background));
+}
+
+// Ctrl+T : thème suivant
+pub fn cycle_theme(
+    controls_query: Query<&Controls>,
+    mut theme_query: Query<&mut CurrentTheme>,
+    mut clearColor: ResMut<ClearColor>,
+){
+    if !controls_query.single().just_pressed(Action::CycleTheme) {
+        return;
+    }
+    let mut theme = theme_query.single_mut();
+    theme.index = (theme.index + 1) % theme::THEMES.len();
+    clearColor.0 = theme::THEMES[theme.index].background;
+}
+
+pub fn setup_grid_overlay(
+    mut commands: Commands,
+    mut meshAssets: ResMut<Assets<Mesh>>,
+    mut materialAssets: ResMut<Assets<ColorMaterial>>
+){
+    let mesh = meshAssets.add(Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, Vec::<[f32; 3]>::new())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, Vec::<[f32; 4]>::new())
+        .with_inserted_indices(Indices::U32(Vec::new())));
+    commands.spawn((
+        MaterialMesh2dBundle{
+            mesh: mesh.clone().into(),
+            material: materialAssets.add(WHITE),
+            transform: Transform::from_xyz(0., 0., 0.2),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        GridOverlay{
+            mesh,
+            enabled: false,
+            lastView: None,
+        },
+    ));
+}
+
+// bord de l'univers borné ou du tore (--topology), par dessus les cellules
+pub fn display_topology(
+    tilemap_query: Query<&TileMap>,
+    theme_query: Query<&CurrentTheme>,
+    mut gizmos: Gizmos,
+){
+    let tileMap = tilemap_query.single();
+    let Some(((x0, y0), (x1, y1))) = tileMap.topology.bounds() else {
+        return;
+    };
+    if tileMap.rule.wolfram.is_some() || tileMap.rule.lenia.is_some() {
+        return;
+    }
+    // centres des cellules des coins, écartés d'une demi-cellule vers l'extérieur
+    let grid = tileMap.rule.grid;
+    let corners: Vec<Vec2> = [(x0, y0, -1., -1.), (x1, y0, 1., -1.), (x1, y1, 1., 1.), (x0, y1, -1., 1.), (x0, y0, -1., -1.)].into_iter()
+        .map(|(x, y, dx, dy)| grid.to_world(&uVec3::new(x, y, tileMap.layer)).truncate() + Vec2::new(dx, dy) * 0.5)
+        .collect();
+    gizmos.linestrip_2d(corners, theme::THEMES[theme_query.single().index].axes);
+}
+
+// L : afficher / cacher la grille. Lignes fines à chaque cellule quand on est assez près,
+// plus marquées toutes les 10 et 100 cellules, et les axes passant par l'origine.
+// Les lignes sont des quads d'une épaisseur constante à l'écran, tous dans le même mesh.
+pub fn update_grid_overlay(
+    controls_query: Query<&Controls>,
+    tilemap_query: Query<&TileMap>,
+    theme_query: Query<&CurrentTheme>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Transform, &OrthographicProjection), With<MainCamera>>,
+    mut overlay_query: Query<(&mut GridOverlay, &mut Visibility)>,
+    mut meshAssets: ResMut<Assets<Mesh>>,
+){
+    let (mut overlay, mut visibility) = overlay_query.single_mut();
+    if controls_query.single().just_pressed(Action::ToggleGrid) {
+        overlay.enabled = !overlay.enabled;
+        overlay.lastView = None;
+    }
+    let wanted = if overlay.enabled { Visibility::Inherited } else { Visibility::Hidden };
+    if *visibility != wanted {
+        *visibility = wanted;
+    }
+    if !overlay.enabled {
+        return;
+    }
+
+    let (transform, projection) = camera_query.single();
+    let window = window_query.single();
+    let themeIndex = theme_query.single().index;
+    let windowSize = Vec2::new(window.width(), window.height());
+    let view = (transform.translation.truncate(), projection.scale, windowSize, themeIndex);
+    if overlay.lastView == Some(view) {
+        return;
+    }
+    overlay.lastView = Some(view);
+
+    let theme = &theme::THEMES[themeIndex];
+    let scale = projection.scale;
+    let half = windowSize * scale * 0.5;
+    let (min, max) = (view.0 - half, view.0 + half);
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut colors: Vec<[f32; 4]> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+    let mut quad = |a: Vec2, b: Vec2, color: Color| {
+        let start = positions.len() as u32;
+        positions.extend([[a.x, a.y, 0.], [b.x, a.y, 0.], [b.x, b.y, 0.], [a.x, b.y, 0.]]);
+        let c = color.to_linear();
+        colors.extend([[c.red, c.green, c.blue, c.alpha]; 4]);
+        indices.extend([start, start + 1, start + 2, start, start + 2, start + 3]);
+    };
+
+    // seules les grilles carrées ont des lignes de cellules, les bords passent entre les entiers
+    let square = matches!(tilemap_query.single().rule.grid, GridType::Square | GridType::Cubic);
+    if square {
+        for (spacing, color, width) in [(1, theme.grid, 1.), (10, theme.gridMajor, 1.), (100, theme.gridMajor, 2.)] {
+            // pas de lignes à moins de 6 pixels d'écart
+            if spacing as f32 / scale < 6. {
+                continue;
+            }
+            let w = width * scale * 0.5;
+            let first = ((min.x + 0.5) / spacing as f32).ceil() as i32;
+            let last = ((max.x + 0.5) / spacing as f32).floor() as i32;
+            for i in first..last + 1 {
+                let x = (i * spacing) as f32 - 0.5;
+                quad(Vec2::new(x - w, min.y), Vec2::new(x + w, max.y), color);
+            }
+            let first = ((min.y + 0.5) / spacing as f32).ceil() as i32;
+            let last = ((max.y + 0.5) / spacing as f32).floor() as i32;
+            for i in first..last + 1 {
+                let y = (i * spacing) as f32 - 0.5;
+                quad(Vec2::new(min.x, y - w), Vec2::new(max.x, y + w), color);
+            }
+        }
+    }
+    let w = scale;
+    quad(Vec2::new(-w, min.y), Vec2::new(w, max.y), theme.axes);
+    quad(Vec2::new(min.x, -w), Vec2::new(max.x, w), theme.axes);
+
+    if let Some(mesh) = meshAssets.get_mut(&overlay.mesh) {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+        mesh.insert_indices(Indices::U32(indices));
+    }
+}
+
+pub fn window_title(tileMap: &TileMap) -> String{
+    if tileMap.rule.is3D() {
+        return format!("Game Of Life 2D - {} - z = {}", tileMap.rule.to_string(), tileMap.layer);
+    }
+    if tileMap.rule.usesBrush() {
+        return format!("Game Of Life 2D - {} - state {}", tileMap.rule.to_string(), tileMap.brush);
+    }
+    format!("Game Of Life 2D - {}", tileMap.rule.to_string())
+}
+
+// R : passer à la règle suivante dans la liste des règles prédéfinies
+pub fn cycle_rule(
+    controls_query: Query<&Controls>,
+    mut tilemap_query: Query<&mut TileMap>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    mut commands: Commands,
+){
+    if !controls_query.single().just_pressed(Action::CycleRule) {
+        return;
+    }
+    let mut tileMap = tilemap_query.single_mut();
+    // les tables de règles absentes du dossier rules/ sont sautées
+    let presets: Vec<Rule> = (0..rules::PRESETS.len()).filter_map(|i| Rule::parse(rules::PRESETS[i]).ok()).collect();
+    let current = presets.iter().position(|r| *r == tileMap.rule);
+    let next = current.map_or(0, |i| (i + 1) % presets.len());
+    let rule = presets[next].clone();
+
+    let mut inTileMapRemove: Vec<Entity> = Vec::new();
+    change_rule(&mut tileMap, rule, &mut inTileMapRemove);
+    for entity in inTileMapRemove{
+        commands.entity(entity).remove::<InTileMap>();
+    }
+
+    let mut window = windows.single_mut();
+    window.title = window_title(&tileMap);
+}
+
+// passage à une autre règle : les cellules qui n'ont plus de sens sont retirées (entités à délier dans inTileMapRemove)
+pub fn change_rule(tileMap: &mut TileMap, rule: Rule, inTileMapRemove: &mut Vec<Entity>){
+    // les cellules mourantes n'ont plus de sens avec une autre règle
+    let dying: Vec<uVec3> = tileMap.current_state.iter().filter(|(_, (_, _, state))| *state > 1).map(|(pos, _)| *pos).collect();
+    for pos in dying{
+        if let Some((Some(entity), _, _)) = tileMap.current_state.remove(&pos){
+            inTileMapRemove.push(entity);
+        }
+    }
+    // Lenia a sa propre grille continue, les cellules discrètes sont retirées
+    if rule.lenia.is_some() {
+        let mut cells: Vec<(Option<Entity>, i32, u8)> = tileMap.current_state.drain().map(|(_, c)| c).collect();
+        cells.extend(tileMap.stable_current_state.drain().map(|(_, c)| c));
+        for (entity, _, _) in cells{
+            if let Some(entity) = entity {
+                inTileMapRemove.push(entity);
+            }
+        }
+    }
+    // en quittant la 3D, on ne garde que la couche 0
+    if tileMap.rule.is3D() && !rule.is3D() {
+        let layers: Vec<uVec3> = tileMap.current_state.keys().filter(|pos| pos.z != 0).copied().collect();
+        for pos in layers{
+            if let Some((Some(entity), _, _)) = tileMap.current_state.remove(&pos){
+                inTileMapRemove.push(entity);
+            }
+        }
+        tileMap.layer = 0;
+    }
+    // changement de réseau : les positions n'ont plus le même sens, tout repasse par l'affichage
+    if rule.grid != tileMap.rule.grid {
+        let stable: Vec<(uVec3, (Option<Entity>, i32, u8))> = tileMap.stable_current_state.drain().collect();
+        for (pos, (entity, _, state)) in stable{
+            tileMap.current_state.insert(pos, (entity, update_count_limit(), state));
+        }
+    }
+    if rule.turmite.is_none() {
+        tileMap.ants.clear();
+    }
+    tileMap.rule = rule;
+    tileMap.brush = 1;
+    tileMap.row1D = None;
+}
+
+// 1 à 9 : état dessiné au clic gauche quand la règle est une table de règles ou une turmite
+pub fn select_brush(
+    controls_query: Query<&Controls>,
+    mut tilemap_query: Query<&mut TileMap>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+){
+    let controls = controls_query.single();
+    let Some(state) = (0..9).position(|i| controls.just_pressed(Action::Brush(i + 1))) else {
+        return;
+    };
+    let mut tileMap = tilemap_query.single_mut();
+    if !tileMap.rule.usesBrush() || state + 1 >= tileMap.rule.states as usize {
+        return;
+    }
+    tileMap.brush = state as u8 + 1;
+    let mut window = windows.single_mut();
+    window.title = window_title(&tileMap);
 }
 
 pub fn camera_mouvement(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    controls_query: Query<&Controls>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
     mut mouse_wheel_input: EventReader<MouseWheel>,
-    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection), With<components::MainCamera>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection, &mut CameraControl), With<components::MainCamera>>,
+    view_query: Query<&View3D>,
+    settings: Res<Settings>,
     time: Res<Time>
 ){
-    if let Ok((mut transform, mut projection)) = camera_query.get_single_mut(){
+    if let Ok((mut transform, mut projection, mut control)) = camera_query.get_single_mut(){
         let mut direction = Vec3::ZERO;
+        let controls = controls_query.single();
 
-        if keyboard_input.pressed(KeyCode::KeyW) {
+        if controls.pressed(Action::PanUp) {
             direction += Vec3::new(0.,1.,0.);
         }
-        if keyboard_input.pressed(KeyCode::KeyS) {
+        if controls.pressed(Action::PanDown) {
             direction += Vec3::new(0.,-1.,0.);
         }
-        if keyboard_input.pressed(KeyCode::KeyD) {
+        if controls.pressed(Action::PanRight) {
             direction += Vec3::new(1.,0.,0.);
         }
-        if keyboard_input.pressed(KeyCode::KeyA) {
+        if controls.pressed(Action::PanLeft) {
             direction += Vec3::new(-1.,0.,0.);
         }
 
         if direction.length() > 0.0 {
             direction = direction.normalize();
+            // se déplacer arrête le suivi
+            control.follow = None;
         }
 
-        transform.translation += direction * CAMERA_SPEED * time.delta_seconds();
+        transform.translation += direction * settings.cameraSpeed * time.delta_seconds();
+
+        let window = window_query.single();
+        let cursor = window.cursor_position();
+
+        // glisser avec le bouton du milieu
+        if mouse_input.pressed(MouseButton::Middle) {
+            if let (Some(last), Some(current)) = (control.lastCursor, cursor) {
+                let delta = current - last;
+                transform.translation.x -= delta.x * projection.scale;
+                transform.translation.y += delta.y * projection.scale;
+                if delta != Vec2::ZERO {
+                    control.follow = None;
+                }
+            }
+            control.lastCursor = cursor;
+        } else {
+            control.lastCursor = None;
+        }
 
         let mut wheel: f32 = 0.0;
 
         for i in mouse_wheel_input.read(){
             wheel += i.y;
         }
+        // en vue 3D la molette éloigne la caméra 3D (control_view3d)
+        if view_query.single().enabled {
+            wheel = 0.0;
+        }
+        // zoom au clavier ou à la manette, centré sur l'écran
+        if controls.pressed(Action::ZoomIn) != controls.pressed(Action::ZoomOut) {
+            let speed = if controls.pressed(Action::ZoomIn) { 1. } else { -1. };
+            projection.scale = (projection.scale * (1. - speed * settings.zoomMultiplier * 0.3 * time.delta_seconds())).max(0.01);
+        }
 
         let mut zoom = projection.scale;
         if wheel != 0.0 {
-            zoom -= (wheel * 0.01) * zoom * ZOOM_MULTIPLIER;
+            zoom -= (wheel * 0.01) * zoom * settings.zoomMultiplier;
 
             if zoom < 0.01 {
                 zoom = 0.01;
             }
+            // le point du monde sous le curseur ne bouge pas
+            if let Some(cursor) = cursor {
+                let offset = (cursor - Vec2::new(window.width(), window.height()) * 0.5) * Vec2::new(1., -1.);
+                let under = transform.translation.truncate() + offset * projection.scale;
+                let translation = under - offset * zoom;
+                transform.translation.x = translation.x;
+                transform.translation.y = translation.y;
+            }
             projection.scale = zoom;
         }
         // println!("{}", zoom);
     }
 }
 
+// F : cadrer toutes les cellules vivantes, Ctrl+1..9 : enregistrer la vue, Alt+1..9 : y revenir,
+// T : suivre l'objet sous le curseur (vaisseau), T sur du vide : arrêter le suivi
+pub fn camera_shortcuts(
+    controls_query: Query<&Controls>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    tilemap_query: Query<&TileMap>,
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection, &mut CameraControl), With<MainCamera>>,
+){
+    let (mut transform, mut projection, mut control) = camera_query.single_mut();
+    let tileMap = tilemap_query.single();
+    let window = window_query.single();
+    let controls = controls_query.single();
+
+    for i in 0..9 {
+        if controls.just_pressed(Action::SaveBookmark(i as u8 + 1)) {
+            control.bookmarks[i] = Some((transform.translation, projection.scale));
+        } else if controls.just_pressed(Action::RecallBookmark(i as u8 + 1)) {
+            if let Some((translation, scale)) = control.bookmarks[i] {
+                transform.translation = translation;
+                projection.scale = scale;
+                control.follow = None;
+            }
+        }
+    }
+
+    let fit = controls.just_pressed(Action::FitView);
+    let select = controls.just_pressed(Action::Follow);
+    if !fit && !select && control.follow.is_none() {
+        return;
+    }
+    // toutes les cellules seulement pour F et T, le suivi ne regarde qu'autour de l'objet
+    let cells: Vec<Vec2> = if fit || select {
+        tileMap.current_state.keys().chain(tileMap.stable_current_state.keys())
+            .filter(|pos| pos.z == tileMap.layer)
+            .map(|pos| tileMap.rule.grid.to_world(pos).truncate())
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    if fit && !cells.is_empty() {
+        let min = cells.iter().fold(Vec2::splat(f32::MAX), |m, p| m.min(*p));
+        let max = cells.iter().fold(Vec2::splat(f32::MIN), |m, p| m.max(*p));
+        let center = (min + max) * 0.5;
+        transform.translation.x = center.x;
+        transform.translation.y = center.y;
+        let size = max - min + Vec2::splat(2.);
+        projection.scale = (size.x / window.width()).max(size.y / window.height()).max(0.01) * 1.1;
+        control.follow = None;
+    }
+
+    if select {
+        control.follow = window.cursor_position().and_then(|cursor| {
+            let offset = (cursor - Vec2::new(window.width(), window.height()) * 0.5) * Vec2::new(1., -1.);
+            select_object(&cells, transform.translation.truncate() + offset * projection.scale)
+        });
+    }
+
+    // l'objet suivi est recherché autour de sa dernière position, la caméra se centre dessus
+    if let Some((center, radius)) = control.follow {
+        let near = cells_near(tileMap, center, radius);
+        if near.is_empty() {
+            control.follow = None;
+        } else {
+            let centroid = near.iter().fold(Vec2::ZERO, |a, p| a + *p) / near.len() as f32;
+            let extent = near.iter().map(|p| p.distance(centroid)).fold(0., f32::max);
+            control.follow = Some((centroid, extent + 4.));
+            transform.translation.x = centroid.x;
+            transform.translation.y = centroid.y;
+        }
+    }
+}
+
+// centres des cellules de la couche éditée à moins de radius de center : les positions du carré autour sont
+// cherchées une à une, sauf si la population est plus petite que ce carré
+fn cells_near(tileMap: &TileMap, center: Vec2, radius: f32) -> Vec<Vec2>{
+    let grid = tileMap.rule.grid;
+    let (min, max) = (center - Vec2::splat(radius + 1.), center + Vec2::splat(radius + 1.));
+    let (a, b) = (grid.from_world(min), grid.from_world(max));
+    let (y0, y1) = (a.y.min(b.y) - 1, a.y.max(b.y) + 1);
+    let side = (2. * radius + 3.) as usize;
+    let population = tileMap.current_state.len() + tileMap.stable_current_state.len();
+    let positions: Vec<uVec3> = if side * side > population {
+        tileMap.current_state.keys().chain(tileMap.stable_current_state.keys()).filter(|pos| pos.z == tileMap.layer).copied().collect()
+    } else {
+        let mut positions: Vec<uVec3> = Vec::new();
+        for y in y0..y1 + 1 {
+            // bornes de la ligne : en hexagonal le x dépend de la ligne
+            let rowY = grid.to_world(&uVec3::new(0, y, 0)).y;
+            let (xa, xb) = (grid.from_world(Vec2::new(min.x, rowY)).x - 1, grid.from_world(Vec2::new(max.x, rowY)).x + 1);
+            for x in xa..xb + 1 {
+                let pos = uVec3::new(x, y, tileMap.layer);
+                if tileMap.current_state.contains_key(&pos) || tileMap.stable_current_state.contains_key(&pos) {
+                    positions.push(pos);
+                }
+            }
+        }
+        positions
+    };
+    positions.iter().map(|pos| grid.to_world(pos).truncate()).filter(|p| p.distance(center) <= radius).collect()
+}
+
+// objet sous le curseur : cellules reliées à la plus proche par des écarts d'au plus 2 cases (dans un rayon de 100)
+fn select_object(cells: &Vec<Vec2>, point: Vec2) -> Option<(Vec2, f32)>{
+    let cells: Vec<Vec2> = cells.iter().filter(|p| p.distance(point) <= 100.).copied().collect();
+    let start = cells.iter().copied().filter(|p| p.distance(point) <= 2.).min_by(|a, b| a.distance(point).total_cmp(&b.distance(point)))?;
+    // cases de 2.5 de côté : les voisins d'une cellule sont dans les 9 cases autour de la sienne
+    let bucket = |p: Vec2| (p / 2.5).floor().as_ivec2();
+    // les centres de deux cellules sont à au moins 0.5 l'un de l'autre (réseau triangulaire)
+    let key = |p: Vec2| (p * 4.).round().as_ivec2();
+    let mut buckets: HashMap<IVec2, Vec<Vec2>> = HashMap::new();
+    for p in cells.iter(){
+        buckets.entry(bucket(*p)).or_default().push(*p);
+    }
+    let mut visited: HashSet<IVec2> = HashSet::from([key(start)]);
+    let mut object: Vec<Vec2> = vec![start];
+    let mut i = 0;
+    while i < object.len() && object.len() < 10000 {
+        let p = object[i];
+        let b = bucket(p);
+        for dy in -1..2 {
+            for dx in -1..2 {
+                for q in buckets.get(&(b + IVec2::new(dx, dy))).into_iter().flatten(){
+                    if (q.x - p.x).abs() <= 2.5 && (q.y - p.y).abs() <= 2.5 && visited.insert(key(*q)) {
+                        object.push(*q);
+                    }
+                }
+            }
+        }
+        i += 1;
+    }
+    let centroid = object.iter().fold(Vec2::ZERO, |a, p| a + *p) / object.len() as f32;
+    let extent = object.iter().map(|p| p.distance(centroid)).fold(0., f32::max);
+    Some((centroid, extent + 4.))
+}
+
+// pose une cellule (état 0 : l'efface) ; les cellules stables autour repassent dans la map active pour être recalculées.
+// De loin (LOD), elles n'ont plus de tuile : l'entité est reprise telle quelle, None compris.
+pub fn draw_cell(tileMap: &mut TileMap, pos: uVec3, state: u8, inTileMapRemove: &mut Vec<Entity>){
+    let current = tileMap.current_state.get(&pos).or(tileMap.stable_current_state.get(&pos)).copied();
+    if current.map_or(0, |(_, _, s)| s) == state {
+        return;
+    }
+    let mut switching: Vec<(uVec3, Option<Entity>, u8)> = Vec::new();
+    let limit = unstable_changer_limit();
+    for i in -limit..limit+1{
+        for j in -limit..limit+1{
+            let spos = uVec3::new(pos.x + i, pos.y + j, pos.z);
+            if let Some((entity, _, sstate)) = tileMap.stable_current_state.get(&spos){
+                switching.push((spos, *entity, *sstate));
+            }
+        }
+    }
+    for (spos, sent, sstate) in switching{
+        tileMap.current_state.insert(spos, (sent, update_count_limit(), sstate));
+        tileMap.stable_current_state.remove(&spos);
+    }
+    if state == 0 {
+        if let Some((Some(entity), _, _)) = tileMap.current_state.remove(&pos) {
+            inTileMapRemove.push(entity);
+        }
+        return;
+    }
+    tileMap.current_state.insert(pos, (current.and_then(|(e, _, _)| e), update_count_limit(), state));
+}
+
 pub fn tile_placement(
     window_query: Query<&Window, With<PrimaryWindow>>,
     camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
     mut tilemap_query: Query<&mut TileMap>,
     mouse_input: Res<ButtonInput<MouseButton>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
     mut commands: Commands,
     mut tile_query: Query<(Entity, &Tile, &mut Transform), With<InTileMap>>,
+    minimap_query: Query<&RelativeCursorPosition, With<Minimap>>,
+    stamp_query: Query<&Stamp>,
 ){
+    if cursor_on_minimap(&minimap_query) {
+        return;
+    }
+    // le clic pose le motif collé (clipboard_stamp)
+    if stamp_query.get_single().map_or(false, |stamp| stamp.pending.is_some() || stamp.consumed) {
+        return;
+    }
+    // Shift + clic sert à poser les turmites (place_ants)
+    let shift = keyboard_input.pressed(KeyCode::ShiftLeft) || keyboard_input.pressed(KeyCode::ShiftRight);
+    if shift && tilemap_query.get_single().map_or(false, |t| t.rule.turmite.is_some()) {
+        return;
+    }
+    // Lenia est édité par edit_lenia
+    if tilemap_query.get_single().map_or(false, |t| t.rule.lenia.is_some()) {
+        return;
+    }
+    // Alt + glisser sert à choisir la zone enregistrée (select_record_region)
+    if keyboard_input.pressed(KeyCode::AltLeft) || keyboard_input.pressed(KeyCode::AltRight) {
+        return;
+    }
     let mut key: MouseButton = MouseButton::Forward;
     if(mouse_input.pressed(MouseButton::Left) && !mouse_input.pressed(MouseButton::Right)){
         key = MouseButton::Left;
@@ -110,66 +715,35 @@ pub fn tile_placement(
     }
     if(key == MouseButton::Left){
         if let Ok(mut tileMap) = tilemap_query.get_single_mut(){
+            let layer = tileMap.layer;
             let (camera, global_transform) = camera_query.single();
             let window = window_query.single();
             if let Some(mut world_position) = window.cursor_position()
                 .and_then(|cursor| camera.viewport_to_world(global_transform, cursor))
                 .map(|ray| ray.origin.truncate()){
-                world_position.x = world_position.x.round();
-                world_position.y = world_position.y.round();
-                if  !tileMap.current_state.contains_key(&uVec3::new(world_position.x as i32, world_position.y as i32, 0)){
-                    tileMap.current_state.insert(uVec3::new(world_position.x as i32, world_position.y as i32, 0), (None, UPDATE_COUNT_LIMIT));
-                    let mut switching: Vec<(uVec3, Entity)> = Vec::new();
-                    for i in -UNSTBLE_CHANGER_LIMIT..UNSTBLE_CHANGER_LIMIT+1{
-                        for j in -UNSTBLE_CHANGER_LIMIT..UNSTBLE_CHANGER_LIMIT+1{
-                            if tileMap.stable_current_state.contains_key(&uVec3::new(world_position.x as i32 + i, world_position.y as i32 + j, 0)){
-                                let (entity, updateCounter) = tileMap.stable_current_state.get(&uVec3::new(world_position.x as i32 + i, world_position.y as i32 + j, 0)).unwrap();
-                                switching.push((uVec3::new(world_position.x as i32 + i, world_position.y as i32 + j, 0), entity.unwrap()));
-                            }
-                        }
-                    }
-
-                    for (spos, sent) in switching{
-                        tileMap.current_state.insert(spos, (Option::from(sent), UPDATE_COUNT_LIMIT));
-                        tileMap.stable_current_state.remove(&spos);
-                    }
-                }
+                let cell = tileMap.rule.grid.from_world(world_position);
+                world_position.x = cell.x as f32;
+                world_position.y = cell.y as f32;
+                let state = if tileMap.rule.usesBrush() { tileMap.brush } else { 1 };
+                // une cellule posée ne libère jamais de tuile
+                draw_cell(&mut tileMap, uVec3::new(world_position.x as i32, world_position.y as i32, layer), state, &mut Vec::new());
             }
         }
     } else if key == MouseButton::Right{
         if let Ok(mut tileMap) = tilemap_query.get_single_mut(){
+            let layer = tileMap.layer;
             let (camera, global_transform) = camera_query.single();
             let window = window_query.single();
             if let Some(mut world_position) = window.cursor_position()
                 .and_then(|cursor| camera.viewport_to_world(global_transform, cursor))
                 .map(|ray| ray.origin.truncate()){
-                world_position.x = world_position.x.round();
-                world_position.y = world_position.y.round();
-                let mut switching: Vec<(uVec3, Entity)> = Vec::new();
-                for i in -1..2{
-                    for j in -1..2{
-                        if tileMap.stable_current_state.contains_key(&uVec3::new(world_position.x as i32 + i, world_position.y as i32 + j, 0)){
-                            let (entity, updateCounter) = tileMap.stable_current_state.get(&uVec3::new(world_position.x as i32 + i, world_position.y as i32 + j, 0)).unwrap();
-                            switching.push((uVec3::new(world_position.x as i32 + i, world_position.y as i32 + j, 0), entity.unwrap()));
-                        }
-                    }
-                }
-                for (spos, sent) in switching{
-                    tileMap.current_state.insert(spos, (Option::from(sent), UPDATE_COUNT_LIMIT));
-                    tileMap.stable_current_state.remove(&spos);
-                }
-
-                if let Some((entity, updateCounter)) = tileMap.stable_current_state.get(&uVec3::new(world_position.x as i32, world_position.y as i32, 0)){
-                    if !entity.is_none(){
-                        commands.entity(entity.unwrap()).remove::<InTileMap>();
-                        tileMap.stable_current_state.remove(&uVec3::new(world_position.x as i32, world_position.y as i32, 0));
-                    }
-                }
-                if let Some((entity, updateCounter)) = tileMap.current_state.get(&uVec3::new(world_position.x as i32, world_position.y as i32, 0)){
-                    if !entity.is_none(){
-                        commands.entity(entity.unwrap()).remove::<InTileMap>();
-                        tileMap.current_state.remove(&uVec3::new(world_position.x as i32, world_position.y as i32, 0));
-                    }
+                let cell = tileMap.rule.grid.from_world(world_position);
+                world_position.x = cell.x as f32;
+                world_position.y = cell.y as f32;
+                let mut inTileMapRemove: Vec<Entity> = Vec::new();
+                draw_cell(&mut tileMap, uVec3::new(world_position.x as i32, world_position.y as i32, layer), 0, &mut inTileMapRemove);
+                for entity in inTileMapRemove{
+                    commands.entity(entity).remove::<InTileMap>();
                 }
             }
         }
@@ -180,35 +754,94 @@ pub fn setup_simulation(
     mut commands: Commands,
 ){
     commands.spawn((
-        TileMap{
-            running: false,
-            current_state: HashMap::new(),
-            stable_current_state: HashMap::new(),
-        }
+        TileMap::new(Rule::life()),
+        SimulationSpeed{
+            generationsPerSecond: None,
+            pending: 0.,
+            generations: 0,
+        },
     ));
 }
 
+// motif, règle et vitesse passés en ligne de commande
+pub fn apply_command_line(
+    options: Res<cli::Options>,
+    mut tilemap_query: Query<(&mut TileMap, &mut SimulationSpeed)>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+){
+    let (mut tileMap, mut speed) = tilemap_query.single_mut();
+    if let Some(rule) = &options.rule {
+        tileMap.rule = rule.clone();
+    }
+    for (pos, state) in options.cells.iter(){
+        tileMap.current_state.insert(*pos, (None, update_count_limit(), *state));
+    }
+    tileMap.topology = options.topology;
+    tileMap.running = options.running;
+    speed.generationsPerSecond = options.generationsPerSecond;
+    windows.single_mut().title = window_title(&tileMap);
+}
+
 
+// les tuiles qui ne sont plus liées à une cellule sont cachées (z = -7 ne suffit plus, c'est une couche valide en 3D)
 pub fn hide_off_tiles(
-    mut not_in_map_tile_query: Query<(Entity, &Tile, &mut Transform), Without<InTileMap>>,
+    mut tile_visibility_query: Query<(&mut Visibility, Has<InTileMap>), With<Tile>>,
 ){
-    not_in_map_tile_query.par_iter_mut().for_each(|(entity, tile, mut transform)|{
-        transform.translation = Vec3::new(transform.translation.x, transform.translation.y, -7.);
+    tile_visibility_query.par_iter_mut().for_each(|(mut visibility, inTileMap)|{
+        let wanted = if inTileMap { Visibility::Inherited } else { Visibility::Hidden };
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
     });
 }
 
 
+// toutes les cellules perdent leur tuile (entités à délier dans inTileMapRemove)
+pub fn release_tiles(tileMap: &mut TileMap, inTileMapRemove: &mut Vec<Entity>){
+    let tileMap = &mut *tileMap;
+    for (_, (entity, _, _)) in tileMap.current_state.iter_mut().chain(tileMap.stable_current_state.iter_mut()){
+        if let Some(e) = entity.take() {
+            inTileMapRemove.push(e);
+        }
+    }
+}
+
 // de l'affichage, pas le plus important.
 pub fn display_tilemap(
     mut refresh_timer_query: Query<&mut RefreshTimer>,
     mut tilemap_query: Query<&mut TileMap>,
-    mut not_in_map_tile_query: Query<(Entity, &Tile, &mut Transform), Without<InTileMap>>,
-    mut tile_query: Query<(Entity, &Tile, &mut Transform), With<InTileMap>>,
-    mut reference_tile_query: Query<(&ReferenceTile, &Mesh2dHandle, &Handle<ColorMaterial>)>,
+    mut not_in_map_tile_query: Query<(Entity, &Tile, &mut Transform, &mut Handle<ColorMaterial>, &mut Mesh2dHandle), Without<InTileMap>>,
+    mut tile_query: Query<(Entity, &Tile, &mut Transform, &mut Handle<ColorMaterial>, &mut Mesh2dHandle), With<InTileMap>>,
+    meshes_query: Query<&TileMeshes>,
+    palette_query: Query<&StatePalette>,
+    camera_query: Query<&OrthographicProjection, With<MainCamera>>,
+    mut density_query: Query<&mut DensityView>,
     mut commands: Commands,
     time: Res<Time>
 ){
 
+    // de loin, les cellules sont dessinées par display_density : plus aucune tuile n'est liée
+    let lod = camera_query.single().scale > LOD_SCALE;
+    let mut density = density_query.single_mut();
+    if lod {
+        let mut inTileMapRemove: Vec<Entity> = Vec::new();
+        release_tiles(&mut tilemap_query.single_mut(), &mut inTileMapRemove);
+        for entity in inTileMapRemove{
+            commands.entity(entity).remove::<InTileMap>();
+        }
+        density.lod = true;
+        return;
+    }
+    if density.lod {
+        // les cellules stables ont perdu leur tuile, elles repassent par la map active pour être redessinées
+        density.lod = false;
+        let mut tileMap = tilemap_query.single_mut();
+        let stable: Vec<(uVec3, (Option<Entity>, i32, u8))> = tileMap.stable_current_state.drain().collect();
+        for (pos, (entity, _, state)) in stable{
+            tileMap.current_state.insert(pos, (entity, update_count_limit(), state));
+        }
+    }
+
     let mut refresh_timer = refresh_timer_query.single_mut();
     // println!("{} {} {}", refresh_timer.lastRefresh, refresh_timer.timeBetweenRefresh, time.elapsed().as_millis());
     if(refresh_timer.timeBetweenRefresh != 0 && refresh_timer.lastRefresh + refresh_timer.timeBetweenRefresh >= time.elapsed().as_millis()){
@@ -231,23 +864,44 @@ pub fn display_tilemap(
 
 
 
-    let (reft, refmesh, refmat) = reference_tile_query.single();
+    let meshes = meshes_query.single();
+    let palette = palette_query.single();
+    let grid = tileMap.rule.grid;
+    let layer = tileMap.layer;
     
-    for (pos, (entity, updateState)) in tileMap.current_state.iter_mut(){
+    for (pos, (entity, updateState, state)) in tileMap.current_state.iter_mut(){
+        // en 3D, seule la couche éditée est affichée en 2D
+        if pos.z != layer {
+            if let Some(e) = entity.take() {
+                commands.entity(e).remove::<InTileMap>();
+            }
+            continue;
+        }
+        let refmat = &palette.materials[*state as usize];
+        let refmesh = meshes.get(grid, pos);
+        let translation = grid.to_world(pos);
         if !entity.is_none(){
-            if let Ok((tileEntity, tile, mut transform)) = tile_query.get_mut(entity.unwrap()){
-                transform.translation = pos.toVec3();
+            if let Ok((tileEntity, tile, mut transform, mut material, mut mesh)) = tile_query.get_mut(entity.unwrap()){
+                transform.translation = translation;
+                if *material != *refmat {
+                    *material = refmat.clone();
+                }
+                if mesh.0 != refmesh.0 {
+                    *mesh = refmesh.clone();
+                }
             } else if(!currTile.is_none()){
-                let (tileEntity, tile, mut tileTransform) = currTile.unwrap();
+                let (tileEntity, tile, mut tileTransform, mut material, mut mesh) = currTile.unwrap();
                 *entity = Option::from(tileEntity);
-                tileTransform.translation = pos.toVec3();
+                tileTransform.translation = translation;
+                *material = refmat.clone();
+                *mesh = refmesh.clone();
                 currTile = tiles.next();
                 commands.entity(tileEntity).insert(InTileMap);
             } else if(index < tileMapSize){
                 let ent = commands.spawn((
                     MaterialMesh2dBundle {
                         mesh: refmesh.clone(),
-                        transform: Transform::from_translation(pos.toVec3()).with_scale(Vec3::splat(1.)),
+                        transform: Transform::from_translation(translation).with_scale(Vec3::splat(1.)),
                         material: refmat.clone(),
                         ..default()
                     },
@@ -258,16 +912,18 @@ pub fn display_tilemap(
             }
         } else {
             if(!currTile.is_none()){
-                let (tileEntity, tile, mut tileTransform) = currTile.unwrap();
+                let (tileEntity, tile, mut tileTransform, mut material, mut mesh) = currTile.unwrap();
                 *entity = Option::from(tileEntity);
-                tileTransform.translation = pos.toVec3();
+                tileTransform.translation = translation;
+                *material = refmat.clone();
+                *mesh = refmesh.clone();
                 currTile = tiles.next();
                 commands.entity(tileEntity).insert(InTileMap);
             } else if(index < tileMapSize){
                 let ent = commands.spawn((
                     MaterialMesh2dBundle {
                         mesh: refmesh.clone(),
-                        transform: Transform::from_translation(pos.toVec3()).with_scale(Vec3::splat(1.)),
+                        transform: Transform::from_translation(translation).with_scale(Vec3::splat(1.)),
                         material: refmat.clone(),
                         ..default()
                     },
@@ -285,11 +941,9 @@ pub fn display_tilemap(
         let unusedPercentage = ((tilesSize-index) / tilesSize) * 100;
         for i in 0..(tilesSize-index){
             if(!currTile.is_none()){
-                let (entity, tile, mut transform) = currTile.unwrap();
+                let (entity, tile, mut transform, _, _) = currTile.unwrap();
                 if(unusedPercentage > 20 && (((tilesSize-index) / tilesSize) * 100) > 80){
                     commands.entity(entity).despawn();
-                } else {
-                    transform.translation = Vec3::new(0.,0.,-7.);
                 }
                 currTile = tiles.next();
             }
@@ -299,6 +953,112 @@ pub fn display_tilemap(
     // println!("{} + {} = {} tiles {}s", sizea, sizeb, tileMapSize, time.delta().as_secs_f64());
 }
 
+pub fn setup_density(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+){
+    let mut image = Image::new_fill(
+        Extent3d{ width: 1, height: 1, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        &[0, 0, 0, 0],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.sampler = ImageSampler::nearest();
+    let handle = images.add(image);
+    commands.spawn((
+        SpriteBundle{
+            texture: handle.clone(),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        DensityView{
+            image: handle,
+            lod: false,
+            lastUpdate: -DENSITY_REFRESH,
+        },
+    ));
+}
+
+// blocs de densité sur la zone visible : un pixel par bloc de 2^n x 2^n cellules, l'opacité suit la population.
+// Recalculé toutes les DENSITY_REFRESH secondes comme la minimap, pas à chaque frame.
+pub fn display_density(
+    tilemap_query: Query<&TileMap>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Transform, &OrthographicProjection), With<MainCamera>>,
+    mut density_query: Query<(&mut DensityView, &mut Transform, &mut Sprite, &mut Visibility), Without<MainCamera>>,
+    theme_query: Query<&CurrentTheme>,
+    mut images: ResMut<Assets<Image>>,
+    time: Res<Time>,
+){
+    let (mut density, mut transform, mut sprite, mut visibility) = density_query.single_mut();
+    if !density.lod {
+        if *visibility != Visibility::Hidden {
+            *visibility = Visibility::Hidden;
+        }
+        // mise à jour immédiate au prochain passage en LOD
+        density.lastUpdate = -DENSITY_REFRESH;
+        return;
+    }
+    if *visibility != Visibility::Inherited {
+        *visibility = Visibility::Inherited;
+    }
+    if time.elapsed_seconds() - density.lastUpdate < DENSITY_REFRESH {
+        return;
+    }
+    density.lastUpdate = time.elapsed_seconds();
+    let tileMap = tilemap_query.single();
+    let (cameraTransform, projection) = camera_query.single();
+    let window = window_query.single();
+
+    let mut block = (projection.scale.ceil() as u32).next_power_of_two() as i32;
+    let half = Vec2::new(window.width(), window.height()) * projection.scale * 0.5;
+    let center = cameraTransform.translation.truncate();
+    let (min, max) = (center - half, center + half);
+    while ((max.x - min.x) / block as f32) as u32 + 2 > MAX_DENSITY_SIZE || ((max.y - min.y) / block as f32) as u32 + 2 > MAX_DENSITY_SIZE {
+        block *= 2;
+    }
+    let bx0 = (min.x / block as f32).floor() as i32;
+    let by0 = (min.y / block as f32).floor() as i32;
+    let width = ((max.x / block as f32).floor() as i32 - bx0 + 1) as usize;
+    let height = ((max.y / block as f32).floor() as i32 - by0 + 1) as usize;
+
+    let mut counts: Vec<u32> = vec![0; width * height];
+    for pos in tileMap.current_state.keys().chain(tileMap.stable_current_state.keys()){
+        if pos.z != tileMap.layer {
+            continue;
+        }
+        let p = tileMap.rule.grid.to_world(pos);
+        let bx = (p.x / block as f32).floor() as i32 - bx0;
+        let by = (p.y / block as f32).floor() as i32 - by0;
+        if bx >= 0 && by >= 0 && (bx as usize) < width && (by as usize) < height {
+            // ligne 0 de l'image en haut
+            counts[(height - 1 - by as usize) * width + bx as usize] += 1;
+        }
+    }
+
+    let Some(image) = images.get_mut(&density.image) else {
+        return;
+    };
+    if image.width() as usize != width || image.height() as usize != height {
+        image.resize(Extent3d{ width: width as u32, height: height as u32, depth_or_array_layers: 1 });
+    }
+    let full = (block * block) as f32;
+    let [r, g, b, _] = theme::THEMES[theme_query.single().index].cell.to_srgba().to_u8_array();
+    for (pixel, count) in image.data.chunks_mut(4).zip(counts.iter()){
+        if *count == 0 {
+            pixel.copy_from_slice(&[0, 0, 0, 0]);
+        } else {
+            // couleur des cellules du thème, racine pour que les blocs peu peuplés restent visibles
+            let a = (64. + 191. * (*count as f32 / full).min(1.).sqrt()) as u8;
+            pixel.copy_from_slice(&[r, g, b, a]);
+        }
+    }
+    let size = Vec2::new(width as f32, height as f32) * block as f32;
+    sprite.custom_size = Some(size);
+    transform.translation = Vec3::new(bx0 as f32 * block as f32 - 0.5 + size.x * 0.5, by0 as f32 * block as f32 - 0.5 + size.y * 0.5, 0.);
+}
+
 pub fn setup_tiles_cache(mut commands: Commands){
     commands.spawn(TilesCache{
         entities: Vec::new()
@@ -331,12 +1091,14 @@ pub fn setup_refresh_timer(
 
 
 pub fn run_simulation(
-    mut tilemap_query: Query<&mut TileMap>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut tilemap_query: Query<(&mut TileMap, &mut SimulationSpeed)>,
+    controls_query: Query<&Controls>,
+    time: Res<Time>,
     mut commands: Commands,
 ){
-    let mut tileMap = tilemap_query.single_mut();
-    if(keyboard_input.just_pressed(KeyCode::Space)){
+    let (mut tileMap, mut speed) = tilemap_query.single_mut();
+    let controls = controls_query.single();
+    if(controls.just_pressed(Action::Run)){
         tileMap.running = !tileMap.running;
         if(tileMap.running){
             // println!("Simulation started");
@@ -344,94 +1106,171 @@ pub fn run_simulation(
             // println!("Simulation stopped");
         }
     }
-    if(tileMap.running){
-        let mut newTileMap: HashMap<uVec3, (Option<Entity>, i32)> = tileMap.current_state.clone();
-        let mut newStableTileMap: HashMap<uVec3, (Option<Entity>, i32)> = tileMap.stable_current_state.clone();
+    // N : une seule génération pendant la pause
+    let step = !tileMap.running && controls.just_pressed(Action::Step);
+    let generations = match speed.generationsPerSecond {
+        _ if step => 1,
+        _ if !tileMap.running => 0,
+        None => 1,
+        Some(rate) => {
+            // au plus un quart de seconde de retard, pour ne pas bloquer l'affichage après un ralentissement
+            speed.pending = (speed.pending + rate * time.delta_seconds()).min(rate * 0.25 + 1.);
+            let generations = speed.pending.floor();
+            speed.pending -= generations;
+            generations as u32
+        }
+    };
+    speed.generations = generations;
+    if(generations > 0 && tileMap.rule.lenia.is_none()){
         let mut inTileMapRemove: Vec<Entity> = Vec::new();
-        //TODO multithread the inside of this loop, or not cuz it might be slower WTF ???
-        for tile in tileMap.current_state.iter(){
-            checkArround(tile.0, &tileMap.current_state, &tileMap.stable_current_state, &mut newTileMap, &mut newStableTileMap, &mut inTileMapRemove);
+        for _ in 0..generations {
+            step_generation(&mut tileMap, &mut inTileMapRemove);
         }
-        //
-
         // supprimer le composant pour le délier du pixel supprimé
         for t in inTileMapRemove{
             commands.entity(t).remove::<InTileMap>();
         }
+    }
+}
+
+// une génération de la règle courante (Lenia est calculé à part par run_lenia),
+// sans accès au monde Bevy pour servir aussi à l'enregistrement et au mode sans fenêtre.
+// Hors du plan infini, les cellules sorties du rectangle sont retirées après coup,
+// et pour un tore les cellules près des bords sont d'abord recopiées de l'autre côté
+pub fn step_generation(tileMap: &mut TileMap, inTileMapRemove: &mut Vec<Entity>){
+    let topology = tileMap.topology;
+    if topology == Topology::Plane || tileMap.rule.wolfram.is_some() {
+        step_plane(tileMap, inTileMapRemove);
+        return;
+    }
+    if let Topology::Torus{ .. } = topology {
+        // profondeur de la bande recopiée : voisin le plus éloigné du réseau, multiplié par la portée
+        let depth = tileMap.rule.grid.reach() * tileMap.rule.range.max(1);
+        let tileMap = &mut *tileMap;
+        topology.wake_edges(depth, &mut tileMap.current_state, &mut tileMap.stable_current_state, update_count_limit());
+        topology.add_ghosts(depth, &mut tileMap.current_state);
+    }
+    step_plane(tileMap, inTileMapRemove);
+    topology.clip(&mut tileMap.current_state, inTileMapRemove);
+    topology.clip(&mut tileMap.stable_current_state, inTileMapRemove);
+    topology.move_ants(&mut tileMap.ants);
+}
+
+fn step_plane(tileMap: &mut TileMap, inTileMapRemove: &mut Vec<Entity>){
+    if let Some(wolfram) = tileMap.rule.wolfram {
+        wolfram.step(&mut tileMap.current_state, &mut tileMap.stable_current_state, &mut tileMap.row1D);
+    } else if let Some(turmite) = tileMap.rule.turmite.clone() {
+        turmite.step(&mut tileMap.ants, &mut tileMap.current_state, &mut tileMap.stable_current_state, inTileMapRemove);
+    } else if(tileMap.rule.isLargerThanLife() || tileMap.rule.is3D() || tileMap.rule.table.is_some()){
+        tileMap.current_state = if let Some(table) = &tileMap.rule.table {
+            ruletable::step(table, &tileMap.current_state, &tileMap.stable_current_state, inTileMapRemove)
+        } else if tileMap.rule.is3D() {
+            life3d::step(&tileMap.rule, &tileMap.current_state, &tileMap.stable_current_state, inTileMapRemove)
+        } else {
+            ltl::step(&tileMap.rule, &tileMap.current_state, &tileMap.stable_current_state, inTileMapRemove)
+        };
+        tileMap.stable_current_state.clear();
+    } else {
+        let mut newTileMap: HashMap<uVec3, (Option<Entity>, i32, u8)> = tileMap.current_state.clone();
+        let mut newStableTileMap: HashMap<uVec3, (Option<Entity>, i32, u8)> = tileMap.stable_current_state.clone();
+        //TODO multithread the inside of this loop, or not cuz it might be slower WTF ???
+        for tile in tileMap.current_state.iter(){
+            checkArround(tile.0, &tileMap.rule, &tileMap.current_state, &tileMap.stable_current_state, &mut newTileMap, &mut newStableTileMap, inTileMapRemove);
+        }
         tileMap.current_state = newTileMap;
         tileMap.stable_current_state = newStableTileMap;
     }
 }
 
+// seules les cellules à l'état 1 comptent comme voisines, les cellules mourantes occupent la case sans compter
+pub fn isAlive(pos: &uVec3, tileMap: &HashMap<uVec3, (Option<Entity>, i32, u8)>, stableTileMap: &HashMap<uVec3, (Option<Entity>, i32, u8)>) -> bool{
+    stableTileMap.contains_key(pos) || matches!(tileMap.get(pos), Some((_, _, 1)))
+}
 
+// réveiller les pixels dormants autour de pos
+fn wakeArround(pos: &uVec3, stableTileMap: &HashMap<uVec3, (Option<Entity>, i32, u8)>, newTileMap: &mut HashMap<uVec3, (Option<Entity>, i32, u8)>, newStableTileMap: &mut HashMap<uVec3, (Option<Entity>, i32, u8)>){
+    let limit = unstable_changer_limit();
+    for i in -limit..limit+1{
+        for j in -limit..limit+1{
+            let p = uVec3::new(pos.x + i, pos.y + j, 0);
+            if !(i==0 && j==0) && stableTileMap.contains_key(&p){
+                let (entity, _, state) = stableTileMap.get(&p).unwrap();
+                newStableTileMap.remove(&p);
+                newTileMap.insert(p, (*entity, update_count_limit(), *state));
+            }
+        }
+    }
+}
+
+// bit du voisin (dx, dy) dans le voisinage 3x3 des règles non totalistes, y vers le haut
+// (sans signification sur les réseaux hexagonal et triangulaire, qui sont totalistes)
+pub fn neighbourBit(dx: i32, dy: i32) -> u16{
+    if dx.abs() > 1 || dy.abs() > 1 {
+        return 0;
+    }
+    1 << ((1 - dy) * 3 + dx + 1)
+}
+
+pub fn checkArround(pos: &uVec3, rule: &Rule, tileMap: &HashMap<uVec3, (Option<Entity>, i32, u8)>, stableTileMap: &HashMap<uVec3, (Option<Entity>, i32, u8)>, newTileMap: &mut HashMap<uVec3, (Option<Entity>, i32, u8)>, newStableTileMap: &mut HashMap<uVec3, (Option<Entity>, i32, u8)>, inTileMapRemove: &mut Vec<Entity>){
+    let (ent, updateStep, state) = *tileMap.get(pos).unwrap();
+
+    // cellule mourante : elle avance d'un état sans regarder ses voisins, puis disparait
+    if state > 1 {
+        if state + 1 >= rule.states {
+            if(!ent.is_none()){
+                inTileMapRemove.push(ent.unwrap());
+            }
+            newTileMap.remove(pos);
+        } else {
+            newTileMap.insert(*pos, (ent, update_count_limit(), state + 1));
+        }
+        return;
+    }
 
-pub fn checkArround(pos: &uVec3, tileMap: &HashMap<uVec3, (Option<Entity>, i32)>, stableTileMap: &HashMap<uVec3, (Option<Entity>, i32)>, newTileMap: &mut HashMap<uVec3, (Option<Entity>, i32)>, newStableTileMap: &mut HashMap<uVec3, (Option<Entity>, i32)>, inTileMapRemove: &mut Vec<Entity>){
     let mut count = 0;
+    let mut neighbours: u16 = 0;
     // regarder autour du pixel
-    for i in -1..2{
+    for (i, j) in rule.grid.neighbours(pos){
+        let neighbour = uVec3::new(pos.x + i, pos.y + j, 0);
         //regarder autour des pixels de contours pour donner la vie
-        for j in -1..2{
-            if(!tileMap.contains_key(&uVec3::new(pos.x + i, pos.y + j, 0)) && !stableTileMap.contains_key(&uVec3::new(pos.x + i, pos.y + j, 0))){
-                let mut countArround = 0;
-                let mut tilesAvailable: Vec<uVec3> = Vec::new();
-                for k in -1..2{
-                    for l in -1..2{
-                        if(!(k==0 && l==0) && (tileMap.contains_key(&uVec3::new(pos.x + i + k, pos.y + j + l, 0)) || stableTileMap.contains_key(&uVec3::new(pos.x + i + k, pos.y + j + l, 0)))){
-                            countArround += 1;
-                            tilesAvailable.push(*pos);
-                        }
-                    }
-                }
-                // donner la vie
-                if(countArround == 3){
-                    newTileMap.insert(uVec3::new(pos.x + i, pos.y + j, 0), (None, UPDATE_COUNT_LIMIT));
-                    for p in tilesAvailable{
-                        if stableTileMap.contains_key(&p){
-                            // réveiller les pixels dormants
-                            for k in -UNSTBLE_CHANGER_LIMIT..UNSTBLE_CHANGER_LIMIT+1{
-                                for l in -UNSTBLE_CHANGER_LIMIT..UNSTBLE_CHANGER_LIMIT+1{
-                                    if stableTileMap.contains_key(&uVec3::new(pos.x+k, pos.y+l, pos.z)) || tileMap.contains_key(&uVec3::new(pos.x+k, pos.y+l, pos.z)){
-                                        let (entity, updateCount) = stableTileMap.get(&uVec3::new(pos.x, pos.y, pos.z)).unwrap();
-                                        newStableTileMap.remove(&uVec3::new(p.x + k, p.y + l, 0));
-                                        newTileMap.insert(uVec3::new(p.x + k, p.y + l, 0), (*entity, UPDATE_COUNT_LIMIT));
-                                    }
-                                }
-                            }
-                        }
-                    }
+        if(!tileMap.contains_key(&neighbour) && !stableTileMap.contains_key(&neighbour)){
+            let mut countArround = 0;
+            let mut neighboursArround: u16 = 0;
+            for (k, l) in rule.grid.neighbours(&neighbour){
+                if(isAlive(&uVec3::new(neighbour.x + k, neighbour.y + l, 0), tileMap, stableTileMap)){
+                    countArround += 1;
+                    neighboursArround |= neighbourBit(*k, *l);
                 }
             }
-            if(!(i==0 && j==0) && (tileMap.contains_key(&uVec3::new(pos.x + i, pos.y + j, 0)) || stableTileMap.contains_key(&uVec3::new(pos.x + i, pos.y + j, 0)))){
-                count += 1;
+            // donner la vie
+            if(rule.next_alive(false, countArround, neighboursArround) && !newTileMap.contains_key(&neighbour)){
+                newTileMap.insert(neighbour, (None, update_count_limit(), 1));
+                wakeArround(&neighbour, stableTileMap, newTileMap, newStableTileMap);
             }
+        } else if(isAlive(&neighbour, tileMap, stableTileMap)){
+            count += 1;
+            neighbours |= neighbourBit(*i, *j);
         }
     }
     // tuer ou mettre à jour le compteur de stabilité
-    if(count < 2 || count > 3){
-        let (ent, upd) = tileMap.get(&uVec3::new(pos.x, pos.y, 0)).unwrap();
-        if(!ent.is_none()){
-            inTileMapRemove.push(ent.unwrap());
-        }
-        newTileMap.remove(&uVec3::new(pos.x, pos.y, 0));
-        newStableTileMap.remove(&uVec3::new(pos.x, pos.y, 0));
-        // réveiller les pixels dormants
-        for i in -UNSTBLE_CHANGER_LIMIT..UNSTBLE_CHANGER_LIMIT+1{
-            for j in -UNSTBLE_CHANGER_LIMIT..UNSTBLE_CHANGER_LIMIT+1{
-                if !(i==0 && j==0) && stableTileMap.contains_key(&uVec3::new(pos.x + i, pos.y + j, 0)){
-                    let (entity, updateStep) = stableTileMap.get(&uVec3::new(pos.x + i, pos.y + j, 0)).unwrap();
-                    newStableTileMap.remove(&uVec3::new(pos.x + i, pos.y + j, 0));
-                    newTileMap.insert(uVec3::new(pos.x + i, pos.y + j, 0), (*entity, UPDATE_COUNT_LIMIT));
-                }
+    if(!rule.next_alive(true, count, neighbours)){
+        if rule.states > 2 {
+            newTileMap.insert(*pos, (ent, update_count_limit(), 2));
+        } else {
+            if(!ent.is_none()){
+                inTileMapRemove.push(ent.unwrap());
             }
+            newTileMap.remove(pos);
         }
+        newStableTileMap.remove(pos);
+        wakeArround(pos, stableTileMap, newTileMap, newStableTileMap);
     } else {
-        let (entity, updateStep) = tileMap.get(&uVec3::new(pos.x, pos.y, 0)).unwrap();
-        if(*updateStep <= 1){
+        if(updateStep <= 1){
             // println!("moved one key !!!!");
-            newTileMap.remove(&uVec3::new(pos.x, pos.y, 0));
-            newStableTileMap.insert(uVec3::new(pos.x, pos.y, 0), (*entity, updateStep-1));
+            newTileMap.remove(pos);
+            newStableTileMap.insert(*pos, (ent, updateStep-1, 1));
         } else {
-            newTileMap.insert(uVec3::new(pos.x, pos.y, 0), (*entity, updateStep-1));
+            newTileMap.insert(*pos, (ent, updateStep-1, 1));
         }
     }
 }
@@ -439,11 +1278,12 @@ pub fn checkArround(pos: &uVec3, tileMap: &HashMap<uVec3, (Option<Entity>, i32)>
 
 pub fn place_patterns(
     mut tilemap_query: Query<&mut TileMap>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    controls_query: Query<&Controls>,
     mut tile_query: Query<(Entity, &Tile, &mut Transform), With<InTileMap>>,
     mut commands: Commands,
 ){
-    if(keyboard_input.just_pressed(KeyCode::KeyB)){
+    let controls = controls_query.single();
+    if(controls.just_pressed(Action::Benchmark) && tilemap_query.single().rule.lenia.is_none()){
         let mut tileMap = tilemap_query.single_mut();
 
         let benchmark = [
@@ -484,16 +1324,21 @@ pub fn place_patterns(
                 if(benchmark[i][j] == 1 && !(tileMap.current_state.contains_key(&uVec3::new(i as i32, j as i32, 0)))){
                     let mut entity: Option<Entity> = None;
                     if tileMap.stable_current_state.contains_key(&uVec3::new(i as i32, j as i32, 0)) {
-                        let (pos, (e, update_count)) = tileMap.stable_current_state.get_key_value(&uVec3::new(i as i32, j as i32, 0)).unwrap();
+                        let (pos, (e, update_count, _)) = tileMap.stable_current_state.get_key_value(&uVec3::new(i as i32, j as i32, 0)).unwrap();
                         entity = *e;
                     }
                     tileMap.stable_current_state.remove(&uVec3::new(i as i32, j as i32, 0));
-                    tileMap.current_state.insert(uVec3::new(i as i32, j as i32, 0), (entity, UPDATE_COUNT_LIMIT));
+                    tileMap.current_state.insert(uVec3::new(i as i32, j as i32, 0), (entity, update_count_limit(), 1));
                 }
             }
         }
     }
-    if(keyboard_input.just_pressed(KeyCode::KeyG)){
+    if(controls.just_pressed(Action::Seed) && tilemap_query.single().rule.wolfram.is_some()){
+        // en 1D, G pose la graine classique : une seule cellule vivante, sous la ligne la plus basse
+        let mut tileMap = tilemap_query.single_mut();
+        let row = tileMap.current_state.keys().chain(tileMap.stable_current_state.keys()).map(|p| p.y - 1).min().unwrap_or(0);
+        tileMap.current_state.insert(uVec3::new(0, row, 0), (None, update_count_limit(), 1));
+    } else if(controls.just_pressed(Action::Seed) && tilemap_query.single().rule.lenia.is_none()){
         let mut tileMap = tilemap_query.single_mut();
 
         let glider = [
@@ -507,22 +1352,24 @@ pub fn place_patterns(
                 if(glider[i][j] == 1 && !(tileMap.current_state.contains_key(&uVec3::new(i as i32, j as i32, 0)))){
                     let mut entity: Option<Entity> = None;
                     if tileMap.stable_current_state.contains_key(&uVec3::new(i as i32, j as i32, 0)) {
-                        let (pos, (e, update_count)) = tileMap.stable_current_state.get_key_value(&uVec3::new(i as i32, j as i32, 0)).unwrap();
+                        let (pos, (e, update_count, _)) = tileMap.stable_current_state.get_key_value(&uVec3::new(i as i32, j as i32, 0)).unwrap();
                         entity = *e;
                     }
                     tileMap.stable_current_state.remove(&uVec3::new(i as i32, j as i32, 0));
-                    tileMap.current_state.insert(uVec3::new(i as i32, j as i32, 0), (entity, UPDATE_COUNT_LIMIT));
+                    tileMap.current_state.insert(uVec3::new(i as i32, j as i32, 0), (entity, update_count_limit(), 1));
                 }
             }
         }
     }
-    if(keyboard_input.just_pressed(KeyCode::Backspace)){
+    if(controls.just_pressed(Action::Clear)){
         let mut tileMap = tilemap_query.single_mut();
+        tileMap.macrocell = None;
+        tileMap.row1D = None;
         tileMap.current_state.clear();
         tileMap.stable_current_state.clear();
+        tileMap.ants.clear();
         for (entity, tile, mut transform) in tile_query.iter_mut(){
             commands.entity(entity).remove::<InTileMap>();
-            transform.translation = Vec3::new(transform.translation.x, transform.translation.y, -7.);
         }
     }
 
@@ -531,8 +1378,1047 @@ pub fn place_patterns(
 
 }
 
-pub fn toggle_vsync(input: Res<ButtonInput<KeyCode>>, mut windows: Query<&mut Window>){
-    if input.just_pressed(KeyCode::KeyV){
+// toutes les cellules non mortes, actives ou stables
+pub fn living_cells(tileMap: &TileMap) -> Vec<(uVec3, u8)>{
+    tileMap.current_state.iter().chain(tileMap.stable_current_state.iter()).map(|(pos, (_, _, state))| (*pos, *state)).collect()
+}
+
+// Ctrl+S : enregistrer l'univers dans pattern.rle, Ctrl+O : le recharger (avec sa règle et son réseau)
+pub fn save_load_pattern(
+    mut tilemap_query: Query<&mut TileMap>,
+    controls_query: Query<&Controls>,
+    mut tile_query: Query<(Entity, &Tile, &mut Transform), With<InTileMap>>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    mut commands: Commands,
+){
+    let controls = controls_query.single();
+    let mut tileMap = tilemap_query.single_mut();
+    if controls.just_pressed(Action::SavePattern) {
+        let text = rle::write(&living_cells(&tileMap), &tileMap.rule.to_string());
+        if let Err(e) = std::fs::write(PATTERN_FILE, text) {
+            println!("could not save {}: {}", PATTERN_FILE, e);
+        }
+    }
+    if controls.just_pressed(Action::LoadPattern) {
+        let pattern = match std::fs::read_to_string(PATTERN_FILE).map_err(|e| e.to_string()).and_then(|text| rle::read(&text)) {
+            Ok(pattern) => pattern,
+            Err(e) => {
+                println!("could not load {}: {}", PATTERN_FILE, e);
+                return;
+            }
+        };
+        if let Some(rulestring) = &pattern.rule {
+            match Rule::parse(rulestring) {
+                Ok(rule) => tileMap.rule = rule,
+                Err(e) => println!("{}, keeping {}", e, tileMap.rule.to_string()),
+            }
+        }
+        tileMap.macrocell = None;
+        tileMap.row1D = None;
+        tileMap.current_state.clear();
+        tileMap.stable_current_state.clear();
+        tileMap.ants.clear();
+        for (entity, tile, mut transform) in tile_query.iter_mut(){
+            commands.entity(entity).remove::<InTileMap>();
+        }
+        for (pos, state) in pattern.cells{
+            if state < tileMap.rule.states {
+                tileMap.current_state.insert(pos, (None, update_count_limit(), state));
+            }
+        }
+        let mut window = windows.single_mut();
+        window.title = window_title(&tileMap);
+    }
+}
+
+// Ctrl+M : enregistrer l'univers dans pattern.mc (format macrocell de Golly),
+// Ctrl+Shift+M : charger pattern.mc, seulement autour de la vue actuelle (le motif peut être immense) :
+// recommencer après avoir déplacé la caméra pour charger une autre zone
+pub fn save_load_macrocell(
+    mut tilemap_query: Query<&mut TileMap>,
+    controls_query: Query<&Controls>,
+    mut tile_query: Query<Entity, With<InTileMap>>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Transform, &OrthographicProjection), With<MainCamera>>,
+    mut commands: Commands,
+){
+    let controls = controls_query.single();
+    let save = controls.just_pressed(Action::SaveMacrocell);
+    if !save && !controls.just_pressed(Action::LoadMacrocell) {
+        return;
+    }
+    let mut tileMap = tilemap_query.single_mut();
+    if save {
+        let cells: Vec<(uVec3, u8)> = living_cells(&tileMap).into_iter().filter(|(pos, _)| pos.z == tileMap.layer).collect();
+        // après un chargement partiel, seule la fenêtre chargée est remplacée dans l'univers complet
+        let universe = match &tileMap.macrocell {
+            Some(loaded) => loaded.universe.merge(loaded.min, loaded.max, &cells, &tileMap.rule.to_string(), tileMap.rule.states > 2),
+            None => Macrocell::from_cells(&cells, &tileMap.rule.to_string(), tileMap.rule.states > 2),
+        };
+        let text = universe.write();
+        match std::fs::write(MACROCELL_FILE, text) {
+            Ok(()) => println!("saved {}", MACROCELL_FILE),
+            Err(e) => println!("could not save {}: {}", MACROCELL_FILE, e),
+        }
+        return;
+    }
+
+    let universe = match std::fs::read_to_string(MACROCELL_FILE).map_err(|e| e.to_string()).and_then(|text| Macrocell::read(&text)) {
+        Ok(universe) => universe,
+        Err(e) => {
+            println!("could not load {}: {}", MACROCELL_FILE, e);
+            return;
+        }
+    };
+    // la vue, plus une marge d'une demi-vue de chaque côté
+    let (transform, projection) = camera_query.single();
+    let mut window = window_query.single_mut();
+    let half = Vec2::new(window.width(), window.height()) * projection.scale;
+    let center = transform.translation.truncate();
+    let grid = tileMap.rule.grid;
+    let (a, b) = (grid.from_world(center - half), grid.from_world(center + half));
+    let (min, max) = ((a.x.min(b.x) as i64, a.y.min(b.y) as i64), (a.x.max(b.x) as i64, a.y.max(b.y) as i64));
+    let cells = match universe.cells_in(min, max, MAX_MACROCELL_CELLS) {
+        Ok(cells) => cells,
+        Err(e) => {
+            println!("could not load {}: {}, zoom in", MACROCELL_FILE, e);
+            return;
+        }
+    };
+    if let Some(rulestring) = &universe.rule {
+        match Rule::parse(rulestring) {
+            Ok(rule) => tileMap.rule = rule,
+            Err(e) => println!("{}, keeping {}", e, tileMap.rule.to_string()),
+        }
+    }
+    tileMap.current_state.clear();
+    tileMap.stable_current_state.clear();
+    tileMap.ants.clear();
+    for entity in tile_query.iter_mut(){
+        commands.entity(entity).remove::<InTileMap>();
+    }
+    let population = universe.population();
+    println!("loaded {} of {} cells from {}", cells.len(), population, MACROCELL_FILE);
+    for (pos, state) in cells{
+        if state < tileMap.rule.states {
+            tileMap.current_state.insert(pos, (None, update_count_limit(), state));
+        }
+    }
+    tileMap.row1D = None;
+    tileMap.macrocell = if (tileMap.current_state.len() as u128) < population {
+        Some(Arc::new(macrocell::Loaded{ universe, min, max }))
+    } else {
+        None
+    };
+    window.title = window_title(&tileMap);
+}
+
+// Ctrl+I : ajouter l'image pattern.png au centre de la vue (pixels sombres vivants), Ctrl+Shift+I : inversée ;
+// seuil, échelle et tramage viennent de settings.ron (importThreshold, importScale, importDither)
+pub fn import_image(
+    mut tilemap_query: Query<&mut TileMap>,
+    controls_query: Query<&Controls>,
+    theme_query: Query<&CurrentTheme>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    settings: Res<Settings>,
+){
+    let controls = controls_query.single();
+    let invert = controls.just_pressed(Action::ImportImageInverted);
+    if !invert && !controls.just_pressed(Action::ImportImage) {
+        return;
+    }
+    let mut tileMap = tilemap_query.single_mut();
+    if tileMap.rule.lenia.is_some() {
+        println!("images cannot be imported with Lenia");
+        return;
+    }
+    let bitmap = match import::read_png(IMAGE_FILE) {
+        Ok(bitmap) => bitmap,
+        Err(e) => {
+            println!("could not import {}: {}", IMAGE_FILE, e);
+            return;
+        }
+    };
+    let settings = settings.import_settings(invert);
+    let theme = &theme::THEMES[theme_query.single().index];
+    let center = tileMap.rule.grid.from_world(camera_query.single().translation.truncate());
+    let (width, height) = import::cell_size(&bitmap, &settings);
+    let origin = uVec3::new(center.x - width as i32 / 2, center.y + height as i32 / 2, tileMap.layer);
+    let cells = match import::to_cells(&bitmap, &settings, &export_colors(&tileMap.rule, theme), origin) {
+        Ok(cells) => cells,
+        Err(e) => {
+            println!("could not import {}: {}", IMAGE_FILE, e);
+            return;
+        }
+    };
+    println!("imported {} cells from {}", cells.len(), IMAGE_FILE);
+    for (pos, state) in cells{
+        // les cellules importées ont toutes un état non nul, aucune tuile n'est libérée
+        draw_cell(&mut tileMap, pos, state, &mut Vec::new());
+    }
+}
+
+// couleurs RGBA des états pour les exports, comme à l'écran ; l'état 0 a la couleur du fond
+// pour que l'import d'une image exportée retrouve les mêmes états
+pub fn export_colors(rule: &Rule, theme: &theme::Theme) -> Vec<[u8; 4]>{
+    (0..rule.states).map(|state| if state == 0 { theme.background } else { state_color(state, rule, theme) }.to_srgba().to_u8_array()).collect()
+}
+
+// nom de fichier daté, pour ne pas écraser les exports précédents
+pub fn export_file_name(prefix: &str, extension: &str) -> String{
+    let seconds = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    format!("{}-{}.{}", prefix, seconds, extension)
+}
+
+// Ctrl+E : PNG de toutes les cellules vivantes (1 pixel par cellule),
+// Ctrl+Shift+E : PNG de la vue actuelle, à la résolution de l'écran ;
+// pngCellSize dans settings.ron impose le nombre de pixels par cellule aux deux
+pub fn export_png(
+    controls_query: Query<&Controls>,
+    tilemap_query: Query<&TileMap>,
+    theme_query: Query<&CurrentTheme>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Transform, &OrthographicProjection), With<MainCamera>>,
+    settings: Res<Settings>,
+){
+    let controls = controls_query.single();
+    let shift = controls.just_pressed(Action::ExportView);
+    if !shift && !controls.just_pressed(Action::ExportPng) {
+        return;
+    }
+    let tileMap = tilemap_query.single();
+    let theme = &theme::THEMES[theme_query.single().index];
+    let cells: Vec<(uVec3, u8)> = living_cells(tileMap).into_iter().filter(|(pos, _)| pos.z == tileMap.layer).collect();
+    let grid = tileMap.rule.grid;
+
+    let (min, max, cellSize) = if shift {
+        let (transform, projection) = camera_query.single();
+        let window = window_query.single();
+        let cellSize = if settings.pngCellSize > 0 { settings.pngCellSize } else { (1. / projection.scale).round().max(1.) as u32 };
+        let half = Vec2::new(window.width(), window.height()) * projection.scale * 0.5;
+        let center = transform.translation.truncate();
+        (center - half + Vec2::splat(0.5), center + half - Vec2::splat(0.5), cellSize)
+    } else {
+        match export::bounding_box(&cells, grid) {
+            Some((min, max)) => (min, max, settings.pngCellSize.max(1)),
+            None => {
+                println!("nothing to export");
+                return;
+            }
+        }
+    };
+    let path = export_file_name("export", "png");
+    let result = export::rasterize(&cells, grid, min, max, cellSize, &export_colors(&tileMap.rule, theme), theme.background.to_srgba().to_u8_array())
+        .and_then(|raster| export::write_png(&path, &raster));
+    match result {
+        Ok(()) => println!("saved {}", path),
+        Err(e) => println!("could not export {}: {}", path, e),
+    }
+}
+
+pub fn setup_recorder(
+    mut commands: Commands,
+){
+    commands.spawn(Recorder{
+        region: None,
+        dragStart: None,
+        job: None,
+    });
+}
+
+// Alt + glisser (clic gauche) : zone enregistrée par Ctrl+G, Alt + clic droit : revenir à tout le motif
+pub fn select_record_region(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut recorder_query: Query<&mut Recorder>,
+    mut gizmos: Gizmos,
+){
+    let mut recorder = recorder_query.single_mut();
+    let (camera, global_transform) = camera_query.single();
+    let cursor = window_query.single().cursor_position()
+        .and_then(|cursor| camera.viewport_to_world(global_transform, cursor))
+        .map(|ray| ray.origin.truncate().round());
+    let alt = keyboard_input.pressed(KeyCode::AltLeft) || keyboard_input.pressed(KeyCode::AltRight);
+
+    if alt && mouse_input.just_pressed(MouseButton::Left) {
+        recorder.dragStart = cursor;
+    }
+    if alt && mouse_input.just_pressed(MouseButton::Right) {
+        recorder.region = None;
+        println!("recording everything");
+    }
+    if let (Some(start), Some(end)) = (recorder.dragStart, cursor) {
+        if mouse_input.pressed(MouseButton::Left) {
+            gizmos.rect_2d((start + end) * 0.5, 0., (end - start).abs() + Vec2::ONE, Color::srgb(1., 0.8, 0.2));
+        } else {
+            recorder.region = Some((start.min(end), start.max(end)));
+            recorder.dragStart = None;
+        }
+    }
+    if let (Some((min, max)), None) = (recorder.region, recorder.dragStart) {
+        gizmos.rect_2d((min + max) * 0.5, 0., max - min + Vec2::ONE, Color::srgba(1., 0.8, 0.2, 0.6));
+    }
+}
+
+// Ctrl+G : GIF des prochaines générations, Ctrl+Shift+G : suite de PNG. Générations, pas et délai
+// viennent de settings.ron (recordFrom, recordTo, recordStep, recordDelay), comme les options de "record".
+// Le calcul se fait sur une copie de la TileMap dans un thread, au zoom actuel
+pub fn record_animation(
+    controls_query: Query<&Controls>,
+    tilemap_query: Query<&TileMap>,
+    theme_query: Query<&CurrentTheme>,
+    camera_query: Query<&OrthographicProjection, With<MainCamera>>,
+    mut recorder_query: Query<&mut Recorder>,
+    settings: Res<Settings>,
+){
+    let mut recorder = recorder_query.single_mut();
+    if recorder.job.as_ref().map_or(false, |job| job.is_finished()) {
+        match recorder.job.take().unwrap().join() {
+            Ok(Ok(message)) => println!("{}", message),
+            Ok(Err(e)) => println!("could not record: {}", e),
+            Err(_) => println!("could not record: the recording thread panicked"),
+        }
+    }
+    let controls = controls_query.single();
+    let pngs = controls.just_pressed(Action::RecordPngs);
+    if !pngs && !controls.just_pressed(Action::RecordGif) {
+        return;
+    }
+    if recorder.job.is_some() {
+        println!("a recording is already running");
+        return;
+    }
+    let tileMap = tilemap_query.single();
+    let theme = &theme::THEMES[theme_query.single().index];
+    let settings = record::RecordSettings{
+        from: settings.recordFrom,
+        to: settings.recordTo,
+        step: settings.recordStep,
+        delay: settings.recordDelay,
+        cellSize: (1. / camera_query.single().scale).round().max(1.) as u32,
+        region: recorder.region,
+        output: export_file_name("record", if pngs { "png" } else { "gif" }),
+    };
+    let copy = tileMap.clone();
+    let colors = export_colors(&tileMap.rule, theme);
+    let background = theme.background.to_srgba().to_u8_array();
+    println!("recording {} frames to {}", settings.frameCount(), settings.output);
+    recorder.job = Some(std::thread::spawn(move || record::record(copy, &settings, &colors, background)));
+}
+
+// Ctrl+K : apgcode (nom canonique de Catagolue) de l'objet dans la zone choisie avec Alt + glisser,
+// ou de tout l'univers si aucune zone n'est choisie
+pub fn identify_object(
+    controls_query: Query<&Controls>,
+    tilemap_query: Query<&TileMap>,
+    recorder_query: Query<&Recorder>,
+){
+    if !controls_query.single().just_pressed(Action::Identify) {
+        return;
+    }
+    let tileMap = tilemap_query.single();
+    let region = recorder_query.single().region;
+    let cells: Vec<uVec3> = living_cells(tileMap).into_iter()
+        .map(|(pos, _)| pos)
+        .filter(|pos| pos.z == tileMap.layer)
+        .filter(|pos| region.map_or(true, |(min, max)| {
+            let p = tileMap.rule.grid.to_world(pos).truncate();
+            p.x >= min.x && p.x <= max.x && p.y >= min.y && p.y <= max.y
+        }))
+        .collect();
+    match apgcode::identify(&cells, &tileMap.rule) {
+        Ok(code) => println!("{}", code),
+        Err(e) => println!("could not identify the object: {}", e),
+    }
+}
+
+pub fn setup_object_labels(
+    mut commands: Commands,
+    camera_query: Query<Entity, With<MainCamera>>,
+){
+    commands.spawn((
+        TextBundle::from_section("", TextStyle{ font_size: 16., ..default() })
+            .with_style(Style{
+                position_type: PositionType::Absolute,
+                display: Display::None,
+                ..default()
+            })
+            .with_background_color(Color::srgba(0., 0., 0., 0.75)),
+        TargetCamera(camera_query.single()),
+        ObjectLabels{
+            enabled: false,
+            distance: 1,
+            objects: Vec::new(),
+            lastUpdate: -OBJECTS_REFRESH,
+            classifications: HashMap::new(),
+            rule: String::new(),
+            job: None,
+        },
+    ));
+}
+
+// O : séparer les cellules vivantes en objets, Shift+O : changer la distance de connexion (1 à 4).
+// Chaque objet est entouré d'une couleur, l'info-bulle donne la taille, la boîte et la classification
+pub fn label_objects(
+    controls_query: Query<&Controls>,
+    tilemap_query: Query<&TileMap>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut labels_query: Query<(&mut ObjectLabels, &mut Text, &mut Style)>,
+    mut gizmos: Gizmos,
+    time: Res<Time>,
+){
+    let (mut labels, mut text, mut style) = labels_query.single_mut();
+    let controls = controls_query.single();
+    if controls.just_pressed(Action::ObjectDistance) {
+        labels.distance = labels.distance % 4 + 1;
+        labels.enabled = true;
+        labels.lastUpdate = -OBJECTS_REFRESH;
+        println!("object connectivity distance: {}", labels.distance);
+    } else if controls.just_pressed(Action::ToggleObjects) {
+        labels.enabled = !labels.enabled;
+        labels.lastUpdate = -OBJECTS_REFRESH;
+    }
+    let tileMap = tilemap_query.single();
+    if !labels.enabled || tileMap.rule.lenia.is_some() {
+        labels.objects.clear();
+        style.display = Display::None;
+        return;
+    }
+
+    // les classifications sont calculées dans un thread (apgcode::identify simule jusqu'à MAX_PERIOD générations)
+    // et gardées par forme tant que la règle ne change pas
+    let rule = tileMap.rule.to_string();
+    if labels.rule != rule || labels.classifications.len() >= objects::MAX_CACHED_CLASSIFICATIONS {
+        labels.classifications.clear();
+        labels.rule = rule;
+        labels.job = None;
+    }
+    if labels.job.as_ref().map_or(false, |(_, job)| job.is_finished()) {
+        let (shape, job) = labels.job.take().unwrap();
+        let classification = job.join().unwrap_or(String::from("unknown (the classification thread panicked)"));
+        labels.classifications.insert(shape, classification);
+    }
+
+    let now = time.elapsed_seconds();
+    if now - labels.lastUpdate >= OBJECTS_REFRESH {
+        labels.lastUpdate = now;
+        let cells: Vec<uVec3> = living_cells(tileMap).into_iter().map(|(pos, _)| pos).filter(|pos| pos.z == tileMap.layer).collect();
+        labels.objects = objects::label(&cells, labels.distance, tileMap.rule.grid);
+    }
+    for (i, object) in labels.objects.iter().enumerate(){
+        // angle d'or : des teintes voisines pour des objets voisins restent distinctes
+        let color = Color::hsl((i as f32 * 137.5) % 360., 0.8, 0.6);
+        gizmos.rect_2d((object.worldMin + object.worldMax) * 0.5, 0., object.worldMax - object.worldMin, color);
+    }
+
+    let (camera, global_transform) = camera_query.single();
+    let window = window_query.single();
+    let Some((cursor, world)) = window.cursor_position()
+        .and_then(|cursor| camera.viewport_to_world(global_transform, cursor).map(|ray| (cursor, ray.origin.truncate()))) else {
+        style.display = Display::None;
+        return;
+    };
+    // le plus petit objet sous le curseur
+    let hovered = labels.objects.iter()
+        .filter(|o| world.x >= o.worldMin.x && world.x <= o.worldMax.x && world.y >= o.worldMin.y && world.y <= o.worldMax.y)
+        .min_by_key(|o| o.cells.len());
+    let Some(object) = hovered else {
+        style.display = Display::None;
+        return;
+    };
+    let shape = objects::shape(object);
+    let classification = match labels.classifications.get(&shape) {
+        Some(classification) => classification.clone(),
+        None => String::from("classifying..."),
+    };
+    text.sections[0].value = format!("{} cells\n({}, {}) to ({}, {}), {} x {}\n{}",
+        object.cells.len(), object.min.0, object.min.1, object.max.0, object.max.1,
+        object.max.0 - object.min.0 + 1, object.max.1 - object.min.1 + 1, classification);
+    if labels.job.is_none() && !labels.classifications.contains_key(&shape) {
+        let (cells, rule) = (object.cells.clone(), tileMap.rule.clone());
+        labels.job = Some((shape, std::thread::spawn(move || objects::classify(&cells, &rule))));
+    }
+    style.display = Display::Flex;
+    style.left = Val::Px(cursor.x + 16.);
+    style.top = Val::Px(cursor.y + 16.);
+}
+
+pub fn setup_stamp(
+    mut commands: Commands,
+){
+    commands.spawn(Stamp{
+        pending: None,
+        consumed: false,
+        clipboard: None,
+    });
+}
+
+// motif à poser au clic (collé ou choisi dans la palette), ancré par son coin haut gauche
+pub fn start_stamp(pattern: rle::Pattern, tileMap: &mut TileMap, stamp: &mut Stamp, window: &mut Window){
+    if pattern.cells.is_empty() {
+        println!("empty pattern");
+        return;
+    }
+    // la règle du motif n'est reprise que dans un univers vide, comme un chargement
+    if let Some(rulestring) = &pattern.rule {
+        let empty = tileMap.current_state.is_empty() && tileMap.stable_current_state.is_empty();
+        match Rule::parse(rulestring) {
+            Ok(rule) if empty && rule != tileMap.rule => {
+                tileMap.rule = rule;
+                window.title = window_title(tileMap);
+            }
+            Ok(rule) if rule != tileMap.rule => println!("pasting a {} pattern into {}", rulestring, tileMap.rule.to_string()),
+            Ok(_) => {}
+            Err(e) => println!("{}, keeping {}", e, tileMap.rule.to_string()),
+        }
+    }
+    let minX = pattern.cells.iter().map(|(p, _)| p.x).min().unwrap();
+    let maxY = pattern.cells.iter().map(|(p, _)| p.y).max().unwrap();
+    stamp.pending = Some(pattern.cells.iter().map(|(p, s)| (uVec3::new(p.x - minX, p.y - maxY, 0), *s)).collect());
+}
+
+// Ctrl+C : copier en RLE la zone choisie avec Alt + glisser (ou tout l'univers), Ctrl+X : couper,
+// Ctrl+V : coller du RLE ou du .cells, le motif suit le curseur jusqu'au clic gauche (clic droit ou Échap pour annuler)
+pub fn clipboard_stamp(
+    controls_query: Query<&Controls>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    mut tilemap_query: Query<&mut TileMap>,
+    recorder_query: Query<&Recorder>,
+    mut stamp_query: Query<&mut Stamp>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    minimap_query: Query<&RelativeCursorPosition, With<Minimap>>,
+    mut commands: Commands,
+    mut gizmos: Gizmos,
+){
+    let mut tileMap = tilemap_query.single_mut();
+    let mut stamp = stamp_query.single_mut();
+    let controls = controls_query.single();
+    if stamp.consumed && !mouse_input.pressed(MouseButton::Left) && !mouse_input.pressed(MouseButton::Right) {
+        stamp.consumed = false;
+    }
+    if tileMap.rule.lenia.is_some() {
+        stamp.pending = None;
+        return;
+    }
+
+    let cut = controls.just_pressed(Action::Cut);
+    let paste = controls.just_pressed(Action::Paste);
+    if (cut || paste || controls.just_pressed(Action::Copy)) && stamp.clipboard.is_some() {
+        println!("the clipboard is busy");
+    } else if cut || controls.just_pressed(Action::Copy) {
+        let region = recorder_query.single().region;
+        let grid = tileMap.rule.grid;
+        let layer = tileMap.layer;
+        let inRegion = |pos: &uVec3| pos.z == layer && region.map_or(true, |(min, max)| {
+            let p = grid.to_world(pos).truncate();
+            p.x >= min.x && p.x <= max.x && p.y >= min.y && p.y <= max.y
+        });
+        let copied: Vec<(uVec3, u8)> = living_cells(&tileMap).into_iter().filter(|(pos, _)| inRegion(pos)).collect();
+        let cells: Vec<(uVec3, u8)> = copied.iter().map(|(pos, state)| (uVec3::new(pos.x, pos.y, 0), *state)).collect();
+        stamp.clipboard = Some(ClipboardJob::Copy{
+            reply: clipboard::copy(rle::write(&cells, &tileMap.rule.to_string())),
+            cells: cells.len(),
+            cut: if cut { copied.into_iter().map(|(pos, _)| pos).collect() } else { Vec::new() },
+        });
+    } else if paste {
+        stamp.clipboard = Some(ClipboardJob::Paste(clipboard::paste()));
+    }
+
+    match stamp.clipboard.take() {
+        Some(ClipboardJob::Copy{ reply, cells, cut }) => match reply.try_recv() {
+            Err(TryRecvError::Empty) => stamp.clipboard = Some(ClipboardJob::Copy{ reply, cells, cut }),
+            Ok(Ok(())) => {
+                println!("copied {} cells", cells);
+                let mut inTileMapRemove: Vec<Entity> = Vec::new();
+                for pos in cut{
+                    draw_cell(&mut tileMap, pos, 0, &mut inTileMapRemove);
+                }
+                for entity in inTileMapRemove{
+                    commands.entity(entity).remove::<InTileMap>();
+                }
+            }
+            Ok(Err(e)) => println!("could not copy: {}", e),
+            Err(TryRecvError::Disconnected) => println!("could not copy: the clipboard thread stopped"),
+        },
+        Some(ClipboardJob::Paste(reply)) => match reply.try_recv() {
+            Err(TryRecvError::Empty) => stamp.clipboard = Some(ClipboardJob::Paste(reply)),
+            result => match result.unwrap_or(Err(String::from("the clipboard thread stopped"))).and_then(|text| rle::read_any(&text)) {
+                Ok(pattern) if pattern.cells.is_empty() => println!("nothing to paste"),
+                Ok(pattern) => start_stamp(pattern, &mut tileMap, &mut stamp, &mut window_query.single_mut()),
+                Err(e) => println!("could not paste: {}", e),
+            },
+        },
+        None => {}
+    }
+
+    let Some(cells) = &stamp.pending else {
+        return;
+    };
+    if controls.just_pressed(Action::Cancel) || mouse_input.just_pressed(MouseButton::Right) {
+        stamp.consumed = mouse_input.pressed(MouseButton::Right);
+        stamp.pending = None;
+        return;
+    }
+    let (camera, global_transform) = camera_query.single();
+    let Some(world) = window_query.single().cursor_position()
+        .and_then(|cursor| camera.viewport_to_world(global_transform, cursor))
+        .map(|ray| ray.origin.truncate()) else {
+        return;
+    };
+    let grid = tileMap.rule.grid;
+    let anchor = grid.from_world(world);
+    let at = |p: &uVec3| uVec3::new(anchor.x + p.x, anchor.y + p.y, tileMap.layer);
+    // aperçu : une case par cellule, ou seulement le contour pour les gros motifs
+    let color = Color::srgba(0.3, 0.8, 1., 0.8);
+    if cells.len() <= 5000 {
+        for (p, _) in cells.iter(){
+            gizmos.rect_2d(grid.to_world(&at(p)).truncate(), 0., Vec2::splat(0.8), color);
+        }
+    } else {
+        let points: Vec<Vec2> = cells.iter().map(|(p, _)| grid.to_world(&at(p)).truncate()).collect();
+        let (min, max) = points.iter().fold((points[0], points[0]), |(min, max), p| (min.min(*p), max.max(*p)));
+        gizmos.rect_2d((min + max) * 0.5, 0., max - min + Vec2::ONE, color);
+    }
+    if mouse_input.just_pressed(MouseButton::Left) && !cursor_on_minimap(&minimap_query) {
+        let placed: Vec<(uVec3, u8)> = cells.iter().map(|(p, s)| (at(p), *s)).filter(|(_, s)| *s < tileMap.rule.states).collect();
+        let mut inTileMapRemove: Vec<Entity> = Vec::new();
+        for (pos, state) in placed{
+            draw_cell(&mut tileMap, pos, state, &mut inTileMapRemove);
+        }
+        for entity in inTileMapRemove{
+            commands.entity(entity).remove::<InTileMap>();
+        }
+        stamp.pending = None;
+        stamp.consumed = true;
+    }
+}
+
+// Life 3D : Tab bascule entre la vue 3D et l'édition par tranche, PageUp / PageDown changent de couche,
+// les flèches font tourner la caméra 3D et la molette l'éloigne
+pub fn control_view3d(
+    controls_query: Query<&Controls>,
+    mut mouse_wheel_input: EventReader<MouseWheel>,
+    mut tilemap_query: Query<&mut TileMap>,
+    mut view_query: Query<(&mut View3D, &mut Camera, &mut Transform), Without<MainCamera>>,
+    mut camera2d_query: Query<&mut Camera, (With<MainCamera>, Without<View3D>)>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    settings: Res<Settings>,
+    time: Res<Time>
+){
+    let mut tileMap = tilemap_query.single_mut();
+    let (mut view, mut camera3d, mut transform) = view_query.single_mut();
+    let mut camera2d = camera2d_query.single_mut();
+    let controls = controls_query.single();
+    let wheel: f32 = mouse_wheel_input.read().map(|i| i.y).sum();
+
+    if !tileMap.rule.is3D() {
+        view.enabled = false;
+    } else {
+        if controls.just_pressed(Action::Toggle3D) {
+            view.enabled = !view.enabled;
+        }
+        let mut layer = tileMap.layer;
+        if controls.just_pressed(Action::LayerUp) {
+            layer += 1;
+        }
+        if controls.just_pressed(Action::LayerDown) {
+            layer -= 1;
+        }
+        if layer != tileMap.layer {
+            tileMap.layer = layer;
+            windows.single_mut().title = window_title(&tileMap);
+        }
+    }
+
+    if camera3d.is_active != view.enabled {
+        camera3d.is_active = view.enabled;
+        camera2d.is_active = !view.enabled;
+    }
+    if !view.enabled {
+        return;
+    }
+
+    let speed = 1.5 * time.delta_seconds();
+    if controls.pressed(Action::OrbitLeft) {
+        view.yaw -= speed;
+    }
+    if controls.pressed(Action::OrbitRight) {
+        view.yaw += speed;
+    }
+    if controls.pressed(Action::OrbitUp) {
+        view.pitch = (view.pitch + speed).min(1.5);
+    }
+    if controls.pressed(Action::OrbitDown) {
+        view.pitch = (view.pitch - speed).max(-1.5);
+    }
+    view.distance = (view.distance - wheel * 0.01 * view.distance * settings.zoomMultiplier).max(2.);
+
+    // la caméra vise le centre des cellules
+    let cells = tileMap.current_state.len().max(1) as f32;
+    view.target = tileMap.current_state.keys().fold(Vec3::ZERO, |acc, pos| acc + pos.toVec3()) / cells;
+    let offset = Vec3::new(view.pitch.cos() * view.yaw.sin(), view.pitch.sin(), view.pitch.cos() * view.yaw.cos()) * view.distance;
+    *transform = Transform::from_translation(view.target + offset).looking_at(view.target, Vec3::Y);
+}
+
+// un cube par cellule vivante en vue 3D, les cubes ont tous le même mesh et le même material pour être instanciés ensemble
+pub fn display_cubes(
+    tilemap_query: Query<&TileMap>,
+    view_query: Query<&View3D>,
+    cube_assets_query: Query<&CubeAssets>,
+    mut cube_query: Query<(&mut Transform, &mut Visibility), With<Cube>>,
+    mut commands: Commands,
+){
+    let tileMap = tilemap_query.single();
+    let view = view_query.single();
+    let mut cubes = cube_query.iter_mut();
+
+    if view.enabled {
+        let assets = cube_assets_query.single();
+        for pos in tileMap.current_state.keys(){
+            if let Some((mut transform, mut visibility)) = cubes.next() {
+                transform.translation = pos.toVec3();
+                if *visibility != Visibility::Inherited {
+                    *visibility = Visibility::Inherited;
+                }
+            } else {
+                commands.spawn((
+                    PbrBundle{
+                        mesh: assets.mesh.clone(),
+                        material: assets.material.clone(),
+                        transform: Transform::from_translation(pos.toVec3()),
+                        ..default()
+                    },
+                    Cube,
+                ));
+            }
+        }
+    }
+    for (_, mut visibility) in cubes{
+        if *visibility != Visibility::Hidden {
+            *visibility = Visibility::Hidden;
+        }
+    }
+}
+
+// image de l'automate continu, une cellule par unité du monde, cachée hors des règles Lenia
+pub fn setup_lenia(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+){
+    let size = lenia::LENIA_SIZE as u32;
+    let mut image = Image::new_fill(
+        Extent3d{ width: size, height: size, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        &[0, 0, 0, 255],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.sampler = ImageSampler::nearest();
+    let handle = images.add(image);
+    // la colonne 0 est en x = -size / 2, la ligne 0 (en haut) en y = size / 2 - 1
+    commands.spawn((
+        SpriteBundle{
+            texture: handle.clone(),
+            sprite: Sprite{
+                custom_size: Some(Vec2::splat(size as f32)),
+                ..default()
+            },
+            transform: Transform::from_xyz(-0.5, -0.5, 0.),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        Lenia{
+            world: lenia::LeniaWorld::new(lenia::LeniaParams::parse("Lenia").unwrap()),
+            image: handle,
+            seed: 0x9E37_79B9,
+            dirty: true,
+        },
+    ));
+}
+
+// un pas de l'automate continu par frame quand la simulation tourne, puis recopie dans l'image
+pub fn run_lenia(
+    tilemap_query: Query<(&TileMap, &SimulationSpeed)>,
+    mut lenia_query: Query<(&mut Lenia, &mut Visibility)>,
+    mut images: ResMut<Assets<Image>>,
+){
+    let (tileMap, speed) = tilemap_query.single();
+    let (mut lenia, mut visibility) = lenia_query.single_mut();
+    let Some(params) = tileMap.rule.lenia else {
+        if *visibility != Visibility::Hidden {
+            *visibility = Visibility::Hidden;
+        }
+        return;
+    };
+    if *visibility != Visibility::Inherited {
+        *visibility = Visibility::Inherited;
+    }
+    lenia.world.set_params(params);
+    // même cadence que les autres règles : pause, N et générations par seconde (run_simulation)
+    for _ in 0..speed.generations {
+        lenia.world.step();
+        lenia.dirty = true;
+    }
+    if lenia.dirty {
+        if let Some(image) = images.get_mut(&lenia.image) {
+            lenia.world.write_pixels(&mut image.data);
+        }
+        lenia.dirty = false;
+    }
+}
+
+// clic gauche : semer une tache aléatoire de rayon R, clic droit : effacer, G : tache au centre, Backspace : tout effacer
+pub fn edit_lenia(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    tilemap_query: Query<&TileMap>,
+    mut lenia_query: Query<&mut Lenia>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    controls_query: Query<&Controls>,
+    minimap_query: Query<&RelativeCursorPosition, With<Minimap>>,
+){
+    let tileMap = tilemap_query.single();
+    let Some(params) = tileMap.rule.lenia else {
+        return;
+    };
+    let mut lenia = lenia_query.single_mut();
+    let lenia = &mut *lenia;
+    let half = (lenia::LENIA_SIZE / 2) as i32;
+    let radius = params.radius as i32;
+    let controls = controls_query.single();
+    if controls.just_pressed(Action::Clear) {
+        lenia.world.clear();
+        lenia.dirty = true;
+    }
+    if controls.just_pressed(Action::Seed) {
+        lenia.world.paint(half, half, radius, Some(1.), &mut lenia.seed);
+        lenia.dirty = true;
+    }
+    if cursor_on_minimap(&minimap_query) {
+        return;
+    }
+    let value = if mouse_input.pressed(MouseButton::Left) {
+        Some(1.)
+    } else if mouse_input.pressed(MouseButton::Right) {
+        None
+    } else {
+        return;
+    };
+    let (camera, global_transform) = camera_query.single();
+    if let Some(world_position) = window_query.single().cursor_position()
+        .and_then(|cursor| camera.viewport_to_world(global_transform, cursor))
+        .map(|ray| ray.origin.truncate()) {
+        let cell = tileMap.rule.grid.from_world(world_position);
+        let (col, row) = (cell.x + half, half - 1 - cell.y);
+        if col >= 0 && row >= 0 && col < 2 * half && row < 2 * half {
+            lenia.world.paint(col, row, if value.is_some() { radius } else { radius / 2 }, value, &mut lenia.seed);
+            lenia.dirty = true;
+        }
+    }
+}
+
+// minimap dans le coin haut droit, rattachée à la caméra 2D
+pub fn setup_minimap(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    camera_query: Query<Entity, With<MainCamera>>,
+){
+    let mut image = Image::new_fill(
+        Extent3d{ width: MINIMAP_RESOLUTION, height: MINIMAP_RESOLUTION, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        &[0, 0, 0, 160],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.sampler = ImageSampler::nearest();
+    let handle = images.add(image);
+    commands.spawn((
+        ImageBundle{
+            style: Style{
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.),
+                right: Val::Px(10.),
+                width: Val::Px(MINIMAP_SIZE),
+                height: Val::Px(MINIMAP_SIZE),
+                ..default()
+            },
+            image: UiImage::new(handle.clone()),
+            ..default()
+        },
+        RelativeCursorPosition::default(),
+        TargetCamera(camera_query.single()),
+        Minimap{
+            image: handle,
+            min: Vec2::ZERO,
+            span: 1.,
+            lastUpdate: -MINIMAP_REFRESH,
+        },
+    ));
+}
+
+// vrai si le curseur est sur la minimap, les clics ne doivent alors pas toucher la grille
+pub fn cursor_on_minimap(minimap_query: &Query<&RelativeCursorPosition, With<Minimap>>) -> bool{
+    minimap_query.get_single().map_or(false, |r| r.mouse_over())
+}
+
+// redessine la minimap quelques fois par seconde : cellules vivantes et rectangle de la vue
+pub fn update_minimap(
+    tilemap_query: Query<&TileMap>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Transform, &OrthographicProjection), With<MainCamera>>,
+    mut minimap_query: Query<&mut Minimap>,
+    mut images: ResMut<Assets<Image>>,
+    time: Res<Time>,
+){
+    let mut minimap = minimap_query.single_mut();
+    if time.elapsed_seconds() - minimap.lastUpdate < MINIMAP_REFRESH {
+        return;
+    }
+    minimap.lastUpdate = time.elapsed_seconds();
+    let tileMap = tilemap_query.single();
+    let (transform, projection) = camera_query.single();
+    let window = window_query.single();
+
+    // zone affichée : les cellules et la vue, dans un carré avec une marge
+    let half = Vec2::new(window.width(), window.height()) * projection.scale * 0.5;
+    let center = transform.translation.truncate();
+    let (mut min, mut max) = (center - half, center + half);
+    for pos in tileMap.current_state.keys().chain(tileMap.stable_current_state.keys()){
+        let p = tileMap.rule.grid.to_world(pos).truncate();
+        min = min.min(p);
+        max = max.max(p);
+    }
+    let span = (max - min).max_element() * 1.1 + 1.;
+    minimap.min = (min + max) * 0.5 - Vec2::splat(span * 0.5);
+    minimap.span = span;
+
+    let Some(image) = images.get_mut(&minimap.image) else {
+        return;
+    };
+    let resolution = MINIMAP_RESOLUTION as usize;
+    let toPixel = |p: Vec2| -> (i32, i32) {
+        let u = (p - minimap.min) / span * resolution as f32;
+        (u.x as i32, resolution as i32 - 1 - u.y as i32)
+    };
+    for pixel in image.data.chunks_mut(4){
+        pixel.copy_from_slice(&[0, 0, 0, 160]);
+    }
+    let mut set = |(x, y): (i32, i32), color: [u8; 4]| {
+        if x >= 0 && y >= 0 && (x as usize) < resolution && (y as usize) < resolution {
+            let i = (y as usize * resolution + x as usize) * 4;
+            image.data[i..i + 4].copy_from_slice(&color);
+        }
+    };
+    for pos in tileMap.current_state.keys().chain(tileMap.stable_current_state.keys()){
+        set(toPixel(tileMap.rule.grid.to_world(pos).truncate()), [255, 255, 255, 255]);
+    }
+    let (x0, y1) = toPixel(center - half);
+    let (x1, y0) = toPixel(center + half);
+    for x in x0..x1 + 1 {
+        set((x, y0), [255, 200, 0, 255]);
+        set((x, y1), [255, 200, 0, 255]);
+    }
+    for y in y0..y1 + 1 {
+        set((x0, y), [255, 200, 0, 255]);
+        set((x1, y), [255, 200, 0, 255]);
+    }
+}
+
+// clic sur la minimap : centrer la caméra sur le point cliqué
+pub fn minimap_navigation(
+    minimap_query: Query<(&Minimap, &RelativeCursorPosition)>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    mut camera_query: Query<&mut Transform, With<MainCamera>>,
+){
+    if !mouse_input.pressed(MouseButton::Left) {
+        return;
+    }
+    let (minimap, cursor) = minimap_query.single();
+    if !cursor.mouse_over() {
+        return;
+    }
+    if let Some(position) = cursor.normalized {
+        let target = minimap.min + Vec2::new(position.x, 1. - position.y) * minimap.span;
+        let mut transform = camera_query.single_mut();
+        transform.translation.x = target.x;
+        transform.translation.y = target.y;
+    }
+}
+
+// Shift + clic gauche : poser une turmite (un nouveau clic sur elle la fait tourner), Shift + clic droit : l'enlever
+pub fn place_ants(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut tilemap_query: Query<&mut TileMap>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    minimap_query: Query<&RelativeCursorPosition, With<Minimap>>,
+){
+    let mut tileMap = tilemap_query.single_mut();
+    if cursor_on_minimap(&minimap_query) || tileMap.rule.turmite.is_none() || !(keyboard_input.pressed(KeyCode::ShiftLeft) || keyboard_input.pressed(KeyCode::ShiftRight)) {
+        return;
+    }
+    let (camera, global_transform) = camera_query.single();
+    let Some(world_position) = window_query.single().cursor_position()
+        .and_then(|cursor| camera.viewport_to_world(global_transform, cursor))
+        .map(|ray| ray.origin.truncate()) else {
+        return;
+    };
+    let pos = tileMap.rule.grid.from_world(world_position);
+    if mouse_input.just_pressed(MouseButton::Left) {
+        if let Some(ant) = tileMap.ants.iter_mut().find(|a| a.pos == pos) {
+            ant.direction = (ant.direction + 1) % 4;
+        } else {
+            tileMap.ants.push(Ant{ pos, direction: 0, state: 0 });
+        }
+    } else if mouse_input.just_pressed(MouseButton::Right) {
+        tileMap.ants.retain(|a| a.pos != pos);
+    }
+}
+
+// une flèche par turmite, les flèches en trop sont cachées
+pub fn display_ants(
+    tilemap_query: Query<&TileMap>,
+    ant_assets_query: Query<&AntAssets>,
+    mut marker_query: Query<(&mut Transform, &mut Visibility), With<AntMarker>>,
+    mut commands: Commands,
+){
+    let tileMap = tilemap_query.single();
+    let mut markers = marker_query.iter_mut();
+    for ant in tileMap.ants.iter(){
+        let transform = Transform::from_translation(tileMap.rule.grid.to_world(&ant.pos) + Vec3::new(0., 0., 0.5))
+            .with_rotation(Quat::from_rotation_z(-(ant.direction as f32) * std::f32::consts::FRAC_PI_2));
+        if let Some((mut markerTransform, mut visibility)) = markers.next() {
+            *markerTransform = transform;
+            if *visibility != Visibility::Inherited {
+                *visibility = Visibility::Inherited;
+            }
+        } else {
+            let assets = ant_assets_query.single();
+            commands.spawn((
+                MaterialMesh2dBundle{
+                    mesh: assets.mesh.clone(),
+                    material: assets.material.clone(),
+                    transform,
+                    ..default()
+                },
+                AntMarker,
+            ));
+        }
+    }
+    for (_, mut visibility) in markers{
+        if *visibility != Visibility::Hidden {
+            *visibility = Visibility::Hidden;
+        }
+    }
+}
+
+pub fn toggle_vsync(controls_query: Query<&Controls>, mut windows: Query<&mut Window>){
+    if controls_query.single().just_pressed(Action::ToggleVsync){
         let mut window = windows.single_mut();
 
         window.present_mode = if matches!(window.present_mode, PresentMode::AutoVsync) {
@@ -542,4 +2428,490 @@ pub fn toggle_vsync(input: Res<ButtonInput<KeyCode>>, mut windows: Query<&mut Wi
         };
         // println!("PRESENT MODE : {:?}", window.present_mode)
     }
-}
\ No newline at end of file
+}
+pub fn setup_controls(
+    mut commands: Commands,
+){
+    let bindings = keybindings::load(KEYBINDINGS_FILE).unwrap_or_else(|e| {
+        println!("{}, using the default keybindings", e);
+        keybindings::default_bindings()
+    });
+    commands.spawn(Controls{
+        bindings,
+        pressed: HashSet::new(),
+        justPressed: HashSet::new(),
+        lastModified: keybindings::modified(KEYBINDINGS_FILE),
+        lastCheck: 0.,
+        triggered: Vec::new(),
+        captured: false,
+    });
+}
+
+// actions enfoncées à cette frame, d'après les raccourcis ; keybindings.ron est rechargé quand il change
+pub fn update_controls(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepad_input: Res<ButtonInput<GamepadButton>>,
+    time: Res<Time>,
+    mut controls_query: Query<&mut Controls>,
+){
+    let mut controls = controls_query.single_mut();
+    let now = time.elapsed_seconds();
+    if now - controls.lastCheck > 1. {
+        controls.lastCheck = now;
+        let modified = keybindings::modified(KEYBINDINGS_FILE);
+        if modified != controls.lastModified {
+            controls.lastModified = modified;
+            match keybindings::load(KEYBINDINGS_FILE) {
+                Ok(bindings) => {
+                    for (action, list) in bindings{
+                        controls.rebind(action, list);
+                    }
+                    println!("Reloaded {}", KEYBINDINGS_FILE);
+                }
+                Err(e) => println!("{}", e),
+            }
+        }
+    }
+
+    let ctrl = keyboard_input.pressed(KeyCode::ControlLeft) || keyboard_input.pressed(KeyCode::ControlRight);
+    let shift = keyboard_input.pressed(KeyCode::ShiftLeft) || keyboard_input.pressed(KeyCode::ShiftRight);
+    let alt = keyboard_input.pressed(KeyCode::AltLeft) || keyboard_input.pressed(KeyCode::AltRight);
+    let mut pressed: HashSet<Action> = HashSet::new();
+    let mut justPressed: HashSet<Action> = HashSet::new();
+    for (action, list) in controls.bindings.iter(){
+        if controls.captured {
+            break;
+        }
+        for binding in list{
+            let (down, just) = match binding.input {
+                // les modificateurs doivent être exactement ceux du raccourci : Ctrl+S ne déclenche pas S
+                keybindings::Input::Key(key) => {
+                    let modifiers = binding.ctrl == ctrl && binding.shift == shift && binding.alt == alt;
+                    (modifiers && keyboard_input.pressed(key), modifiers && keyboard_input.just_pressed(key))
+                }
+                keybindings::Input::Gamepad(button) => (
+                    gamepad_input.get_pressed().any(|b| b.button_type == button),
+                    gamepad_input.get_just_pressed().any(|b| b.button_type == button),
+                ),
+            };
+            if down {
+                pressed.insert(*action);
+            }
+            if just {
+                justPressed.insert(*action);
+            }
+        }
+    }
+    for action in std::mem::take(&mut controls.triggered){
+        pressed.insert(action);
+        justPressed.insert(action);
+    }
+    controls.pressed = pressed;
+    controls.justPressed = justPressed;
+}
+
+pub fn setup_help_overlay(
+    mut commands: Commands,
+    camera_query: Query<Entity, With<MainCamera>>,
+){
+    commands.spawn((
+        NodeBundle{
+            style: Style{
+                position_type: PositionType::Absolute,
+                left: Val::Px(20.),
+                top: Val::Px(20.),
+                column_gap: Val::Px(40.),
+                padding: UiRect::all(Val::Px(12.)),
+                display: Display::None,
+                ..default()
+            },
+            background_color: BackgroundColor(Color::srgba(0., 0., 0., 0.85)),
+            ..default()
+        },
+        TargetCamera(camera_query.single()),
+        HelpOverlay{
+            visible: false,
+        },
+    )).with_children(|parent| {
+        for i in 0..2 {
+            parent.spawn((
+                TextBundle::from_section("", TextStyle{ font_size: 15., ..default() }),
+                HelpColumn(i),
+            ));
+        }
+    });
+}
+
+// F1 : afficher / cacher l'aide, construite à partir des raccourcis actuels
+pub fn toggle_help(
+    controls_query: Query<&Controls>,
+    mut help_query: Query<(&mut HelpOverlay, &mut Style)>,
+    mut columns_query: Query<(&HelpColumn, &mut Text)>,
+){
+    let controls = controls_query.single();
+    let (mut help, mut style) = help_query.single_mut();
+    let close = help.visible && controls.just_pressed(Action::Cancel);
+    if !controls.just_pressed(Action::Help) && !close {
+        return;
+    }
+    help.visible = !help.visible;
+    style.display = if help.visible { Display::Flex } else { Display::None };
+    if !help.visible {
+        return;
+    }
+    let lines = palette::help_lines(&controls.bindings);
+    let half = (lines.len() + 1) / 2;
+    for (column, mut text) in columns_query.iter_mut(){
+        let part = if column.0 == 0 { &lines[..half] } else { &lines[half..] };
+        text.sections[0].value = part.join("\n");
+    }
+}
+
+pub fn setup_command_palette(
+    mut commands: Commands,
+    camera_query: Query<Entity, With<MainCamera>>,
+){
+    commands.spawn((
+        TextBundle::from_section("", TextStyle{ font_size: 18., ..default() })
+            .with_style(Style{
+                position_type: PositionType::Absolute,
+                left: Val::Percent(30.),
+                top: Val::Px(60.),
+                min_width: Val::Percent(40.),
+                padding: UiRect::all(Val::Px(10.)),
+                display: Display::None,
+                ..default()
+            })
+            .with_background_color(Color::srgba(0., 0., 0., 0.9)),
+        TargetCamera(camera_query.single()),
+        CommandPalette{
+            open: false,
+            query: String::new(),
+            selected: 0,
+            entries: Vec::new(),
+        },
+    ));
+}
+
+// Ctrl+P : taper pour filtrer les actions et les motifs, flèches pour choisir, Entrée pour lancer, Échap pour fermer.
+// Les actions choisies passent par Controls, comme si leur touche avait été pressée.
+pub fn command_palette(
+    mut controls_query: Query<&mut Controls>,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut palette_query: Query<(&mut CommandPalette, &mut Text, &mut Style)>,
+    mut tilemap_query: Query<&mut TileMap>,
+    mut stamp_query: Query<&mut Stamp>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+    settings: Res<Settings>,
+){
+    let mut controls = controls_query.single_mut();
+    let (mut palette, mut text, mut style) = palette_query.single_mut();
+    let palette = &mut *palette;
+    if !palette.open {
+        if controls.just_pressed(Action::CommandPalette) {
+            palette.open = true;
+            palette.query.clear();
+            palette.selected = 0;
+            palette.entries = palette::entries(&controls.bindings, PATTERNS_DIR);
+            controls.captured = true;
+            style.display = Display::Flex;
+            // la touche qui vient d'ouvrir la palette n'est pas tapée dans la recherche
+            keyboard_events.clear();
+        } else {
+            keyboard_events.clear();
+            return;
+        }
+    }
+
+    let mut run: Option<usize> = None;
+    let mut close = false;
+    let results = palette::search(&palette.entries, &palette.query);
+    for event in keyboard_events.read(){
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        match event.key_code {
+            KeyCode::Escape => close = true,
+            KeyCode::Enter | KeyCode::NumpadEnter => {
+                run = results.get(palette.selected).copied();
+                close = true;
+            }
+            KeyCode::ArrowUp => palette.selected = palette.selected.saturating_sub(1),
+            KeyCode::ArrowDown => palette.selected += 1,
+            KeyCode::Backspace => {
+                palette.query.pop();
+                palette.selected = 0;
+            }
+            _ => {
+                // texte selon la disposition du clavier
+                if let Key::Character(c) = &event.logical_key {
+                    palette.query.extend(c.chars().filter(|c| !c.is_control()));
+                    palette.selected = 0;
+                }
+            }
+        }
+    }
+
+    if let Some(index) = run {
+        match &palette.entries[index].command {
+            palette::Command::Action(action) => controls.trigger(*action),
+            palette::Command::Pattern(path) => match rle::read_file(path, &settings.import_settings(false)) {
+                Ok(pattern) => start_stamp(pattern, &mut tilemap_query.single_mut(), &mut stamp_query.single_mut(), &mut window_query.single_mut()),
+                Err(e) => println!("could not open {}: {}", path, e),
+            },
+        }
+    }
+    if close {
+        palette.open = false;
+        palette.entries.clear();
+        controls.captured = false;
+        style.display = Display::None;
+        return;
+    }
+
+    let results = palette::search(&palette.entries, &palette.query);
+    palette.selected = palette.selected.min(results.len().saturating_sub(1));
+    let mut lines = vec![format!("> {}_", palette.query)];
+    let first = palette.selected.saturating_sub(palette::MAX_RESULTS - 1);
+    for (i, index) in results.iter().enumerate().skip(first).take(palette::MAX_RESULTS){
+        let marker = if i == palette.selected { "> " } else { "   " };
+        lines.push(format!("{}{}", marker, palette.entries[*index].label));
+    }
+    if results.is_empty() {
+        lines.push(String::from("   no match"));
+    }
+    text.sections[0].value = lines.join("\n");
+}
+
+// settings.ron est relu quand il change ; la fenêtre n'est modifiée que si sa taille ou la synchronisation verticale changent
+pub fn reload_settings(
+    mut settings: ResMut<Settings>,
+    mut source: ResMut<SettingsSource>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    time: Res<Time>,
+){
+    let now = time.elapsed_seconds();
+    if now - source.lastCheck < 1. {
+        return;
+    }
+    source.lastCheck = now;
+    let modified = settings::modified(&source.path);
+    if modified == source.lastModified {
+        return;
+    }
+    source.lastModified = modified;
+    let loaded = match Settings::load(&source.path, &source.overrides) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            println!("{}, keeping the current settings", e);
+            return;
+        }
+    };
+    println!("Reloaded {}", source.path);
+    let mut window = windows.single_mut();
+    if loaded.windowWidth != settings.windowWidth || loaded.windowHeight != settings.windowHeight {
+        window.resolution.set(loaded.windowWidth, loaded.windowHeight);
+    }
+    if loaded.vsync != settings.vsync {
+        window.present_mode = if loaded.vsync { PresentMode::AutoVsync } else { PresentMode::AutoNoVsync };
+    }
+    loaded.apply();
+    *settings = loaded;
+}
+
+fn clear_universe(tileMap: &mut TileMap, inTileMapRemove: &mut Vec<Entity>){
+    let tileMap = &mut *tileMap;
+    for map in [&mut tileMap.current_state, &mut tileMap.stable_current_state]{
+        inTileMapRemove.extend(map.drain().filter_map(|(_, (entity, _, _))| entity));
+    }
+    tileMap.ants.clear();
+    tileMap.macrocell = None;
+    tileMap.row1D = None;
+}
+
+fn run_remote_command(command: remote::Command, tileMap: &mut TileMap, settings: &Settings, inTileMapRemove: &mut Vec<Entity>) -> Result<Value, String>{
+    let lenia = tileMap.rule.lenia.is_some();
+    match command {
+        remote::Command::LoadPattern{ path, rle: text, x, y } => {
+            let pattern = match (path, text) {
+                (Some(path), None) => rle::read_file(&path, &settings.import_settings(false))?,
+                (None, Some(text)) => rle::read_any(&text)?,
+                _ => return Err(String::from("expected either path or rle")),
+            };
+            let rule = match &pattern.rule {
+                Some(rulestring) => Some(Rule::parse(rulestring)?),
+                None => None,
+            };
+            // tout est vérifié avant de toucher à l'univers : un chargement refusé ne l'efface pas
+            if rule.as_ref().map_or(lenia, |r| r.lenia.is_some()) {
+                return Err(String::from("patterns cannot be loaded with Lenia"));
+            }
+            clear_universe(tileMap, inTileMapRemove);
+            if let Some(rule) = rule {
+                change_rule(tileMap, rule, inTileMapRemove);
+            }
+            let cells: Vec<(uVec3, u8)> = pattern.cells.into_iter().filter(|(_, s)| *s != 0 && *s < tileMap.rule.states).collect();
+            for (pos, state) in cells.iter(){
+                tileMap.current_state.insert(uVec3::new(pos.x + x, pos.y + y, pos.z), (None, update_count_limit(), *state));
+            }
+            Ok(json!({ "cells": cells.len(), "rule": tileMap.rule.to_string() }))
+        }
+        remote::Command::SetCell{ x, y, state } => {
+            if lenia {
+                return Err(String::from("cells cannot be set with Lenia"));
+            }
+            if state >= tileMap.rule.states {
+                return Err(format!("state {} out of range for {} ({} states)", state, tileMap.rule.to_string(), tileMap.rule.states));
+            }
+            let layer = tileMap.layer;
+            draw_cell(tileMap, uVec3::new(x, y, layer), state, inTileMapRemove);
+            Ok(Value::Null)
+        }
+        remote::Command::Step{ n } => {
+            if lenia {
+                return Err(String::from("Lenia cannot be stepped remotely"));
+            }
+            if n > remote::MAX_STEPS {
+                return Err(format!("at most {} generations per step", remote::MAX_STEPS));
+            }
+            // les générations sont calculées par remote_control, la réponse part quand elles sont finies
+            Ok(json!(n))
+        }
+        remote::Command::Run => {
+            tileMap.running = true;
+            Ok(Value::Null)
+        }
+        remote::Command::Pause => {
+            tileMap.running = false;
+            Ok(Value::Null)
+        }
+        remote::Command::Clear => {
+            clear_universe(tileMap, inTileMapRemove);
+            Ok(Value::Null)
+        }
+        remote::Command::GetPopulation => {
+            if lenia {
+                return Err(String::from("Lenia has no discrete population"));
+            }
+            Ok(json!(living_cells(tileMap).len()))
+        }
+        remote::Command::GetCells{ x0, y0, x1, y1 } => {
+            let (minX, maxX) = (x0.min(x1), x0.max(x1));
+            let (minY, maxY) = (y0.min(y1), y0.max(y1));
+            let mut cells: Vec<(uVec3, u8)> = living_cells(tileMap).into_iter()
+                .filter(|(p, _)| p.z == tileMap.layer && p.x >= minX && p.x <= maxX && p.y >= minY && p.y <= maxY)
+                .collect();
+            if cells.len() > remote::MAX_CELLS {
+                return Err(format!("more than {} cells in the rectangle", remote::MAX_CELLS));
+            }
+            cells.sort_by_key(|(p, _)| (-p.y, p.x));
+            Ok(Value::Array(cells.into_iter().map(|(p, s)| json!([p.x, p.y, s])).collect()))
+        }
+        remote::Command::SetRule{ rule } => {
+            let rule = Rule::parse(&rule)?;
+            change_rule(tileMap, rule, inTileMapRemove);
+            Ok(json!(tileMap.rule.to_string()))
+        }
+    }
+}
+
+// requêtes reçues par le contrôle à distance (--remote PORT), exécutées dans l'ordre d'arrivée
+pub fn remote_control(
+    remote: Option<ResMut<remote::RemoteControl>>,
+    mut tilemap_query: Query<&mut TileMap>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    settings: Res<Settings>,
+    mut commands: Commands,
+){
+    let Some(mut remote) = remote else {
+        return;
+    };
+    if remote.stepping.is_none() && remote.requests.is_empty() {
+        return;
+    }
+    let mut tileMap = tilemap_query.single_mut();
+    let mut inTileMapRemove: Vec<Entity> = Vec::new();
+    let start = std::time::Instant::now();
+    loop {
+        // un "step" en cours passe avant les requêtes suivantes, dans la limite de STEP_BUDGET par frame
+        if let Some((remaining, reply)) = remote.stepping.take() {
+            // au moins une génération par frame, pour avancer même si la frame est déjà chargée
+            let mut remaining = remaining;
+            let mut first = true;
+            while remaining > 0 && (first || start.elapsed().as_secs_f32() < remote::STEP_BUDGET) {
+                step_generation(&mut tileMap, &mut inTileMapRemove);
+                remaining -= 1;
+                first = false;
+            }
+            if remaining > 0 {
+                remote.stepping = Some((remaining, reply));
+                break;
+            }
+            let _ = reply.send(Ok(json!({ "population": living_cells(&tileMap).len() })));
+        }
+        let Ok(request) = remote.requests.try_recv() else {
+            break;
+        };
+        let step = matches!(request.command, remote::Command::Step{ .. });
+        match run_remote_command(request.command, &mut tileMap, &settings, &mut inTileMapRemove) {
+            Ok(Value::Number(n)) if step => remote.stepping = Some((n.as_u64().unwrap_or(0) as u32, request.reply)),
+            // le client a pu se déconnecter entre temps
+            result => {
+                let _ = request.reply.send(result);
+            }
+        }
+    }
+    for entity in inTileMapRemove{
+        commands.entity(entity).remove::<InTileMap>();
+    }
+    let title = window_title(&tileMap);
+    let mut window = windows.single_mut();
+    if window.title != title {
+        window.title = title;
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn draw_next_to_stable_cell_after_lod(){
+        let mut tileMap = TileMap::new(Rule::life());
+        let block = [(0, 0), (1, 0), (0, 1), (1, 1)];
+        for (i, (x, y)) in block.into_iter().enumerate(){
+            tileMap.stable_current_state.insert(uVec3::new(x, y, 0), (Some(Entity::from_raw(i as u32)), 0, 1));
+        }
+        let mut inTileMapRemove: Vec<Entity> = Vec::new();
+        release_tiles(&mut tileMap, &mut inTileMapRemove);
+        assert_eq!(inTileMapRemove.len(), 4);
+
+        draw_cell(&mut tileMap, uVec3::new(2, 0, 0), 1, &mut inTileMapRemove);
+        assert!(tileMap.stable_current_state.is_empty());
+        assert_eq!(tileMap.current_state.len(), 5);
+        assert!(tileMap.current_state.values().all(|(entity, _, state)| entity.is_none() && *state == 1));
+    }
+
+    #[test]
+    fn erase_stable_cell_after_lod(){
+        let mut tileMap = TileMap::new(Rule::life());
+        let block = [(0, 0), (1, 0), (0, 1), (1, 1)];
+        for (i, (x, y)) in block.into_iter().enumerate(){
+            tileMap.stable_current_state.insert(uVec3::new(x, y, 0), (Some(Entity::from_raw(i as u32)), 0, 1));
+        }
+        let mut inTileMapRemove: Vec<Entity> = Vec::new();
+        release_tiles(&mut tileMap, &mut inTileMapRemove);
+        inTileMapRemove.clear();
+
+        draw_cell(&mut tileMap, uVec3::new(0, 0, 0), 0, &mut inTileMapRemove);
+        assert!(inTileMapRemove.is_empty());
+        assert!(tileMap.stable_current_state.is_empty());
+        assert!(!tileMap.current_state.contains_key(&uVec3::new(0, 0, 0)));
+        assert_eq!(tileMap.current_state.len(), 3);
+
+        // une cellule qui a encore sa tuile la libère
+        tileMap.current_state.insert(uVec3::new(5, 5, 0), (Some(Entity::from_raw(9)), 0, 1));
+        draw_cell(&mut tileMap, uVec3::new(5, 5, 0), 0, &mut inTileMapRemove);
+        assert_eq!(inTileMapRemove, vec![Entity::from_raw(9)]);
+    }
+}
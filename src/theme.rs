@@ -0,0 +1,30 @@
+use bevy::prelude::Color;
+
+// thèmes de couleurs (Ctrl+T pour passer au suivant) : fond, cellules vivantes, grille et axes
+pub struct Theme{
+    pub name: &'static str,
+    pub background: Color,
+    pub cell: Color,
+    pub grid: Color,        // une ligne par cellule
+    pub gridMajor: Color,   // toutes les 10 et 100 cellules
+    pub axes: Color,
+}
+
+pub const THEMES: [Theme; 2] = [
+    Theme{
+        name: "dark",
+        background: Color::srgb(43. / 255., 44. / 255., 47. / 255.),
+        cell: Color::linear_rgba(1., 1., 1., 1.),
+        grid: Color::srgba(1., 1., 1., 0.07),
+        gridMajor: Color::srgba(1., 1., 1., 0.18),
+        axes: Color::srgba(1., 0.35, 0.3, 0.7),
+    },
+    Theme{
+        name: "light",
+        background: Color::srgb(0.95, 0.95, 0.92),
+        cell: Color::srgb(0.1, 0.1, 0.12),
+        grid: Color::srgba(0., 0., 0., 0.08),
+        gridMajor: Color::srgba(0., 0., 0., 0.22),
+        axes: Color::srgba(0.8, 0.1, 0.1, 0.7),
+    },
+];
@@ -0,0 +1,125 @@
+use bevy::prelude::Entity;
+use bevy::utils::HashMap;
+use crate::components::Ant;
+use crate::uVec3::uVec3;
+
+// forme de l'univers (--topology) : plan infini, rectangle borné (les cellules qui en sortent meurent)
+// ou tore (les bords opposés se touchent). Le rectangle est centré sur l'origine, en coordonnées de cellules ;
+// avec le réseau triangulaire, des tailles paires gardent l'orientation des triangles d'un bord à l'autre.
+// Seules les couches x / y sont concernées en Life 3D, et les règles 1D gardent un plan infini.
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Topology{
+    Plane,
+    Bounded{ width: i32, height: i32 },
+    Torus{ width: i32, height: i32 },
+}
+
+pub const MAX_SIZE: i32 = 1_000_000;
+
+impl Topology{
+    // "plane", "bounded:WxH" ou "torus:WxH"
+    pub fn parse(text: &str) -> Result<Topology, String>{
+        if text == "plane" {
+            return Ok(Topology::Plane);
+        }
+        let invalid = || format!("invalid topology '{}': expected plane, bounded:WIDTHxHEIGHT or torus:WIDTHxHEIGHT", text);
+        let (kind, size) = text.split_once(':').ok_or_else(invalid)?;
+        let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+        let (width, height) = (width.trim().parse::<i32>().map_err(|_| invalid())?, height.trim().parse::<i32>().map_err(|_| invalid())?);
+        if width < 1 || height < 1 || width > MAX_SIZE || height > MAX_SIZE {
+            return Err(format!("invalid topology '{}': sizes must be between 1 and {}", text, MAX_SIZE));
+        }
+        match kind {
+            "bounded" => Ok(Topology::Bounded{ width, height }),
+            "torus" => Ok(Topology::Torus{ width, height }),
+            _ => Err(invalid()),
+        }
+    }
+
+    // coins (min, max) inclus du rectangle
+    pub fn bounds(&self) -> Option<((i32, i32), (i32, i32))>{
+        match self {
+            Topology::Plane => None,
+            Topology::Bounded{ width, height } | Topology::Torus{ width, height } => {
+                let (x0, y0) = (-(width / 2), -(height / 2));
+                Some(((x0, y0), (x0 + width - 1, y0 + height - 1)))
+            }
+        }
+    }
+
+    pub fn contains(&self, pos: &uVec3) -> bool{
+        self.bounds().map_or(true, |((x0, y0), (x1, y1))| pos.x >= x0 && pos.x <= x1 && pos.y >= y0 && pos.y <= y1)
+    }
+
+    // tore : les cellules stables à moins de `range` d'un bord repassent dans la map active,
+    // un changement de l'autre côté du bord ne les réveillerait pas
+    pub fn wake_edges(&self, range: i32, tileMap: &mut HashMap<uVec3, (Option<Entity>, i32, u8)>, stableTileMap: &mut HashMap<uVec3, (Option<Entity>, i32, u8)>, counter: i32){
+        let Some(((x0, y0), (x1, y1))) = self.bounds() else {
+            return;
+        };
+        let near: Vec<uVec3> = stableTileMap.keys()
+            .filter(|pos| pos.x < x0 + range || pos.x > x1 - range || pos.y < y0 + range || pos.y > y1 - range)
+            .copied()
+            .collect();
+        for pos in near{
+            if let Some((entity, _, state)) = stableTileMap.remove(&pos) {
+                tileMap.insert(pos, (entity, counter, state));
+            }
+        }
+    }
+
+    // tore : copies des cellules à moins de `range` d'un bord, de l'autre côté, pour que les voisins
+    // se voient à travers les bords pendant la génération ; elles sont retirées ensuite par clip
+    pub fn add_ghosts(&self, range: i32, tileMap: &mut HashMap<uVec3, (Option<Entity>, i32, u8)>){
+        let Topology::Torus{ width, height } = *self else {
+            return;
+        };
+        let ((x0, y0), (x1, y1)) = self.bounds().unwrap();
+        let mut ghosts: Vec<(uVec3, (Option<Entity>, i32, u8))> = Vec::new();
+        for (pos, (_, counter, state)) in tileMap.iter(){
+            let dxs: Vec<i32> = [(0, true), (width, pos.x > x1 - range), (-width, pos.x < x0 + range)].into_iter().filter(|(_, near)| *near).map(|(d, _)| d).collect();
+            let dys: Vec<i32> = [(0, true), (height, pos.y > y1 - range), (-height, pos.y < y0 + range)].into_iter().filter(|(_, near)| *near).map(|(d, _)| d).collect();
+            for dx in dxs.iter(){
+                for dy in dys.iter(){
+                    if *dx != 0 || *dy != 0 {
+                        ghosts.push((uVec3::new(pos.x - dx, pos.y - dy, pos.z), (None, *counter, *state)));
+                    }
+                }
+            }
+        }
+        for (pos, cell) in ghosts{
+            tileMap.entry(pos).or_insert(cell);
+        }
+    }
+
+    // retire les cellules hors du rectangle (entités à délier dans inTileMapRemove)
+    pub fn clip(&self, tileMap: &mut HashMap<uVec3, (Option<Entity>, i32, u8)>, inTileMapRemove: &mut Vec<Entity>){
+        if *self == Topology::Plane {
+            return;
+        }
+        tileMap.retain(|pos, (entity, _, _)| {
+            if self.contains(pos) {
+                return true;
+            }
+            if let Some(e) = entity {
+                inTileMapRemove.push(*e);
+            }
+            false
+        });
+    }
+
+    // les turmites passent de l'autre côté du tore, ou disparaissent au bord d'un univers borné
+    pub fn move_ants(&self, ants: &mut Vec<Ant>){
+        match *self {
+            Topology::Plane => {}
+            Topology::Bounded{ .. } => ants.retain(|ant| self.contains(&ant.pos)),
+            Topology::Torus{ width, height } => {
+                let ((x0, y0), _) = self.bounds().unwrap();
+                for ant in ants.iter_mut(){
+                    ant.pos = uVec3::new(x0 + (ant.pos.x - x0).rem_euclid(width), y0 + (ant.pos.y - y0).rem_euclid(height), ant.pos.z);
+                }
+            }
+        }
+    }
+}
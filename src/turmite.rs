@@ -0,0 +1,122 @@
+use bevy::prelude::Entity;
+use bevy::utils::HashMap;
+use crate::components::Ant;
+use crate::uVec3::uVec3;
+use crate::settings::update_count_limit;
+
+// fourmi de Langton et turmites sur la grille carrée.
+// "RL", "LLRR" ... : une lettre par couleur de case (R droite, L gauche, N tout droit, U demi-tour),
+// la case passe à la couleur suivante. Sinon table de Golly {{{écrire, tourner, état suivant}, ...}, ...}
+// indexée par état de la turmite puis couleur de la case, tourner : 1 tout droit, 2 droite, 4 demi-tour, 8 gauche.
+
+// directions dans le sens horaire, en commençant par le haut
+pub const DIRECTIONS: [(i32, i32); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+
+pub struct Turmite{
+    pub name: String,
+    pub colors: u8,
+    // [état][couleur] -> (couleur écrite, quarts de tour dans le sens horaire, état suivant)
+    table: Vec<Vec<(u8, u8, usize)>>,
+}
+
+impl PartialEq for Turmite{
+    fn eq(&self, other: &Turmite) -> bool{
+        self.name == other.name
+    }
+}
+
+impl Turmite{
+    pub fn isTurmite(rulestring: &str) -> bool{
+        (rulestring.len() >= 2 && rulestring.chars().all(|c| "LRNU".contains(c))) || rulestring.starts_with("{{")
+    }
+
+    pub fn parse(rulestring: &str) -> Result<Turmite, String>{
+        if rulestring.starts_with('{') {
+            return Turmite::parse_table(rulestring);
+        }
+        if rulestring.len() > 255 {
+            return Err(format!("too many colors in '{}'", rulestring));
+        }
+        let colors = rulestring.len() as u8;
+        let turns: Vec<(u8, u8, usize)> = rulestring.chars().enumerate().map(|(c, letter)| {
+            let turn = match letter {
+                'N' => 0,
+                'R' => 1,
+                'U' => 2,
+                _ => 3,
+            };
+            (((c + 1) % colors as usize) as u8, turn, 0)
+        }).collect();
+        Ok(Turmite{
+            name: rulestring.to_string(),
+            colors,
+            table: vec![turns],
+        })
+    }
+
+    fn parse_table(rulestring: &str) -> Result<Turmite, String>{
+        let compact: String = rulestring.chars().filter(|c| !c.is_whitespace()).collect();
+        let body = compact.strip_prefix("{{{").and_then(|s| s.strip_suffix("}}}"))
+            .ok_or(format!("invalid turmite '{}': expected {{{{{{...}}}}}}", rulestring))?;
+        let mut table: Vec<Vec<(u8, u8, usize)>> = Vec::new();
+        for state in body.split("}},{{"){
+            let mut row: Vec<(u8, u8, usize)> = Vec::new();
+            for entry in state.split("},{"){
+                let values: Vec<usize> = entry.split(',').map(|v| v.parse::<usize>()).collect::<Result<_, _>>()
+                    .map_err(|_| format!("invalid turmite entry '{{{}}}'", entry))?;
+                if values.len() != 3 {
+                    return Err(format!("invalid turmite entry '{{{}}}': expected 3 values", entry));
+                }
+                let turn = match values[1] {
+                    1 => 0,
+                    2 => 1,
+                    4 => 2,
+                    8 => 3,
+                    t => return Err(format!("invalid turn {} (1, 2, 4 or 8)", t)),
+                };
+                row.push((values[0].min(255) as u8, turn, values[2]));
+            }
+            table.push(row);
+        }
+        let colors = table[0].len();
+        if table.iter().any(|row| row.len() != colors) {
+            return Err(String::from("every turmite state needs the same number of colors"));
+        }
+        if colors < 2 || colors > 255 || table.iter().flatten().any(|(write, _, next)| *write as usize >= colors || *next >= table.len()) {
+            return Err(format!("invalid turmite '{}': color or state out of range", rulestring));
+        }
+        Ok(Turmite{
+            name: compact,
+            colors: colors as u8,
+            table,
+        })
+    }
+
+    // un pas pour chaque turmite : lire la case, écrire, tourner, avancer
+    pub fn step(
+        &self,
+        ants: &mut Vec<Ant>,
+        tileMap: &mut HashMap<uVec3, (Option<Entity>, i32, u8)>,
+        stableTileMap: &mut HashMap<uVec3, (Option<Entity>, i32, u8)>,
+        inTileMapRemove: &mut Vec<Entity>
+    ){
+        for ant in ants.iter_mut(){
+            let stable = stableTileMap.remove(&ant.pos);
+            let cell = tileMap.remove(&ant.pos).or(stable);
+            let color = cell.map_or(0, |(_, _, s)| s.min(self.colors - 1));
+            let (write, turn, next) = self.table[ant.state.min(self.table.len() - 1)][color as usize];
+            let entity = cell.and_then(|(e, _, _)| e);
+            if write == 0 {
+                if let Some(e) = entity {
+                    inTileMapRemove.push(e);
+                }
+            } else {
+                tileMap.insert(ant.pos, (entity, update_count_limit(), write));
+            }
+            ant.direction = (ant.direction + turn) % 4;
+            ant.state = next;
+            let (dx, dy) = DIRECTIONS[ant.direction as usize];
+            ant.pos = uVec3::new(ant.pos.x + dx, ant.pos.y + dy, ant.pos.z);
+        }
+    }
+}